@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX IF EXISTS tickets_screening_seat_key;
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+-- a seat can only ever be sold once for a given screening
+CREATE UNIQUE INDEX IF NOT EXISTS tickets_screening_seat_key ON tickets (theatre_screening_id, seat_row, seat_column);
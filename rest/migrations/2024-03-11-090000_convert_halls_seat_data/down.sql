@@ -0,0 +1,13 @@
+-- This file should undo anything in `up.sql`
+
+UPDATE halls SET seat_data = jsonb_build_object('data', COALESCE((
+    SELECT jsonb_agg(COALESCE((
+        SELECT jsonb_agg(
+            CASE WHEN cell = '"Aisle"'::JSONB THEN 0 ELSE 1 END
+            ORDER BY cell_idx
+        )
+        FROM jsonb_array_elements(seat_row) WITH ORDINALITY AS cells(cell, cell_idx)
+    ), '[]'::JSONB) ORDER BY row_idx)
+    FROM jsonb_array_elements(seat_data->'rows') WITH ORDINALITY AS seat_rows(seat_row, row_idx)
+), '[]'::JSONB))
+WHERE seat_data ? 'rows';
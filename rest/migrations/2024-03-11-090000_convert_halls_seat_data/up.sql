@@ -0,0 +1,15 @@
+-- Your SQL goes here
+
+-- converts the old `{"data": [[u16]]}` seat grids to `{"rows": [[SeatKind]]}`,
+-- cells containing 0 had no seat in them, every other cell was a regular seat
+UPDATE halls SET seat_data = jsonb_build_object('rows', COALESCE((
+    SELECT jsonb_agg(COALESCE((
+        SELECT jsonb_agg(
+            CASE WHEN cell::INTEGER = 0 THEN '"Aisle"'::JSONB ELSE '"Standard"'::JSONB END
+            ORDER BY cell_idx
+        )
+        FROM jsonb_array_elements(seat_row) WITH ORDINALITY AS cells(cell, cell_idx)
+    ), '[]'::JSONB) ORDER BY row_idx)
+    FROM jsonb_array_elements(seat_data->'data') WITH ORDINALITY AS seat_rows(seat_row, row_idx)
+), '[]'::JSONB))
+WHERE seat_data ? 'data';
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+
+CREATE TABLE IF NOT EXISTS seat_holds (
+    "id" UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    theatre_screening_id UUID NOT NULL REFERENCES theatre_screenings("id"),
+    "user_id" UUID NOT NULL REFERENCES users("id"),
+    seat_row INTEGER NOT NULL,
+    seat_column INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    expires_at TIMESTAMP NOT NULL,
+
+    UNIQUE(theatre_screening_id, seat_row, seat_column)
+);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE halls DROP COLUMN IF EXISTS cleaning_buffer_minutes;
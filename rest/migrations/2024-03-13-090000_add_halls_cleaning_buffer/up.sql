@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+ALTER TABLE halls ADD COLUMN IF NOT EXISTS cleaning_buffer_minutes INTEGER NOT NULL DEFAULT 15
+    CHECK (cleaning_buffer_minutes >= 0);
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE theatre_screenings DROP COLUMN IF EXISTS schedule_id;
+
+DROP TABLE IF EXISTS screening_schedules;
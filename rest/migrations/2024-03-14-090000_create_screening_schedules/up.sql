@@ -0,0 +1,22 @@
+-- Your SQL goes here
+
+CREATE TABLE IF NOT EXISTS screening_schedules (
+    "id" UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    movie_id UUID NOT NULL REFERENCES movies("id"),
+    theatre_id UUID NOT NULL REFERENCES theatres("id"),
+    hall_id UUID NOT NULL REFERENCES halls("id"),
+    subtitles_language_id UUID REFERENCES languages("id"),
+    audio_language_id UUID NOT NULL REFERENCES languages("id"),
+    is_3d BOOL NOT NULL DEFAULT FALSE,
+    -- ISO 8601 weekday numbers, 1 being Monday
+    weekdays INTEGER[] NOT NULL,
+    times_of_day TIME[] NOT NULL,
+    start_date DATE NOT NULL,
+    end_date DATE NOT NULL,
+    is_deleted BOOL NOT NULL DEFAULT FALSE,
+    CHECK (array_position(weekdays, NULL) IS NULL),
+    CHECK (array_position(times_of_day, NULL) IS NULL),
+    CHECK (end_date >= start_date)
+);
+
+ALTER TABLE theatre_screenings ADD COLUMN IF NOT EXISTS schedule_id UUID REFERENCES screening_schedules("id");
@@ -0,0 +1,13 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE theatre_screenings ALTER COLUMN "status" DROP DEFAULT;
+ALTER TABLE theatre_screenings ALTER COLUMN "status" TYPE INTEGER USING (
+    CASE "status"
+        WHEN 'started' THEN 2
+        WHEN 'finished' THEN 3
+        ELSE 0
+    END
+);
+ALTER TABLE theatre_screenings ALTER COLUMN "status" SET DEFAULT 0;
+
+DROP TYPE IF EXISTS screening_status;
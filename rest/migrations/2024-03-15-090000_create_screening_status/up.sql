@@ -0,0 +1,14 @@
+-- Your SQL goes here
+
+CREATE TYPE screening_status AS ENUM ('scheduled', 'on_sale', 'sold_out', 'started', 'finished', 'cancelled');
+
+-- the old integer states were never set, so the status is derived from when the
+-- screening starts: past ones are over and upcoming ones used to be bookable
+ALTER TABLE theatre_screenings ALTER COLUMN "status" DROP DEFAULT;
+ALTER TABLE theatre_screenings ALTER COLUMN "status" TYPE screening_status USING (
+    CASE
+        WHEN starting_time < NOW() AT TIME ZONE 'UTC' THEN 'finished'
+        ELSE 'on_sale'
+    END
+)::screening_status;
+ALTER TABLE theatre_screenings ALTER COLUMN "status" SET DEFAULT 'scheduled';
@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+
+DROP TABLE IF EXISTS refunds;
+
+DROP TYPE IF EXISTS refund_reason;
+
+DROP INDEX IF EXISTS tickets_screening_seat_key;
+CREATE UNIQUE INDEX IF NOT EXISTS tickets_screening_seat_key
+    ON tickets (theatre_screening_id, seat_row, seat_column);
+
+ALTER TABLE tickets DROP COLUMN IF EXISTS is_cancelled;
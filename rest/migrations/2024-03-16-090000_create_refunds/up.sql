@@ -0,0 +1,22 @@
+-- Your SQL goes here
+
+ALTER TABLE tickets ADD COLUMN IF NOT EXISTS is_cancelled BOOL NOT NULL DEFAULT FALSE;
+
+-- cancelled tickets give their seat back
+DROP INDEX IF EXISTS tickets_screening_seat_key;
+CREATE UNIQUE INDEX IF NOT EXISTS tickets_screening_seat_key
+    ON tickets (theatre_screening_id, seat_row, seat_column)
+    WHERE NOT is_cancelled;
+
+CREATE TYPE refund_reason AS ENUM ('screening_cancelled', 'ticket_cancelled');
+
+CREATE TABLE IF NOT EXISTS refunds (
+    "id" UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    ticket_id UUID NOT NULL UNIQUE REFERENCES tickets("id"),
+    "user_id" UUID NOT NULL REFERENCES users("id"),
+    amount FLOAT NOT NULL,
+    currency VARCHAR(3) NOT NULL,
+    reason refund_reason NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    settled_at TIMESTAMP
+);
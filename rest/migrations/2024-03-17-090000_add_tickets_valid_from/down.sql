@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE tickets ALTER COLUMN used DROP DEFAULT;
+ALTER TABLE tickets DROP COLUMN IF EXISTS valid_from;
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+
+ALTER TABLE tickets ADD COLUMN IF NOT EXISTS valid_from TIMESTAMP;
+
+-- existing tickets get the default grace periods (30 minutes before, 15 minutes after)
+UPDATE tickets
+SET valid_from = s.starting_time - INTERVAL '30 minutes',
+    expires_at = s.starting_time + m."length" * INTERVAL '1 minute' + INTERVAL '15 minutes'
+FROM theatre_screenings s
+JOIN movies m ON m."id" = s.movie_id
+WHERE s."id" = tickets.theatre_screening_id;
+
+ALTER TABLE tickets ALTER COLUMN valid_from SET NOT NULL;
+ALTER TABLE tickets ALTER COLUMN used SET DEFAULT FALSE;
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+
+CREATE TABLE IF NOT EXISTS ticket_check_ins (
+    "id" UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    ticket_id UUID NOT NULL REFERENCES tickets("id"),
+    checker_user_id UUID NOT NULL REFERENCES users("id"),
+    device_id VARCHAR(64) NOT NULL,
+    scanned_at TIMESTAMP NOT NULL,
+    synced_at TIMESTAMP NOT NULL DEFAULT now(),
+
+    -- a scan uploaded more than once is only recorded the first time
+    UNIQUE(ticket_id, device_id, scanned_at)
+);
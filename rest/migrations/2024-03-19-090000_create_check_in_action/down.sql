@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+
+DELETE FROM ticket_check_ins WHERE device_id IS NULL;
+ALTER TABLE ticket_check_ins ALTER COLUMN device_id SET NOT NULL;
+
+ALTER TABLE ticket_check_ins DROP COLUMN IF EXISTS "action";
+
+DROP TYPE IF EXISTS check_in_action;
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+
+CREATE TYPE check_in_action AS ENUM ('enter', 're_enter', 'undo');
+
+-- scans synced so far were all entries
+ALTER TABLE ticket_check_ins ADD COLUMN IF NOT EXISTS "action" check_in_action NOT NULL DEFAULT 'enter';
+ALTER TABLE ticket_check_ins ALTER COLUMN "action" DROP DEFAULT;
+
+-- check-ins done online don't necessarily come from a registered device
+ALTER TABLE ticket_check_ins ALTER COLUMN device_id DROP NOT NULL;
+
+-- `tickets.used` is kept as the state derived from the latest check-in event,
+-- tickets marked before this migration keep their state without any history
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE theatres DROP COLUMN IF EXISTS cancellation_cutoff_minutes;
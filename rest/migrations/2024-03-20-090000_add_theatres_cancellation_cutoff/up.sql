@@ -0,0 +1,5 @@
+-- Your SQL goes here
+
+-- customers can cancel their tickets up until this many minutes before the screening starts
+ALTER TABLE theatres ADD COLUMN IF NOT EXISTS cancellation_cutoff_minutes INT NOT NULL DEFAULT 60
+    CHECK (cancellation_cutoff_minutes >= 0);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+DROP TABLE IF EXISTS ticket_transfers;
+DROP TYPE IF EXISTS ticket_transfer_status;
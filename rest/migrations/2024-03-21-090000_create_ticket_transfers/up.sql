@@ -0,0 +1,18 @@
+-- Your SQL goes here
+
+CREATE TYPE ticket_transfer_status AS ENUM ('pending', 'accepted', 'declined', 'cancelled');
+
+CREATE TABLE IF NOT EXISTS ticket_transfers (
+    "id" UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    ticket_id UUID NOT NULL REFERENCES tickets("id"),
+    from_user_id UUID NOT NULL REFERENCES users("id"),
+    to_user_id UUID NOT NULL REFERENCES users("id"),
+    "status" ticket_transfer_status NOT NULL DEFAULT 'pending',
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    resolved_at TIMESTAMP
+);
+
+-- a ticket can only be offered to one user at a time
+CREATE UNIQUE INDEX IF NOT EXISTS ticket_transfers_pending_key
+    ON ticket_transfers (ticket_id)
+    WHERE "status" = 'pending';
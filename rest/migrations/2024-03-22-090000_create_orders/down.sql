@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE tickets DROP COLUMN IF EXISTS order_id;
+DROP TABLE IF EXISTS orders;
+DROP TYPE IF EXISTS order_status;
@@ -0,0 +1,16 @@
+-- Your SQL goes here
+
+CREATE TYPE order_status AS ENUM ('pending', 'confirmed', 'cancelled');
+
+CREATE TABLE IF NOT EXISTS orders (
+    "id" UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    "user_id" UUID NOT NULL REFERENCES users("id"),
+    theatre_screening_id UUID NOT NULL REFERENCES theatre_screenings("id"),
+    "status" order_status NOT NULL DEFAULT 'confirmed',
+    total_price FLOAT NOT NULL,
+    currency VARCHAR(3) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+-- tickets issued on their own (e.g. by staff) don't belong to any order
+ALTER TABLE tickets ADD COLUMN IF NOT EXISTS order_id UUID REFERENCES orders("id");
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+
+DROP TABLE IF EXISTS payments;
+DROP TYPE IF EXISTS payment_status;
+ALTER TABLE orders ALTER COLUMN "status" SET DEFAULT 'confirmed';
@@ -0,0 +1,20 @@
+-- Your SQL goes here
+
+CREATE TYPE payment_status AS ENUM ('requires_confirmation', 'succeeded', 'failed', 'cancelled');
+
+-- orders are paid for before their tickets become usable
+ALTER TABLE orders ALTER COLUMN "status" SET DEFAULT 'pending';
+
+CREATE TABLE IF NOT EXISTS payments (
+    "id" UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    order_id UUID NOT NULL UNIQUE REFERENCES orders("id"),
+    provider VARCHAR(32) NOT NULL,
+    intent_id VARCHAR(255) NOT NULL,
+    client_secret VARCHAR(255) NOT NULL,
+    amount FLOAT NOT NULL,
+    currency VARCHAR(3) NOT NULL,
+    "status" payment_status NOT NULL DEFAULT 'requires_confirmation',
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    updated_at TIMESTAMP NOT NULL DEFAULT now(),
+    UNIQUE (provider, intent_id)
+);
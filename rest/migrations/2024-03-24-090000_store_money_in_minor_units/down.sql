@@ -0,0 +1,31 @@
+-- This file should undo anything in `up.sql`
+
+CREATE FUNCTION pg_temp.currency_exponent(code VARCHAR) RETURNS INT AS $$
+    SELECT CASE
+        WHEN code IN ('BIF', 'CLP', 'DJF', 'GNF', 'ISK', 'JPY', 'KMF', 'KRW', 'PYG', 'RWF', 'UGX', 'UYI', 'VND', 'VUV', 'XAF', 'XOF', 'XPF') THEN 0
+        WHEN code IN ('BHD', 'IQD', 'JOD', 'KWD', 'LYD', 'OMR', 'TND') THEN 3
+        WHEN code IN ('CLF', 'UYW') THEN 4
+        ELSE 2
+    END
+$$ LANGUAGE SQL IMMUTABLE;
+
+ALTER TABLE payments
+    DROP CONSTRAINT IF EXISTS payments_currency_check,
+    ALTER COLUMN amount TYPE FLOAT
+        USING amount / POWER(10, pg_temp.currency_exponent(currency));
+
+ALTER TABLE refunds
+    DROP CONSTRAINT IF EXISTS refunds_currency_check,
+    ALTER COLUMN amount TYPE FLOAT
+        USING amount / POWER(10, pg_temp.currency_exponent(currency));
+
+ALTER TABLE orders
+    DROP CONSTRAINT IF EXISTS orders_currency_check,
+    ALTER COLUMN total_price TYPE FLOAT
+        USING total_price / POWER(10, pg_temp.currency_exponent(currency));
+
+ALTER TABLE ticket_types
+    DROP CONSTRAINT IF EXISTS ticket_types_price_check,
+    DROP CONSTRAINT IF EXISTS ticket_types_currency_check,
+    ALTER COLUMN price TYPE FLOAT
+        USING price / POWER(10, pg_temp.currency_exponent(currency));
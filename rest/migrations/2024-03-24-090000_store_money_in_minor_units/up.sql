@@ -0,0 +1,39 @@
+-- Your SQL goes here
+
+-- amounts are stored as integers in the minor units of their currency (e.g. cents),
+-- of which ISO 4217 currencies have 0, 2, 3 or 4
+CREATE FUNCTION pg_temp.currency_exponent(code VARCHAR) RETURNS INT AS $$
+    SELECT CASE
+        WHEN code IN ('BIF', 'CLP', 'DJF', 'GNF', 'ISK', 'JPY', 'KMF', 'KRW', 'PYG', 'RWF', 'UGX', 'UYI', 'VND', 'VUV', 'XAF', 'XOF', 'XPF') THEN 0
+        WHEN code IN ('BHD', 'IQD', 'JOD', 'KWD', 'LYD', 'OMR', 'TND') THEN 3
+        WHEN code IN ('CLF', 'UYW') THEN 4
+        ELSE 2
+    END
+$$ LANGUAGE SQL IMMUTABLE;
+
+-- currency codes are compared as they're stored, so they're kept upper-case
+UPDATE ticket_types SET currency = UPPER(currency);
+UPDATE orders SET currency = UPPER(currency);
+UPDATE refunds SET currency = UPPER(currency);
+UPDATE payments SET currency = UPPER(currency);
+
+ALTER TABLE ticket_types
+    ALTER COLUMN price TYPE BIGINT
+        USING ROUND(price::NUMERIC * POWER(10::NUMERIC, pg_temp.currency_exponent(currency)))::BIGINT,
+    ADD CONSTRAINT ticket_types_price_check CHECK (price >= 0),
+    ADD CONSTRAINT ticket_types_currency_check CHECK (currency ~ '^[A-Z]{3}$');
+
+ALTER TABLE orders
+    ALTER COLUMN total_price TYPE BIGINT
+        USING ROUND(total_price::NUMERIC * POWER(10::NUMERIC, pg_temp.currency_exponent(currency)))::BIGINT,
+    ADD CONSTRAINT orders_currency_check CHECK (currency ~ '^[A-Z]{3}$');
+
+ALTER TABLE refunds
+    ALTER COLUMN amount TYPE BIGINT
+        USING ROUND(amount::NUMERIC * POWER(10::NUMERIC, pg_temp.currency_exponent(currency)))::BIGINT,
+    ADD CONSTRAINT refunds_currency_check CHECK (currency ~ '^[A-Z]{3}$');
+
+ALTER TABLE payments
+    ALTER COLUMN amount TYPE BIGINT
+        USING ROUND(amount::NUMERIC * POWER(10::NUMERIC, pg_temp.currency_exponent(currency)))::BIGINT,
+    ADD CONSTRAINT payments_currency_check CHECK (currency ~ '^[A-Z]{3}$');
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE tickets DROP COLUMN IF EXISTS price, DROP COLUMN IF EXISTS currency;
+DROP TABLE IF EXISTS pricing_rules;
+DROP TYPE IF EXISTS price_adjustment;
+DROP TYPE IF EXISTS seat_kind;
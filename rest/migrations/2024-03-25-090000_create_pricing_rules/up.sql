@@ -0,0 +1,35 @@
+-- Your SQL goes here
+
+CREATE TYPE seat_kind AS ENUM ('aisle', 'standard', 'vip', 'wheelchair', 'companion', 'broken');
+CREATE TYPE price_adjustment AS ENUM ('set', 'add', 'percent');
+
+-- Adjustments layered on the price of a ticket type, a rule applies
+-- to a ticket when every condition it sets matches the ticket
+CREATE TABLE IF NOT EXISTS pricing_rules (
+    "id" UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    ticket_type_id UUID NOT NULL REFERENCES ticket_types("id"),
+    "name" VARCHAR(100) NOT NULL,
+    theatre_screening_id UUID REFERENCES theatre_screenings("id"),
+    seat_kind seat_kind,
+    -- ISO 8601 weekday numbers the screening starts on, empty for every day
+    weekdays INT[] NOT NULL DEFAULT '{}',
+    -- band of the screening's starting time, which wraps around midnight
+    -- when it starts later than it ends
+    starts_from TIME,
+    starts_until TIME,
+    is_3d BOOL,
+    adjustment price_adjustment NOT NULL,
+    -- minor units for 'set' and 'add', basis points for 'percent'
+    "value" BIGINT NOT NULL,
+    -- rules are applied in ascending order
+    "priority" INT NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+-- tickets keep the price they were sold for, since rules can change afterwards
+ALTER TABLE tickets ADD COLUMN IF NOT EXISTS price BIGINT, ADD COLUMN IF NOT EXISTS currency VARCHAR(3);
+
+UPDATE tickets SET price = ticket_types.price, currency = ticket_types.currency
+FROM ticket_types WHERE ticket_types.id = tickets.ticket_type_id;
+
+ALTER TABLE tickets ALTER COLUMN price SET NOT NULL, ALTER COLUMN currency SET NOT NULL;
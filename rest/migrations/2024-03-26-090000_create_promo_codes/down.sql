@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE tickets DROP COLUMN IF EXISTS promo_code_id, DROP COLUMN IF EXISTS discount;
+ALTER TABLE orders DROP COLUMN IF EXISTS promo_code_id, DROP COLUMN IF EXISTS discount;
+DROP TABLE IF EXISTS promo_codes;
+DROP TYPE IF EXISTS promo_discount;
@@ -0,0 +1,32 @@
+-- Your SQL goes here
+
+CREATE TYPE promo_discount AS ENUM ('percent', 'fixed');
+
+CREATE TABLE IF NOT EXISTS promo_codes (
+    "id" UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    theatre_id UUID NOT NULL REFERENCES theatres("id"),
+    -- always stored in upper case
+    code VARCHAR(32) NOT NULL,
+    discount promo_discount NOT NULL,
+    -- basis points for 'percent', minor units of `currency` for 'fixed'
+    "value" BIGINT NOT NULL,
+    currency VARCHAR(3),
+    -- bookings the code can be redeemed in, at all and by a single user
+    max_uses INT,
+    max_uses_per_user INT,
+    valid_from TIMESTAMP,
+    valid_until TIMESTAMP,
+    -- the code only applies to tickets of these movies/ticket types, empty for any
+    movie_ids UUID[] NOT NULL DEFAULT '{}',
+    ticket_type_ids UUID[] NOT NULL DEFAULT '{}',
+    is_deleted BOOL NOT NULL DEFAULT false,
+    created_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS promo_codes_theatre_code_idx ON promo_codes (theatre_id, code) WHERE NOT is_deleted;
+
+-- the discount is already taken off of the order's total and the tickets' prices
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS promo_code_id UUID REFERENCES promo_codes("id"),
+    ADD COLUMN IF NOT EXISTS discount BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE tickets ADD COLUMN IF NOT EXISTS promo_code_id UUID REFERENCES promo_codes("id"),
+    ADD COLUMN IF NOT EXISTS discount BIGINT NOT NULL DEFAULT 0;
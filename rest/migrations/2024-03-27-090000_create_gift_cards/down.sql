@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE orders DROP COLUMN IF EXISTS gift_card_id, DROP COLUMN IF EXISTS gift_card_amount;
+DROP TABLE IF EXISTS gift_card_entries;
+DROP TABLE IF EXISTS gift_cards;
+DROP TYPE IF EXISTS gift_card_entry_kind;
@@ -0,0 +1,32 @@
+-- Your SQL goes here
+
+CREATE TYPE gift_card_entry_kind AS ENUM ('purchase', 'redemption', 'refund');
+
+CREATE TABLE IF NOT EXISTS gift_cards (
+    "id" UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    theatre_id UUID NOT NULL REFERENCES theatres("id"),
+    -- only a hash of the code is kept, the code itself is shown once when the card is issued
+    code_hash VARCHAR(64) NOT NULL UNIQUE,
+    -- last characters of the code, so that staff can tell cards apart
+    code_suffix VARCHAR(4) NOT NULL,
+    currency VARCHAR(3) NOT NULL,
+    -- in minor units, always the sum of the card's ledger entries
+    balance BIGINT NOT NULL CHECK (balance >= 0),
+    issuer_user_id UUID NOT NULL REFERENCES users("id"),
+    created_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS gift_card_entries (
+    "id" UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    gift_card_id UUID NOT NULL REFERENCES gift_cards("id"),
+    kind gift_card_entry_kind NOT NULL,
+    -- positive for money put on the card, negative for money taken off of it
+    amount BIGINT NOT NULL,
+    order_id UUID REFERENCES orders("id"),
+    refund_id UUID UNIQUE REFERENCES refunds("id"),
+    created_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+-- what the order's gift card covers of its total, the rest is paid through the payment provider
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS gift_card_id UUID REFERENCES gift_cards("id"),
+    ADD COLUMN IF NOT EXISTS gift_card_amount BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+
+DROP TABLE IF EXISTS loyalty_entries;
+DROP TABLE IF EXISTS loyalty_accounts;
+ALTER TABLE ticket_types DROP COLUMN IF EXISTS loyalty_points_price;
+ALTER TABLE theatres DROP COLUMN IF EXISTS loyalty_points_per_unit;
+DROP TYPE IF EXISTS loyalty_entry_kind;
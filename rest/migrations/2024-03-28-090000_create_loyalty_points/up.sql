@@ -0,0 +1,35 @@
+-- Your SQL goes here
+
+CREATE TYPE loyalty_entry_kind AS ENUM ('earn', 'revoke', 'redemption', 'refund');
+
+-- points earned per whole unit of currency a checked in ticket was sold for, 0 turns earning off
+ALTER TABLE theatres ADD COLUMN IF NOT EXISTS loyalty_points_per_unit INT NOT NULL DEFAULT 0
+    CHECK (loyalty_points_per_unit >= 0);
+
+-- points a ticket of the type can be redeemed for, the type can't be redeemed when it's NULL
+ALTER TABLE ticket_types ADD COLUMN IF NOT EXISTS loyalty_points_price INT
+    CHECK (loyalty_points_price > 0);
+
+-- points are earned and redeemed at a single theatre
+CREATE TABLE IF NOT EXISTS loyalty_accounts (
+    user_id UUID NOT NULL REFERENCES users("id"),
+    theatre_id UUID NOT NULL REFERENCES theatres("id"),
+    -- always the sum of the account's ledger entries
+    points INT NOT NULL DEFAULT 0 CHECK (points >= 0),
+    PRIMARY KEY (user_id, theatre_id)
+);
+
+CREATE TABLE IF NOT EXISTS loyalty_entries (
+    "id" UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users("id"),
+    theatre_id UUID NOT NULL REFERENCES theatres("id"),
+    kind loyalty_entry_kind NOT NULL,
+    -- positive for points added to the account, negative for points taken off of it
+    points INT NOT NULL,
+    ticket_id UUID REFERENCES tickets("id"),
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    FOREIGN KEY (user_id, theatre_id) REFERENCES loyalty_accounts(user_id, theatre_id)
+);
+
+CREATE INDEX IF NOT EXISTS loyalty_entries_user_id_idx ON loyalty_entries(user_id, created_at);
+CREATE INDEX IF NOT EXISTS loyalty_entries_ticket_id_idx ON loyalty_entries(ticket_id);
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE tickets DROP COLUMN IF EXISTS membership_subscription_id;
+DROP TABLE IF EXISTS membership_periods;
+DROP TABLE IF EXISTS membership_subscriptions;
+DROP TABLE IF EXISTS membership_plans;
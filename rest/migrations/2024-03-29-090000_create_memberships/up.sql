@@ -0,0 +1,43 @@
+-- Your SQL goes here
+
+CREATE TABLE IF NOT EXISTS membership_plans (
+    "id" UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    theatre_id UUID NOT NULL REFERENCES theatres("id"),
+    "name" VARCHAR(100) NOT NULL,
+    "description" TEXT,
+    -- what a billing period costs, in minor units of `currency`
+    price BIGINT NOT NULL CHECK (price >= 0),
+    currency VARCHAR(3) NOT NULL,
+    period_days INT NOT NULL CHECK (period_days > 0),
+    -- tickets a member can book per billing period, NULL for unlimited
+    tickets_per_period INT CHECK (tickets_per_period > 0),
+    -- the plan only covers tickets of these ticket types, empty for any
+    ticket_type_ids UUID[] NOT NULL DEFAULT '{}',
+    is_deleted BOOL NOT NULL DEFAULT false,
+    created_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS membership_subscriptions (
+    "id" UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    membership_plan_id UUID NOT NULL REFERENCES membership_plans("id"),
+    user_id UUID NOT NULL REFERENCES users("id"),
+    issuer_user_id UUID NOT NULL REFERENCES users("id"),
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    -- a cancelled subscription isn't renewed anymore, its paid periods still cover bookings
+    cancelled_at TIMESTAMP
+);
+
+-- billing periods paid for, each one at the price of the plan at the time
+CREATE TABLE IF NOT EXISTS membership_periods (
+    "id" UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    membership_subscription_id UUID NOT NULL REFERENCES membership_subscriptions("id"),
+    starts_at TIMESTAMP NOT NULL,
+    ends_at TIMESTAMP NOT NULL CHECK (ends_at > starts_at),
+    price BIGINT NOT NULL,
+    currency VARCHAR(3) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+-- tickets booked with a membership cost nothing and count towards its allowance
+ALTER TABLE tickets ADD COLUMN IF NOT EXISTS membership_subscription_id UUID
+    REFERENCES membership_subscriptions("id");
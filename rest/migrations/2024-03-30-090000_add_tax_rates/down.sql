@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE tickets DROP COLUMN IF EXISTS tax_rate;
+ALTER TABLE theatres DROP COLUMN IF EXISTS tax_rate;
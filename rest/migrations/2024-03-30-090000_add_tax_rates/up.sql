@@ -0,0 +1,8 @@
+-- Your SQL goes here
+
+-- ticket prices include taxes, the rate is in basis points (e.g. 2000 for 20%)
+ALTER TABLE theatres ADD COLUMN IF NOT EXISTS tax_rate INT NOT NULL DEFAULT 0
+    CHECK (tax_rate >= 0 AND tax_rate <= 10000);
+
+-- the theatre's rate at the time the ticket was issued, receipts are based on it
+ALTER TABLE tickets ADD COLUMN IF NOT EXISTS tax_rate INT NOT NULL DEFAULT 0;
@@ -5,7 +5,7 @@ use utoipa::{
 
 use crate::handlers::movie::{MovieQuery, MovieReviewQuery};
 use crate::handlers::role::BridgeRoleQuery;
-use crate::handlers::user::NewPasswordForm;
+use crate::handlers::user::{NewPasswordForm, QrFormat};
 use crate::services::user::LoginResponse;
 use crate::{handlers::auth::EmailVerificationQuery, services::SortBy};
 
@@ -42,16 +42,48 @@ impl Modify for AuthAddon {
         handlers::theatre::role::update_roles_batch,
         handlers::theatre::screening::get_timeline,
         handlers::theatre::screening::get_theatre_screening,
+        handlers::theatre::screening::get_screening_seats,
+        handlers::theatre::screening::quote_price,
+        handlers::theatre::screening::stream_seat_events,
         handlers::theatre::screening::update_theatre_screening,
         handlers::theatre::screening::delete_theatre_screening,
+        handlers::theatre::screening::cancel_theatre_screening,
         handlers::theatre::screening::create_theatre_screening,
+        handlers::theatre::schedule::get_screening_schedules,
+        handlers::theatre::schedule::preview_screening_schedule,
+        handlers::theatre::schedule::create_screening_schedule,
+        handlers::theatre::schedule::cancel_screening_schedule,
+        handlers::theatre::seat_hold::hold_seats,
+        handlers::theatre::seat_hold::get_seat_holds,
+        handlers::theatre::seat_hold::release_seat_hold,
+        handlers::theatre::seat_hold::confirm_seat_holds,
+        handlers::theatre::order::create_order,
         handlers::theatre::ticket_type::get_all_ticket_types,
         handlers::theatre::ticket_type::create_ticket_type,
         handlers::theatre::ticket_type::delete_ticket_type,
+        handlers::theatre::pricing_rule::get_all_pricing_rules,
+        handlers::theatre::pricing_rule::create_pricing_rule,
+        handlers::theatre::pricing_rule::delete_pricing_rule,
+        handlers::theatre::promo_code::get_all_promo_codes,
+        handlers::theatre::promo_code::create_promo_code,
+        handlers::theatre::promo_code::delete_promo_code,
+        handlers::theatre::gift_card::get_all_gift_cards,
+        handlers::theatre::gift_card::get_gift_card,
+        handlers::theatre::gift_card::issue_gift_card,
+        handlers::theatre::loyalty::redeem_loyalty_points,
+        handlers::theatre::membership::get_all_membership_plans,
+        handlers::theatre::membership::create_membership_plan,
+        handlers::theatre::membership::delete_membership_plan,
+        handlers::theatre::membership::get_all_membership_subscriptions,
+        handlers::theatre::membership::create_membership_subscription,
+        handlers::theatre::membership::renew_membership_subscription,
+        handlers::theatre::membership::cancel_membership_subscription,
         handlers::theatre::ticket::query_tickets,
         handlers::theatre::ticket::create_ticket,
         handlers::theatre::ticket::validate,
         handlers::theatre::ticket::validate_and_mark,
+        handlers::theatre::ticket::cancel_ticket,
+        handlers::theatre::ticket::sync_ticket_scans,
         handlers::movie::submit_new_review,
         handlers::movie::get_review_by_id,
         handlers::movie::delete_review_by_id,
@@ -63,13 +95,30 @@ impl Modify for AuthAddon {
         handlers::movie::create_movie,
         handlers::language::get_all_languages,
         handlers::language::get_language,
+        handlers::payment::payment_webhook,
+        handlers::gift_card::get_gift_card_balance,
         handlers::auth::login_user,
         handlers::auth::register_user,
         handlers::auth::verify_email,
+        handlers::auth::get_ticket_jwks,
         handlers::role::get_all_roles,
         handlers::role::query_bridge_roles,
         handlers::user::get_self_user,
         handlers::user::get_self_tickets,
+        handlers::user::get_self_ticket_qr,
+        handlers::user::get_self_ticket_receipt,
+        handlers::user::email_self_ticket_receipt,
+        handlers::user::get_self_orders,
+        handlers::user::get_self_loyalty,
+        handlers::user::get_self_memberships,
+        handlers::user::get_self_order,
+        handlers::user::pay_self_order,
+        handlers::user::cancel_self_ticket,
+        handlers::user::transfer_self_ticket,
+        handlers::user::get_self_transfers,
+        handlers::user::accept_self_transfer,
+        handlers::user::decline_self_transfer,
+        handlers::user::cancel_self_transfer,
         handlers::user::update_self_password,
         handlers::user::get_partial_user,
         handlers::user::get_user_reviews,
@@ -78,7 +127,7 @@ impl Modify for AuthAddon {
         handlers::user::get_self_roles
     ),
     components(
-        schemas(ExtendedTheatre, UpdateMovieReview, UpdateUser, FormTicket, NewPasswordForm, PartialMovie, PartialMovieReview, ExtendedMovieReview, PartialUser, Ticket, User, SortBy, LoginResponse, Language, MovieReview, Theatre, Movie, UserTheatreRole, Hall, TheatreScreening, TheatreScreeningEvent, TicketType, FormUser, FormTheatreScreening, FormHall, FormTheatre, FormMovie, FormTicketType, FormMovieReview, UserRoleForm, RoleUpdateAction, LoginUser, EmailVerificationQuery, MovieQuery, BridgeRoleQuery),
+        schemas(ExtendedTheatre, SeatData, SeatKind, SeatHold, SeatPosition, FormSeatHold, ConfirmSeatHold, FormConfirmSeatHolds, SeatStatus, SeatAvailability, SeatEvent, SeatEventKind, ScreeningStatus, ScreeningSchedule, FormScreeningSchedule, ScheduledScreening, ExtendedScreeningSchedule, UpdateMovieReview, UpdateUser, FormTicket, NewPasswordForm, QrFormat, TicketClaims, TicketCheckIn, CheckInAction, ExtendedTicket, TicketScan, FormTicketScans, TicketScanOutcome, TicketScanResult, Refund, RefundReason, TicketTransfer, TicketTransferStatus, FormTicketTransfer, Order, OrderStatus, FormOrder, FormOrderTicket, ExtendedOrder, Payment, PaymentStatus, FormOrderPayment, PartialMovie, PartialMovieReview, ExtendedMovieReview, PartialUser, Ticket, User, SortBy, LoginResponse, Language, MovieReview, Theatre, Movie, UserTheatreRole, Hall, TheatreScreening, TheatreScreeningEvent, TicketType, FormUser, FormTheatreScreening, FormHall, FormTheatre, FormMovie, FormTicketType, Money, PricingRule, FormPricingRule, PriceAdjustment, PriceQuote, PriceStep, PromoCode, FormPromoCode, ExtendedPromoCode, PromoDiscount, GiftCard, GiftCardEntry, GiftCardEntryKind, FormGiftCard, IssuedGiftCard, ExtendedGiftCard, FormGiftCardCode, GiftCardBalance, LoyaltyEntryKind, LoyaltyAccount, LoyaltyEntry, UserLoyalty, FormLoyaltyRedemption, MembershipPlan, FormMembershipPlan, MembershipSubscription, FormMembershipSubscription, MembershipPeriod, ExtendedMembershipSubscription, FormMovieReview, UserRoleForm, RoleUpdateAction, LoginUser, EmailVerificationQuery, MovieQuery, BridgeRoleQuery),
     ),
     modifiers(&AuthAddon)
 )]
@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use super::*;
 use chrono::Utc;
+use jsonwebtoken::jwk::JwkSet;
 use tokio::sync::Mutex;
 
 use super::ErrorType;
@@ -9,6 +10,7 @@ use crate::{
     mailer::Mailer,
     model::{FormUser, JwtType, LoginUser, User},
     services::user::{LoginResponse, UserResource, UserService},
+    ticket_keys::{TicketKeys, TICKET_KEYS},
 };
 
 use utoipa::{IntoParams, ToSchema};
@@ -55,7 +57,7 @@ pub async fn login_user(
     }
 
     let Some(password_hash) = user.password_hash else {
-        return Err(ErrorType::Conflict);
+        return Err(ErrorType::Conflict(vec![]));
     };
 
     if crate::password::verify(params.password.as_bytes(), &password_hash) {
@@ -89,7 +91,7 @@ pub async fn register_user(
         .await?
         .is_some()
     {
-        return Err(ErrorType::Conflict);
+        return Err(ErrorType::Conflict(vec![]));
     }
 
     let user = user_service.create(user.into_inner()).await?;
@@ -153,11 +155,32 @@ pub async fn verify_email(
     }
 }
 
+/// Publishes the public keys tickets are signed with as a JWK set,
+/// so that checker devices can verify tickets offline
+///
+/// Tickets carry the ID of their key in the `kid` header. The set is empty
+/// when the server signs tickets with a shared secret instead.
+#[utoipa::path(
+    context_path = "/api/v1/auth",
+    responses(
+        (status = OK, description = "The public ticket signing keys are returned", body = Object)
+    )
+)]
+#[get("/jwks")]
+pub async fn get_ticket_jwks() -> HandlerResult<JwkSet> {
+    Ok(TICKET_KEYS
+        .as_ref()
+        .map(TicketKeys::jwks)
+        .unwrap_or(JwkSet { keys: vec![] })
+        .into())
+}
+
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/auth")
             .service(login_user)
             .service(register_user)
-            .service(verify_email),
+            .service(verify_email)
+            .service(get_ticket_jwks),
     );
 }
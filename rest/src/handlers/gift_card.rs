@@ -0,0 +1,41 @@
+use crate::{
+    model::{FormGiftCardCode, GiftCardBalance},
+    services::gift_card::GiftCardService,
+};
+
+use super::*;
+
+/// Checks the balance of a gift card by its code
+///
+/// The code is sent in the body, so that it doesn't end up in any URL logs
+#[utoipa::path(
+    context_path = "/api/v1/gift_card",
+    request_body = FormGiftCardCode,
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = NOT_FOUND, description = "There's no gift card with the given code"),
+        (status = OK, description = "The gift card's balance was returned", body = GiftCardBalance)
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/balance")]
+pub async fn get_gift_card_balance(
+    form: web::Json<FormGiftCardCode>,
+    gift_card_service: web::Data<GiftCardService>,
+    _claims: JwtClaims,
+) -> HandlerResult<GiftCardBalance> {
+    match gift_card_service
+        .get_balance(form.into_inner().code)
+        .await?
+    {
+        Some(v) => Ok(v.into()),
+        None => Err(ErrorType::NotFound),
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/gift_card").service(get_gift_card_balance));
+}
@@ -2,22 +2,28 @@ use actix_web::{
     body::BoxBody, delete, get, http::StatusCode, post, put, web, HttpResponse, Responder,
     ResponseError,
 };
+use lettre::Message;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use tokio::sync::Mutex;
 use utoipa::ToSchema;
 use validator::{Validate, ValidationErrors};
 
 use crate::{
+    mailer::Mailer,
     model::{JwtClaims, JwtType, User},
+    payment::PaymentError,
     services::{
         user::{UserResource, UserService},
-        DatabaseError,
+        DatabaseError, MailBuildError,
     },
 };
 
 pub mod auth;
+pub mod gift_card;
 pub mod language;
 pub mod movie;
+pub mod payment;
 pub mod role;
 pub mod theatre;
 pub mod user;
@@ -32,11 +38,23 @@ pub enum ErrorType {
     InsufficientPermission,
     EmailNotVerified,
     ServerError,
-    Conflict,
+    /// holds the IDs of the resources which clashed with the request
+    Conflict(Vec<uuid::Uuid>),
     Invalid,
+    NotOnSale,
     NoAuth,
     NotFound,
     Expired,
+    /// the ticket can't be checked in yet, its screening hasn't opened for entry
+    TooEarly,
+    Cancelled,
+    /// the ticket can't be cancelled anymore, its screening is about to start
+    PastCutoff,
+    /// the membership's allowance for its current billing period is used up
+    AllowanceExceeded,
+    /// the ticket's order hasn't been paid for yet
+    PaymentRequired,
+    PaymentDeclined,
 }
 
 pub struct SuccessResponse<T>(pub T);
@@ -61,13 +79,38 @@ pub async fn user_res_from_jwt(
     }
 }
 
+/// Queues notification emails (e.g. about cancellations), failures are
+/// only logged since the change they notify about has already been committed
+async fn queue_notification_mails(
+    mailer: &Mutex<Mailer>,
+    messages: Result<Vec<Message>, MailBuildError>,
+) {
+    let messages = match messages {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("Error when building notification emails: {:?}", e);
+            return;
+        }
+    };
+
+    let mailer = mailer.lock().await;
+
+    for message in messages {
+        if let Err(e) = mailer.queue_mail(message).await {
+            log::error!("Error when queueing a notification email: {:?}", e);
+        }
+    }
+}
+
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.configure(auth::config)
         .configure(theatre::config)
         .configure(movie::config)
         .configure(user::config)
         .configure(role::config)
-        .configure(language::config);
+        .configure(language::config)
+        .configure(payment::config)
+        .configure(gift_card::config);
 }
 
 impl<T> From<T> for SuccessResponse<T>
@@ -94,7 +137,21 @@ where
 
 impl From<DatabaseError> for ErrorType {
     fn from(value: DatabaseError) -> Self {
-        ErrorType::Database(value)
+        match value {
+            DatabaseError::Conflict(ids) => ErrorType::Conflict(ids),
+            DatabaseError::Invalid => ErrorType::Invalid,
+            DatabaseError::NotOnSale => ErrorType::NotOnSale,
+            DatabaseError::Cancelled => ErrorType::Cancelled,
+            DatabaseError::PastCutoff => ErrorType::PastCutoff,
+            DatabaseError::AllowanceExceeded => ErrorType::AllowanceExceeded,
+            DatabaseError::TicketLimitExceeded => ErrorType::InsufficientPermission,
+            DatabaseError::PaymentDeclined => ErrorType::PaymentDeclined,
+            // anything but an outage of the provider comes from a bad request
+            DatabaseError::Payment(ref e) if !matches!(e, PaymentError::Provider(_)) => {
+                ErrorType::Invalid
+            }
+            _ => ErrorType::Database(value),
+        }
     }
 }
 
@@ -140,12 +197,19 @@ impl ResponseError for ErrorType {
             ErrorType::Database(e) => ErrorType::status_code_db_error(e),
             ErrorType::Validation(_) | ErrorType::Invalid => StatusCode::BAD_REQUEST,
             ErrorType::ServerError => StatusCode::INTERNAL_SERVER_ERROR,
-            ErrorType::InsufficientPermission => StatusCode::FORBIDDEN,
+            ErrorType::InsufficientPermission | ErrorType::AllowanceExceeded => {
+                StatusCode::FORBIDDEN
+            }
             ErrorType::Expired | ErrorType::NoAuth | ErrorType::EmailNotVerified => {
                 StatusCode::UNAUTHORIZED
             }
             ErrorType::NotFound => StatusCode::NOT_FOUND,
-            ErrorType::Conflict => StatusCode::CONFLICT,
+            ErrorType::Cancelled => StatusCode::GONE,
+            ErrorType::PaymentRequired | ErrorType::PaymentDeclined => StatusCode::PAYMENT_REQUIRED,
+            ErrorType::Conflict(_)
+            | ErrorType::NotOnSale
+            | ErrorType::TooEarly
+            | ErrorType::PastCutoff => StatusCode::CONFLICT,
         }
     }
 }
@@ -0,0 +1,47 @@
+use actix_web::HttpRequest;
+
+use crate::services::payment::PaymentService;
+
+use super::*;
+
+/// header the payment provider signs its webhook requests with
+pub const SIGNATURE_HEADER: &str = "Payment-Signature";
+
+/// Receives payment state changes from the payment provider
+///
+/// The body is verified against the signature header, the same event
+/// can be delivered more than once and is only applied the first time
+#[utoipa::path(
+    context_path = "/api/v1/payment",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = NOT_FOUND, description = "The payment intent doesn't belong to any order"),
+        (status = BAD_REQUEST, description = "The signature is missing or doesn't match the body"),
+        (status = OK, description = "The event was applied to its payment")
+    ),
+    params(
+        ("Payment-Signature" = String, Header, description = "Signature of the request body")
+    )
+)]
+#[post("/webhook")]
+pub async fn payment_webhook(
+    req: HttpRequest,
+    body: web::Bytes,
+    payment_service: web::Data<PaymentService>,
+) -> HandlerResult<()> {
+    let Some(signature) = req
+        .headers()
+        .get(SIGNATURE_HEADER)
+        .and_then(|x| x.to_str().ok())
+    else {
+        return Err(ErrorType::Invalid);
+    };
+
+    payment_service.handle_webhook(signature, &body).await?;
+
+    Ok(().into())
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/payment").service(payment_webhook));
+}
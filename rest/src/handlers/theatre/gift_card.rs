@@ -0,0 +1,161 @@
+use crate::model::{ExtendedGiftCard, FormGiftCard, GiftCard, IssuedGiftCard};
+
+use super::*;
+
+/// Gets the gift cards issued by a theatre
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/gift_card",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || TicketManager)"),
+        (status = NOT_FOUND, description = "The selected theatre was not found"),
+        (status = OK, description = "The selected theatre was found and its GiftCards were returned", body = Vec<GiftCard>)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID for Theatre"),
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[get("/all")]
+pub async fn get_all_gift_cards(
+    path: web::Path<uuid::Uuid>,
+    theatre_service: web::Data<TheatreService>,
+    user_service: web::Data<UserService>,
+    role_service: web::Data<RoleService>,
+    bridge_role_service: web::Data<BridgeRoleService>,
+    claims: JwtClaims,
+) -> HandlerResult<Vec<GiftCard>> {
+    let theatre_id = path.into_inner();
+    let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if !user.is_super_user {
+        check_roles_or!(
+            [Role::TheatreOwner, Role::TicketManager],
+            user.id,
+            theatre_id,
+            bridge_role_service,
+            role_service
+        );
+    }
+
+    Ok(theatre_res.get_gift_cards().await?.into())
+}
+
+/// Gets one of the theatre's gift cards along with its balance ledger
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/gift_card",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || TicketManager)"),
+        (status = NOT_FOUND, description = "The selected theatre or gift card was not found"),
+        (status = OK, description = "The gift card and its ledger were returned", body = ExtendedGiftCard)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID for Theatre"),
+        ("gid", description = "Unique storage ID for GiftCard")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[get("/{gid}")]
+pub async fn get_gift_card(
+    path: web::Path<(uuid::Uuid, uuid::Uuid)>,
+    theatre_service: web::Data<TheatreService>,
+    user_service: web::Data<UserService>,
+    role_service: web::Data<RoleService>,
+    bridge_role_service: web::Data<BridgeRoleService>,
+    claims: JwtClaims,
+) -> HandlerResult<ExtendedGiftCard> {
+    let (theatre_id, gift_card_id) = path.into_inner();
+    let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if !user.is_super_user {
+        check_roles_or!(
+            [Role::TheatreOwner, Role::TicketManager],
+            user.id,
+            theatre_id,
+            bridge_role_service,
+            role_service
+        );
+    }
+
+    match theatre_res.get_gift_card(gift_card_id).await? {
+        Some(v) => Ok(v.into()),
+        None => Err(ErrorType::NotFound),
+    }
+}
+
+/// Issues a new gift card loaded with the sold amount
+///
+/// The card's code is only returned here, it can't be looked up later on
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/gift_card",
+    request_body = FormGiftCard,
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID for Theatre")
+    ),
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || TicketManager)"),
+        (status = NOT_FOUND, description = "The selected theatre was not found"),
+        (status = BAD_REQUEST, description = "Invalid data supplied"),
+        (status = OK, description = "The selected theatre was found and the gift card was issued", body = IssuedGiftCard)
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/new")]
+pub async fn issue_gift_card(
+    path: web::Path<uuid::Uuid>,
+    new_gift_card: web::Json<FormGiftCard>,
+    theatre_service: web::Data<TheatreService>,
+    user_service: web::Data<UserService>,
+    role_service: web::Data<RoleService>,
+    bridge_role_service: web::Data<BridgeRoleService>,
+    claims: JwtClaims,
+) -> HandlerResult<IssuedGiftCard> {
+    new_gift_card.validate()?;
+
+    let theatre_id = path.into_inner();
+    let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if !user.is_super_user {
+        check_roles_or!(
+            [Role::TheatreOwner, Role::TicketManager],
+            user.id,
+            theatre_id,
+            bridge_role_service,
+            role_service
+        );
+    }
+
+    Ok(theatre_res
+        .issue_gift_card(user.id, new_gift_card.into_inner())
+        .await?
+        .into())
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/gift_card")
+            .service(get_all_gift_cards)
+            .service(issue_gift_card)
+            .service(get_gift_card),
+    );
+}
@@ -1,4 +1,4 @@
-use crate::model::{CreateHall, FormHall, Hall, SeatData};
+use crate::model::{CreateHall, FormHall, Hall};
 
 use super::*;
 
@@ -70,10 +70,6 @@ pub async fn create_hall(
         return Err(ErrorType::NotFound);
     };
 
-    if let Err(_) = serde_json::from_value::<SeatData>(new_hall.seat_data.clone()) {
-        return Err(ErrorType::Invalid);
-    }
-
     if !user.is_super_user {
         check_roles_or!(
             [Role::TheatreOwner],
@@ -0,0 +1,68 @@
+use crate::model::{FormLoyaltyRedemption, Ticket, MAX_TICKETS_PER_SCREENING};
+
+use super::*;
+
+/// Books a seat of a screening for the logged in user with their loyalty points
+///
+/// Only ticket types which have a points price can be redeemed, the points are taken off
+/// of what the user has earned at the theatre and put back if the ticket is cancelled
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/screening",
+    request_body = FormLoyaltyRedemption,
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = FORBIDDEN, description = "User would exceed the maximum amount of tickets for the screening"),
+        (status = NOT_FOUND, description = "The selected theatre or screening was not found"),
+        (status = BAD_REQUEST, description = "Invalid data supplied, the seat doesn't exist in the hall, the ticket type can't be redeemed for points or the user doesn't have enough of them"),
+        (status = CONFLICT, description = "The seat has already been booked or held by someone else or the screening isn't on sale"),
+        (status = OK, description = "The seat was booked and its free ticket is returned", body = Ticket)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID for Theatre"),
+        ("tsid" = uuid::Uuid, description = "Unique storage ID for TheatreScreening")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/{tsid}/redeem")]
+pub async fn redeem_loyalty_points(
+    path: web::Path<(uuid::Uuid, uuid::Uuid)>,
+    form: web::Json<FormLoyaltyRedemption>,
+    user_service: web::Data<UserService>,
+    theatre_service: web::Data<TheatreService>,
+    claims: JwtClaims,
+) -> HandlerResult<Ticket> {
+    let (theatre_id, theatre_screening_id) = path.into_inner();
+    let (user_res, _) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if theatre_res
+        .get_theatre_screening(theatre_screening_id)
+        .await?
+        .is_none()
+    {
+        return Err(ErrorType::NotFound);
+    }
+
+    let owned_ticket_count = user_res
+        .get_tickets_count(Some(theatre_screening_id))
+        .await?;
+    if owned_ticket_count >= MAX_TICKETS_PER_SCREENING {
+        return Err(ErrorType::InsufficientPermission);
+    }
+
+    Ok(Ticket::from(
+        user_res
+            .redeem_loyalty_points(theatre_screening_id, form.into_inner())
+            .await?,
+    )
+    .into())
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(redeem_loyalty_points);
+}
@@ -0,0 +1,347 @@
+use crate::model::{
+    ExtendedMembershipSubscription, FormMembershipPlan, FormMembershipSubscription, MembershipPlan,
+    MembershipSubscription,
+};
+
+use super::*;
+
+/// Gets the membership plans a theatre offers
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/membership",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = NOT_FOUND, description = "The selected theatre was not found"),
+        (status = OK, description = "The selected theatre was found and its MembershipPlans were returned", body = Vec<MembershipPlan>)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID for Theatre"),
+    ),
+)]
+#[get("/plan/all")]
+pub async fn get_all_membership_plans(
+    path: web::Path<uuid::Uuid>,
+    theatre_service: web::Data<TheatreService>,
+) -> HandlerResult<Vec<MembershipPlan>> {
+    let Some(theatre_res) = theatre_service.get_by_id(path.into_inner()).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    Ok(theatre_res.get_membership_plans().await?.into())
+}
+
+/// Creates a new membership plan
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/membership",
+    request_body = FormMembershipPlan,
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID for Theatre")
+    ),
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || TicketManager)"),
+        (status = NOT_FOUND, description = "The selected theatre was not found"),
+        (status = BAD_REQUEST, description = "Invalid data supplied or one of the ticket types doesn't belong to the theatre"),
+        (status = OK, description = "The selected theatre was found and new MembershipPlan was created", body = MembershipPlan)
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/plan/new")]
+pub async fn create_membership_plan(
+    path: web::Path<uuid::Uuid>,
+    new_plan: web::Json<FormMembershipPlan>,
+    theatre_service: web::Data<TheatreService>,
+    user_service: web::Data<UserService>,
+    role_service: web::Data<RoleService>,
+    bridge_role_service: web::Data<BridgeRoleService>,
+    claims: JwtClaims,
+) -> HandlerResult<MembershipPlan> {
+    new_plan.validate()?;
+
+    let theatre_id = path.into_inner();
+    let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if !user.is_super_user {
+        check_roles_or!(
+            [Role::TheatreOwner, Role::TicketManager],
+            user.id,
+            theatre_id,
+            bridge_role_service,
+            role_service
+        );
+    }
+
+    Ok(theatre_res
+        .create_membership_plan(new_plan.into_inner())
+        .await?
+        .into())
+}
+
+/// Deletes a membership plan, its members keep their memberships until they run out
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/membership",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || TicketManager)"),
+        (status = NOT_FOUND, description = "The selected theatre was not found"),
+        (status = OK, description = "The selected theatre was found and the MembershipPlan was deleted")
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID for Theatre"),
+        ("pid", description = "Unique storage ID for MembershipPlan")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[delete("/plan/{pid}")]
+pub async fn delete_membership_plan(
+    path: web::Path<(uuid::Uuid, uuid::Uuid)>,
+    theatre_service: web::Data<TheatreService>,
+    user_service: web::Data<UserService>,
+    role_service: web::Data<RoleService>,
+    bridge_role_service: web::Data<BridgeRoleService>,
+    claims: JwtClaims,
+) -> HandlerResult<()> {
+    let (theatre_id, plan_id) = path.into_inner();
+    let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if !user.is_super_user {
+        check_roles_or!(
+            [Role::TheatreOwner, Role::TicketManager],
+            user.id,
+            theatre_id,
+            bridge_role_service,
+            role_service
+        );
+    }
+
+    Ok(theatre_res.delete_membership_plan(plan_id).await?.into())
+}
+
+/// Gets the memberships in a theatre's plans along with their billing periods
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/membership",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || TicketManager)"),
+        (status = NOT_FOUND, description = "The selected theatre was not found"),
+        (status = OK, description = "The selected theatre was found and the memberships were returned, newest first", body = Vec<ExtendedMembershipSubscription>)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID for Theatre"),
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[get("/subscription/all")]
+pub async fn get_all_membership_subscriptions(
+    path: web::Path<uuid::Uuid>,
+    theatre_service: web::Data<TheatreService>,
+    user_service: web::Data<UserService>,
+    role_service: web::Data<RoleService>,
+    bridge_role_service: web::Data<BridgeRoleService>,
+    claims: JwtClaims,
+) -> HandlerResult<Vec<ExtendedMembershipSubscription>> {
+    let theatre_id = path.into_inner();
+    let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if !user.is_super_user {
+        check_roles_or!(
+            [Role::TheatreOwner, Role::TicketManager],
+            user.id,
+            theatre_id,
+            bridge_role_service,
+            role_service
+        );
+    }
+
+    Ok(theatre_res.get_membership_subscriptions().await?.into())
+}
+
+/// Signs a user up for a membership plan
+///
+/// The first billing period starts right away and is charged at the box office
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/membership",
+    request_body = FormMembershipSubscription,
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID for Theatre")
+    ),
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || TicketManager)"),
+        (status = NOT_FOUND, description = "The selected theatre was not found"),
+        (status = BAD_REQUEST, description = "Invalid data supplied, the plan isn't offered by the theatre or the user doesn't exist"),
+        (status = OK, description = "The selected theatre was found and the membership was created", body = ExtendedMembershipSubscription)
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/subscription/new")]
+pub async fn create_membership_subscription(
+    path: web::Path<uuid::Uuid>,
+    new_subscription: web::Json<FormMembershipSubscription>,
+    theatre_service: web::Data<TheatreService>,
+    user_service: web::Data<UserService>,
+    role_service: web::Data<RoleService>,
+    bridge_role_service: web::Data<BridgeRoleService>,
+    claims: JwtClaims,
+) -> HandlerResult<ExtendedMembershipSubscription> {
+    let theatre_id = path.into_inner();
+    let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if !user.is_super_user {
+        check_roles_or!(
+            [Role::TheatreOwner, Role::TicketManager],
+            user.id,
+            theatre_id,
+            bridge_role_service,
+            role_service
+        );
+    }
+
+    Ok(theatre_res
+        .create_membership_subscription(user.id, new_subscription.into_inner())
+        .await?
+        .into())
+}
+
+/// Renews a membership for another billing period
+///
+/// The period starts when the last paid one ends, or right away if that's already over
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/membership",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || TicketManager)"),
+        (status = NOT_FOUND, description = "The selected theatre or membership was not found"),
+        (status = BAD_REQUEST, description = "The membership is cancelled or its plan was deleted"),
+        (status = OK, description = "The membership was renewed", body = ExtendedMembershipSubscription)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID for Theatre"),
+        ("sid", description = "Unique storage ID for MembershipSubscription")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/subscription/{sid}/renew")]
+pub async fn renew_membership_subscription(
+    path: web::Path<(uuid::Uuid, uuid::Uuid)>,
+    theatre_service: web::Data<TheatreService>,
+    user_service: web::Data<UserService>,
+    role_service: web::Data<RoleService>,
+    bridge_role_service: web::Data<BridgeRoleService>,
+    claims: JwtClaims,
+) -> HandlerResult<ExtendedMembershipSubscription> {
+    let (theatre_id, subscription_id) = path.into_inner();
+    let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if !user.is_super_user {
+        check_roles_or!(
+            [Role::TheatreOwner, Role::TicketManager],
+            user.id,
+            theatre_id,
+            bridge_role_service,
+            role_service
+        );
+    }
+
+    match theatre_res
+        .renew_membership_subscription(subscription_id)
+        .await?
+    {
+        Some(v) => Ok(v.into()),
+        None => Err(ErrorType::NotFound),
+    }
+}
+
+/// Cancels a membership, the billing periods already paid for keep covering bookings
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/membership",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || TicketManager)"),
+        (status = NOT_FOUND, description = "The selected theatre or an active membership was not found"),
+        (status = OK, description = "The membership was cancelled", body = MembershipSubscription)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID for Theatre"),
+        ("sid", description = "Unique storage ID for MembershipSubscription")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/subscription/{sid}/cancel")]
+pub async fn cancel_membership_subscription(
+    path: web::Path<(uuid::Uuid, uuid::Uuid)>,
+    theatre_service: web::Data<TheatreService>,
+    user_service: web::Data<UserService>,
+    role_service: web::Data<RoleService>,
+    bridge_role_service: web::Data<BridgeRoleService>,
+    claims: JwtClaims,
+) -> HandlerResult<MembershipSubscription> {
+    let (theatre_id, subscription_id) = path.into_inner();
+    let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if !user.is_super_user {
+        check_roles_or!(
+            [Role::TheatreOwner, Role::TicketManager],
+            user.id,
+            theatre_id,
+            bridge_role_service,
+            role_service
+        );
+    }
+
+    match theatre_res
+        .cancel_membership_subscription(subscription_id)
+        .await?
+    {
+        Some(v) => Ok(v.into()),
+        None => Err(ErrorType::NotFound),
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/membership")
+            .service(get_all_membership_plans)
+            .service(create_membership_plan)
+            .service(delete_membership_plan)
+            .service(get_all_membership_subscriptions)
+            .service(create_membership_subscription)
+            .service(renew_membership_subscription)
+            .service(cancel_membership_subscription),
+    );
+}
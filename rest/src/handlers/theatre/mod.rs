@@ -1,16 +1,28 @@
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
 use utoipa::IntoParams;
 
 use crate::{
     check_roles_or,
+    mailer::Mailer,
     model::{ExtendedTheatre, FormTheatre, Point, Role, Theatre},
     services::{bridge_role::*, role::*, theatre::*},
 };
 
 use super::*;
 
+pub mod gift_card;
 pub mod hall;
+pub mod loyalty;
+pub mod membership;
+pub mod order;
+pub mod pricing_rule;
+pub mod promo_code;
 pub mod role;
+pub mod schedule;
 pub mod screening;
+pub mod seat_hold;
 pub mod ticket;
 pub mod ticket_type;
 
@@ -200,8 +212,13 @@ pub fn config(cfg: &mut web::ServiceConfig) {
             .service(
                 web::scope("/{id}")
                     .configure(screening::config)
+                    .configure(schedule::config)
                     .configure(role::config)
                     .configure(ticket_type::config)
+                    .configure(pricing_rule::config)
+                    .configure(promo_code::config)
+                    .configure(gift_card::config)
+                    .configure(membership::config)
                     .configure(ticket::config)
                     .configure(hall::config),
             ),
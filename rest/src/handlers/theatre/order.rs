@@ -0,0 +1,82 @@
+use crate::{
+    model::{ExtendedOrder, FormOrder, MAX_TICKETS_PER_SCREENING},
+    services::payment::PaymentService,
+};
+
+use super::*;
+
+/// Books several seats of a screening for the logged in user as one order
+///
+/// Every seat is booked or none of them are, the total price of the order
+/// is the sum of the prices of the chosen ticket types, minus the discount of
+/// the promo code if one is given. A given gift card pays for as much of the
+/// order as its balance covers, the rest awaits payment of the order's payment
+/// intent and the order is cancelled if it isn't paid for in time
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/screening",
+    request_body = FormOrder,
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = FORBIDDEN, description = "User would exceed the maximum amount of tickets for the screening or a membership's allowance for its current billing period"),
+        (status = NOT_FOUND, description = "The selected theatre or screening was not found"),
+        (status = BAD_REQUEST, description = "Invalid data supplied, a seat doesn't exist in the hall, a ticket type doesn't belong to the theatre, the ticket types are priced in different currencies the promo or gift card code can't be redeemed for the order or a membership doesn't cover its ticket"),
+        (status = CONFLICT, description = "A seat has already been booked or held by someone else or the screening isn't on sale"),
+        (status = OK, description = "The order was placed and returned along with its tickets and payment", body = ExtendedOrder)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID for Theatre"),
+        ("tsid" = uuid::Uuid, description = "Unique storage ID for TheatreScreening")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/{tsid}/order")]
+pub async fn create_order(
+    path: web::Path<(uuid::Uuid, uuid::Uuid)>,
+    form: web::Json<FormOrder>,
+    user_service: web::Data<UserService>,
+    theatre_service: web::Data<TheatreService>,
+    payment_service: web::Data<PaymentService>,
+    claims: JwtClaims,
+) -> HandlerResult<ExtendedOrder> {
+    form.validate()?;
+
+    let (theatre_id, theatre_screening_id) = path.into_inner();
+    let (user_res, _) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if theatre_res
+        .get_theatre_screening(theatre_screening_id)
+        .await?
+        .is_none()
+    {
+        return Err(ErrorType::NotFound);
+    }
+
+    let owned_ticket_count = user_res
+        .get_tickets_count(Some(theatre_screening_id))
+        .await?;
+    if owned_ticket_count + form.tickets.len() as i64 > MAX_TICKETS_PER_SCREENING {
+        return Err(ErrorType::InsufficientPermission);
+    }
+
+    let form = form.into_inner();
+    let order = user_res
+        .create_order(
+            theatre_screening_id,
+            form.tickets,
+            form.promo_code,
+            form.gift_card_code,
+        )
+        .await?;
+
+    Ok(payment_service.start_payment(order).await?.into())
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(create_order);
+}
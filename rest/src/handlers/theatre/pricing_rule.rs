@@ -0,0 +1,158 @@
+use crate::model::{FormPricingRule, PricingRule};
+
+use super::*;
+
+/// Gets the pricing rules of all ticket types of a theatre
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/pricing_rule",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || TicketManager)"),
+        (status = NOT_FOUND, description = "The selected theatre was not found"),
+        (status = OK, description = "The selected theatre was found and its PricingRules were returned", body = Vec<PricingRule>)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID for Theatre"),
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[get("/all")]
+pub async fn get_all_pricing_rules(
+    path: web::Path<uuid::Uuid>,
+    theatre_service: web::Data<TheatreService>,
+    user_service: web::Data<UserService>,
+    role_service: web::Data<RoleService>,
+    bridge_role_service: web::Data<BridgeRoleService>,
+    claims: JwtClaims,
+) -> HandlerResult<Vec<PricingRule>> {
+    let theatre_id = path.into_inner();
+    let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if !user.is_super_user {
+        check_roles_or!(
+            [Role::TheatreOwner, Role::TicketManager],
+            user.id,
+            theatre_id,
+            bridge_role_service,
+            role_service
+        );
+    }
+
+    Ok(theatre_res.get_pricing_rules().await?.into())
+}
+
+/// Creates a new pricing rule for one of the theatre's ticket types
+///
+/// Weekdays and starting time bands are matched against the screening's starting time in UTC
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/pricing_rule",
+    request_body = FormPricingRule,
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID for Theatre")
+    ),
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || TicketManager)"),
+        (status = NOT_FOUND, description = "The selected theatre was not found"),
+        (status = BAD_REQUEST, description = "Invalid data supplied, or the ticket type or screening doesn't belong to the theatre"),
+        (status = OK, description = "The selected theatre was found and new PricingRule was created", body = PricingRule)
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/new")]
+pub async fn create_pricing_rule(
+    path: web::Path<uuid::Uuid>,
+    new_rule: web::Json<FormPricingRule>,
+    theatre_service: web::Data<TheatreService>,
+    user_service: web::Data<UserService>,
+    role_service: web::Data<RoleService>,
+    bridge_role_service: web::Data<BridgeRoleService>,
+    claims: JwtClaims,
+) -> HandlerResult<PricingRule> {
+    new_rule.validate()?;
+
+    let theatre_id = path.into_inner();
+    let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if !user.is_super_user {
+        check_roles_or!(
+            [Role::TheatreOwner, Role::TicketManager],
+            user.id,
+            theatre_id,
+            bridge_role_service,
+            role_service
+        );
+    }
+
+    Ok(theatre_res
+        .create_pricing_rule(new_rule.into_inner())
+        .await?
+        .into())
+}
+
+/// Deletes a pricing rule, tickets already sold keep their price
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/pricing_rule",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || TicketManager)"),
+        (status = NOT_FOUND, description = "The selected theatre was not found"),
+        (status = OK, description = "The selected theatre was found and the PricingRule was deleted")
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID for Theatre"),
+        ("rid", description = "Unique storage ID for PricingRule")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[delete("/{rid}")]
+pub async fn delete_pricing_rule(
+    path: web::Path<(uuid::Uuid, uuid::Uuid)>,
+    theatre_service: web::Data<TheatreService>,
+    user_service: web::Data<UserService>,
+    role_service: web::Data<RoleService>,
+    bridge_role_service: web::Data<BridgeRoleService>,
+    claims: JwtClaims,
+) -> HandlerResult<()> {
+    let (theatre_id, rule_id) = path.into_inner();
+    let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if !user.is_super_user {
+        check_roles_or!(
+            [Role::TheatreOwner, Role::TicketManager],
+            user.id,
+            theatre_id,
+            bridge_role_service,
+            role_service
+        );
+    }
+
+    Ok(theatre_res.delete_pricing_rule(rule_id).await?.into())
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/pricing_rule")
+            .service(get_all_pricing_rules)
+            .service(create_pricing_rule)
+            .service(delete_pricing_rule),
+    );
+}
@@ -0,0 +1,157 @@
+use crate::model::{ExtendedPromoCode, FormPromoCode, PromoCode};
+
+use super::*;
+
+/// Gets the promo codes of a theatre along with how many times each was redeemed
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/promo_code",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || TicketManager)"),
+        (status = NOT_FOUND, description = "The selected theatre was not found"),
+        (status = OK, description = "The selected theatre was found and its PromoCodes were returned", body = Vec<ExtendedPromoCode>)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID for Theatre"),
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[get("/all")]
+pub async fn get_all_promo_codes(
+    path: web::Path<uuid::Uuid>,
+    theatre_service: web::Data<TheatreService>,
+    user_service: web::Data<UserService>,
+    role_service: web::Data<RoleService>,
+    bridge_role_service: web::Data<BridgeRoleService>,
+    claims: JwtClaims,
+) -> HandlerResult<Vec<ExtendedPromoCode>> {
+    let theatre_id = path.into_inner();
+    let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if !user.is_super_user {
+        check_roles_or!(
+            [Role::TheatreOwner, Role::TicketManager],
+            user.id,
+            theatre_id,
+            bridge_role_service,
+            role_service
+        );
+    }
+
+    Ok(theatre_res.get_promo_codes().await?.into())
+}
+
+/// Creates a new promo code for the theatre
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/promo_code",
+    request_body = FormPromoCode,
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID for Theatre")
+    ),
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || TicketManager)"),
+        (status = NOT_FOUND, description = "The selected theatre was not found"),
+        (status = BAD_REQUEST, description = "Invalid data supplied, a movie doesn't exist or a ticket type doesn't belong to the theatre"),
+        (status = CONFLICT, description = "The theatre already has a promo code with the same code"),
+        (status = OK, description = "The selected theatre was found and new PromoCode was created", body = PromoCode)
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/new")]
+pub async fn create_promo_code(
+    path: web::Path<uuid::Uuid>,
+    new_promo_code: web::Json<FormPromoCode>,
+    theatre_service: web::Data<TheatreService>,
+    user_service: web::Data<UserService>,
+    role_service: web::Data<RoleService>,
+    bridge_role_service: web::Data<BridgeRoleService>,
+    claims: JwtClaims,
+) -> HandlerResult<PromoCode> {
+    new_promo_code.validate()?;
+
+    let theatre_id = path.into_inner();
+    let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if !user.is_super_user {
+        check_roles_or!(
+            [Role::TheatreOwner, Role::TicketManager],
+            user.id,
+            theatre_id,
+            bridge_role_service,
+            role_service
+        );
+    }
+
+    Ok(theatre_res
+        .create_promo_code(new_promo_code.into_inner())
+        .await?
+        .into())
+}
+
+/// Deletes a promo code, bookings it was already redeemed in keep their discount
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/promo_code",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || TicketManager)"),
+        (status = NOT_FOUND, description = "The selected theatre was not found"),
+        (status = OK, description = "The selected theatre was found and the PromoCode was deleted")
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID for Theatre"),
+        ("pid", description = "Unique storage ID for PromoCode")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[delete("/{pid}")]
+pub async fn delete_promo_code(
+    path: web::Path<(uuid::Uuid, uuid::Uuid)>,
+    theatre_service: web::Data<TheatreService>,
+    user_service: web::Data<UserService>,
+    role_service: web::Data<RoleService>,
+    bridge_role_service: web::Data<BridgeRoleService>,
+    claims: JwtClaims,
+) -> HandlerResult<()> {
+    let (theatre_id, promo_code_id) = path.into_inner();
+    let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if !user.is_super_user {
+        check_roles_or!(
+            [Role::TheatreOwner, Role::TicketManager],
+            user.id,
+            theatre_id,
+            bridge_role_service,
+            role_service
+        );
+    }
+
+    Ok(theatre_res.delete_promo_code(promo_code_id).await?.into())
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/promo_code")
+            .service(get_all_promo_codes)
+            .service(create_promo_code)
+            .service(delete_promo_code),
+    );
+}
@@ -0,0 +1,200 @@
+use crate::model::{
+    ExtendedScreeningSchedule, FormScreeningSchedule, ScheduledScreening, ScreeningSchedule,
+};
+
+use super::*;
+
+/// Gets all active screening schedules for a given theatre ID
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/schedule",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = NOT_FOUND, description = "The selected theatre was not found"),
+        (status = OK, description = "The selected theatre was found and its schedules were returned", body = Vec<ScreeningSchedule>)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID of Theatre")
+    )
+)]
+#[get("/all")]
+pub async fn get_screening_schedules(
+    path: web::Path<uuid::Uuid>,
+    theatre_service: web::Data<TheatreService>,
+) -> HandlerResult<Vec<ScreeningSchedule>> {
+    let theatre_id = path.into_inner();
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    Ok(theatre_res.get_screening_schedules().await?.into())
+}
+
+/// Lists the screenings a schedule would generate, without creating anything
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/schedule",
+    request_body = FormScreeningSchedule,
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || ScreeningsManager)"),
+        (status = NOT_FOUND, description = "The selected theatre, hall or movie was not found"),
+        (status = BAD_REQUEST, description = "Invalid data supplied"),
+        (status = OK, description = "The generated screenings and their overlaps were returned", body = Vec<ScheduledScreening>)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID of Theatre")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/preview")]
+pub async fn preview_screening_schedule(
+    path: web::Path<uuid::Uuid>,
+    schedule: web::Json<FormScreeningSchedule>,
+    user_service: web::Data<UserService>,
+    theatre_service: web::Data<TheatreService>,
+    bridge_role_service: web::Data<BridgeRoleService>,
+    role_service: web::Data<RoleService>,
+    claims: JwtClaims,
+) -> HandlerResult<Vec<ScheduledScreening>> {
+    schedule.validate()?;
+
+    let theatre_id = path.into_inner();
+    let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if !user.is_super_user {
+        check_roles_or!(
+            [Role::TheatreOwner, Role::ScreeningsManager],
+            user.id,
+            theatre_id,
+            bridge_role_service,
+            role_service
+        );
+    }
+
+    Ok(theatre_res
+        .preview_screening_schedule(schedule.into_inner())
+        .await?
+        .into())
+}
+
+/// Creates a new screening schedule along with all of its screenings
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/schedule",
+    request_body = FormScreeningSchedule,
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || ScreeningsManager)"),
+        (status = NOT_FOUND, description = "The selected theatre, hall or movie was not found"),
+        (status = BAD_REQUEST, description = "Invalid data supplied, the schedule generates no screenings or its screenings overlap with each other"),
+        (status = CONFLICT, description = "Some of the screenings would overlap with existing ones, their IDs are returned in the error body"),
+        (status = OK, description = "The schedule and its screenings were created and returned", body = ExtendedScreeningSchedule)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID of Theatre")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/new")]
+pub async fn create_screening_schedule(
+    path: web::Path<uuid::Uuid>,
+    schedule: web::Json<FormScreeningSchedule>,
+    user_service: web::Data<UserService>,
+    theatre_service: web::Data<TheatreService>,
+    bridge_role_service: web::Data<BridgeRoleService>,
+    role_service: web::Data<RoleService>,
+    claims: JwtClaims,
+) -> HandlerResult<ExtendedScreeningSchedule> {
+    schedule.validate()?;
+
+    let theatre_id = path.into_inner();
+    let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if !user.is_super_user {
+        check_roles_or!(
+            [Role::TheatreOwner, Role::ScreeningsManager],
+            user.id,
+            theatre_id,
+            bridge_role_service,
+            role_service
+        );
+    }
+
+    Ok(theatre_res
+        .create_screening_schedule(schedule.into_inner())
+        .await?
+        .into())
+}
+
+/// Cancels a screening schedule along with all of its screenings which haven't started yet
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/schedule",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || ScreeningsManager)"),
+        (status = NOT_FOUND, description = "The selected theatre or schedule was not found"),
+        (status = OK, description = "The schedule and its upcoming screenings were cancelled, their ticket owners are notified by email")
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID of Theatre"),
+        ("sid" = uuid::Uuid, description = "Unique storage ID of ScreeningSchedule")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[delete("/{sid}")]
+pub async fn cancel_screening_schedule(
+    path: web::Path<(uuid::Uuid, uuid::Uuid)>,
+    user_service: web::Data<UserService>,
+    theatre_service: web::Data<TheatreService>,
+    bridge_role_service: web::Data<BridgeRoleService>,
+    role_service: web::Data<RoleService>,
+    mailer_service: web::Data<Arc<Mutex<Mailer>>>,
+    claims: JwtClaims,
+) -> HandlerResult<()> {
+    let (theatre_id, schedule_id) = path.into_inner();
+    let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if !user.is_super_user {
+        check_roles_or!(
+            [Role::TheatreOwner, Role::ScreeningsManager],
+            user.id,
+            theatre_id,
+            bridge_role_service,
+            role_service
+        );
+    }
+
+    let cancellations = theatre_res.cancel_screening_schedule(schedule_id).await?;
+
+    for cancellation in cancellations.iter() {
+        queue_notification_mails(&mailer_service, cancellation.notification_mails()).await;
+    }
+
+    Ok(().into())
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/schedule")
+            .service(get_screening_schedules)
+            .service(preview_screening_schedule)
+            .service(create_screening_schedule)
+            .service(cancel_screening_schedule),
+    );
+}
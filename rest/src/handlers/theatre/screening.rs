@@ -1,13 +1,22 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use chrono::{Datelike, NaiveTime, Utc};
+use tokio::sync::broadcast::{error::RecvError, Receiver};
 use utoipa::IntoParams;
 use validator::ValidationError;
 
-use crate::model::{
-    CreateTheatreScreening, FormTheatreScreening, TheatreScreening, TheatreScreeningEvent,
+use crate::{
+    model::{
+        CreateTheatreScreening, FormTheatreScreening, PriceQuote, PriceQuoteQuery,
+        SeatAvailability, SeatEvent, TheatreScreening, TheatreScreeningEvent,
+    },
+    services::seat_event::SeatEventBus,
 };
 
+/// how often an idle seat event stream sends a comment to keep the connection open
+const SEAT_EVENT_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
 use super::*;
 
 #[derive(Deserialize, IntoParams)]
@@ -106,6 +115,146 @@ pub async fn get_theatre_screening(
     }
 }
 
+/// Fetches the availability of every seat of a given theatre screening
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/screening",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = NOT_FOUND, description = "The selected theatre or screening was not found"),
+        (status = OK, description = "The selected screening was found and the seat availability was returned", body = Vec<SeatAvailability>)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID for Theatre"),
+        ("tsid", description = "Unique storage ID for TheatreScreening")
+    )
+)]
+#[get("/{tsid}/seats")]
+pub async fn get_screening_seats(
+    path: web::Path<(uuid::Uuid, uuid::Uuid)>,
+    theatre_service: web::Data<TheatreService>,
+) -> HandlerResult<Vec<SeatAvailability>> {
+    let (theatre_id, theatre_screening_id) = path.into_inner();
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    match theatre_res
+        .get_screening_seats(theatre_screening_id)
+        .await?
+    {
+        Some(v) => Ok(v.into()),
+        None => Err(ErrorType::NotFound),
+    }
+}
+
+/// Quotes the price of a ticket for a given theatre screening, explaining
+/// which pricing rules of the ticket type it was derived with
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/screening",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = NOT_FOUND, description = "The selected theatre or screening was not found"),
+        (status = BAD_REQUEST, description = "The ticket type doesn't belong to the theatre or the seat can't be booked"),
+        (status = OK, description = "The price was quoted", body = PriceQuote)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID for Theatre"),
+        ("tsid", description = "Unique storage ID for TheatreScreening"),
+        PriceQuoteQuery
+    )
+)]
+#[get("/{tsid}/quote")]
+pub async fn quote_price(
+    path: web::Path<(uuid::Uuid, uuid::Uuid)>,
+    query: web::Query<PriceQuoteQuery>,
+    theatre_service: web::Data<TheatreService>,
+) -> HandlerResult<PriceQuote> {
+    let (theatre_id, theatre_screening_id) = path.into_inner();
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    match theatre_res
+        .quote_price(theatre_screening_id, query.into_inner())
+        .await?
+    {
+        Some(v) => Ok(v.into()),
+        None => Err(ErrorType::NotFound),
+    }
+}
+
+/// waits for the next seat event of the given screening and formats it as an SSE message,
+/// `None` once the event bus has been closed
+async fn next_seat_event_message(
+    receiver: &mut Receiver<SeatEvent>,
+    theatre_screening_id: uuid::Uuid,
+) -> Option<String> {
+    loop {
+        match tokio::time::timeout(SEAT_EVENT_KEEP_ALIVE, receiver.recv()).await {
+            Err(_) => return Some(": keep-alive\n\n".to_string()),
+            Ok(Ok(event)) if event.theatre_screening_id == theatre_screening_id => {
+                return Some(format!("data: {}\n\n", serde_json::to_string(&event).ok()?));
+            }
+            Ok(Ok(_)) => continue,
+            Ok(Err(RecvError::Lagged(skipped))) => {
+                return Some(format!("event: lagged\ndata: {}\n\n", skipped));
+            }
+            Ok(Err(RecvError::Closed)) => return None,
+        }
+    }
+}
+
+/// Streams seat status changes of a given theatre screening as Server-Sent Events
+///
+/// Every event is a JSON encoded SeatEvent. A `lagged` event is sent when the
+/// client fell too far behind and missed some changes, after which it should
+/// fetch the seats again.
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/screening",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = NOT_FOUND, description = "The selected theatre or screening was not found"),
+        (status = OK, description = "The selected screening was found and the event stream was opened", body = SeatEvent, content_type = "text/event-stream")
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID for Theatre"),
+        ("tsid", description = "Unique storage ID for TheatreScreening")
+    )
+)]
+#[get("/{tsid}/seats/events")]
+pub async fn stream_seat_events(
+    path: web::Path<(uuid::Uuid, uuid::Uuid)>,
+    theatre_service: web::Data<TheatreService>,
+    seat_events: web::Data<SeatEventBus>,
+) -> Result<HttpResponse, ErrorType> {
+    let (theatre_id, theatre_screening_id) = path.into_inner();
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if theatre_res
+        .get_theatre_screening(theatre_screening_id)
+        .await?
+        .is_none()
+    {
+        return Err(ErrorType::NotFound);
+    }
+
+    let receiver = seat_events.subscribe();
+    let events = futures_util::stream::unfold(receiver, move |mut receiver| async move {
+        let message = next_seat_event_message(&mut receiver, theatre_screening_id).await?;
+        Some((
+            Ok::<_, actix_web::Error>(web::Bytes::from(message)),
+            receiver,
+        ))
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(events))
+}
+
 /// Updates a theatre screening
 #[utoipa::path(
     context_path = "/api/v1/theatre/{id}/screening",
@@ -115,7 +264,8 @@ pub async fn get_theatre_screening(
         (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
         (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || ScreeningsManager)"),
         (status = NOT_FOUND, description = "The selected theatre was not found"),
-        (status = BAD_REQUEST, description = "Invalid data supplied"),
+        (status = BAD_REQUEST, description = "Invalid data supplied or the status can't be changed to the given one (cancelling is done through the cancel endpoint)"),
+        (status = CONFLICT, description = "The screening would overlap with other screenings in the hall, their IDs are returned in the error body"),
         (status = OK, description = "The selected theatre was found and the TheatreScreening was updated", body = TheatreScreening)
     ),
     params(
@@ -175,6 +325,7 @@ pub async fn update_theatre_screening(
         (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
         (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || ScreeningsManager)"),
         (status = NOT_FOUND, description = "The selected theatre was not found"),
+        (status = CONFLICT, description = "The screening still has valid tickets and has to be cancelled instead, their IDs are returned in the error body"),
         (status = OK, description = "The selected theatre was found and the TheatreScreening was deleted")
     ),
     params(
@@ -216,6 +367,61 @@ pub async fn delete_theatre_screening(
         .into())
 }
 
+/// Cancels a theatre screening, invalidating its tickets, recording a refund
+/// for each of them and notifying their owners by email
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/screening",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || ScreeningsManager)"),
+        (status = NOT_FOUND, description = "The selected theatre or screening was not found"),
+        (status = BAD_REQUEST, description = "The screening has already started, finished or been cancelled"),
+        (status = OK, description = "The TheatreScreening was cancelled and returned", body = TheatreScreening)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID for Theatre"),
+        ("tsid" = uuid::Uuid, description = "Unique storage ID for TheatreScreening")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/{tsid}/cancel")]
+pub async fn cancel_theatre_screening(
+    path: web::Path<(uuid::Uuid, uuid::Uuid)>,
+    user_service: web::Data<UserService>,
+    theatre_service: web::Data<TheatreService>,
+    bridge_role_service: web::Data<BridgeRoleService>,
+    role_service: web::Data<RoleService>,
+    mailer_service: web::Data<Arc<Mutex<Mailer>>>,
+    claims: JwtClaims,
+) -> HandlerResult<TheatreScreening> {
+    let (theatre_id, theatre_screening_id) = path.into_inner();
+    let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if !user.is_super_user {
+        check_roles_or!(
+            [Role::TheatreOwner, Role::ScreeningsManager],
+            user.id,
+            theatre_id,
+            bridge_role_service,
+            role_service
+        );
+    }
+
+    let cancellation = theatre_res
+        .cancel_theatre_screening(theatre_screening_id)
+        .await?;
+
+    queue_notification_mails(&mailer_service, cancellation.notification_mails()).await;
+
+    Ok(cancellation.screening.into())
+}
+
 /// Creates a new theatre screening
 #[utoipa::path(
     context_path = "/api/v1/theatre/{id}/screening",
@@ -228,7 +434,8 @@ pub async fn delete_theatre_screening(
         (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
         (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || ScreeningsManager)"),
         (status = NOT_FOUND, description = "The selected theatre was not found"),
-        (status = BAD_REQUEST, description = "Invalid data supplied"),
+        (status = BAD_REQUEST, description = "Invalid data supplied or the screening can't start off with the given status"),
+        (status = CONFLICT, description = "The screening would overlap with other screenings in the hall, or its hall was changed while it has sold tickets or held seats, the conflicting IDs are returned in the error body"),
         (status = OK, description = "The selected theatre was found and new TheatreScreening was created", body = TheatreScreening)
     ),
     security(
@@ -245,8 +452,6 @@ pub async fn create_theatre_screening(
     role_service: web::Data<RoleService>,
     claims: JwtClaims,
 ) -> HandlerResult<TheatreScreening> {
-    // TODO: implement event overlap checks
-
     let theatre_id = path.into_inner();
     let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
     let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
@@ -279,9 +484,16 @@ pub async fn create_theatre_screening(
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/screening")
+            .configure(seat_hold::config)
+            .configure(order::config)
+            .configure(loyalty::config)
             .service(get_timeline)
+            .service(get_screening_seats)
+            .service(quote_price)
+            .service(stream_seat_events)
             .service(update_theatre_screening)
             .service(delete_theatre_screening)
+            .service(cancel_theatre_screening)
             .service(create_theatre_screening)
             .service(get_theatre_screening),
     );
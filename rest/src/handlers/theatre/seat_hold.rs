@@ -0,0 +1,207 @@
+use std::collections::HashSet;
+
+use crate::{
+    model::{ExtendedOrder, FormConfirmSeatHolds, FormSeatHold, SeatHold},
+    services::{payment::PaymentService, seat_hold::SeatHoldService},
+};
+
+use super::*;
+
+/// Temporarily holds seats of a screening for the logged in user
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/screening",
+    request_body = FormSeatHold,
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = FORBIDDEN, description = "User would exceed the maximum amount of tickets for the screening"),
+        (status = NOT_FOUND, description = "The selected theatre or screening was not found"),
+        (status = BAD_REQUEST, description = "Invalid data supplied or a seat doesn't exist in the hall"),
+        (status = CONFLICT, description = "A seat has already been booked or held by someone else or the screening isn't on sale"),
+        (status = OK, description = "The seats were held and the holds were returned", body = Vec<SeatHold>)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID for Theatre"),
+        ("tsid" = uuid::Uuid, description = "Unique storage ID for TheatreScreening")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/{tsid}/hold")]
+pub async fn hold_seats(
+    path: web::Path<(uuid::Uuid, uuid::Uuid)>,
+    form: web::Json<FormSeatHold>,
+    user_service: web::Data<UserService>,
+    theatre_service: web::Data<TheatreService>,
+    seat_hold_service: web::Data<SeatHoldService>,
+    claims: JwtClaims,
+) -> HandlerResult<Vec<SeatHold>> {
+    form.validate()?;
+
+    let (theatre_id, theatre_screening_id) = path.into_inner();
+    let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if theatre_res
+        .get_theatre_screening(theatre_screening_id)
+        .await?
+        .is_none()
+    {
+        return Err(ErrorType::NotFound);
+    }
+
+    Ok(seat_hold_service
+        .hold_seats(theatre_screening_id, user.id, form.into_inner().seats)
+        .await?
+        .into())
+}
+
+/// Fetches the active seat holds of the logged in user for a screening
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/screening",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = OK, description = "The active holds were returned", body = Vec<SeatHold>)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID for Theatre"),
+        ("tsid" = uuid::Uuid, description = "Unique storage ID for TheatreScreening")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[get("/{tsid}/hold")]
+pub async fn get_seat_holds(
+    path: web::Path<(uuid::Uuid, uuid::Uuid)>,
+    user_service: web::Data<UserService>,
+    seat_hold_service: web::Data<SeatHoldService>,
+    claims: JwtClaims,
+) -> HandlerResult<Vec<SeatHold>> {
+    let (_, theatre_screening_id) = path.into_inner();
+    let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
+
+    Ok(seat_hold_service
+        .get_user_holds(theatre_screening_id, user.id)
+        .await?
+        .into())
+}
+
+/// Releases a seat hold of the logged in user
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/screening",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = NOT_FOUND, description = "The hold wasn't found among the user's holds for the selected screening"),
+        (status = OK, description = "The hold was released")
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID for Theatre"),
+        ("tsid" = uuid::Uuid, description = "Unique storage ID for TheatreScreening"),
+        ("hid" = uuid::Uuid, description = "Unique storage ID for SeatHold")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[delete("/{tsid}/hold/{hid}")]
+pub async fn release_seat_hold(
+    path: web::Path<(uuid::Uuid, uuid::Uuid, uuid::Uuid)>,
+    user_service: web::Data<UserService>,
+    seat_hold_service: web::Data<SeatHoldService>,
+    claims: JwtClaims,
+) -> HandlerResult<()> {
+    let (theatre_id, theatre_screening_id, seat_hold_id) = path.into_inner();
+    let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
+
+    Ok(seat_hold_service
+        .release(theatre_id, theatre_screening_id, seat_hold_id, user.id)
+        .await?
+        .into())
+}
+
+/// Converts seat holds of the logged in user into tickets of an order awaiting payment
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/screening",
+    request_body = FormConfirmSeatHolds,
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = FORBIDDEN, description = "A ticket type doesn't belong to the selected theatre or a membership's allowance for its current billing period is used up"),
+        (status = NOT_FOUND, description = "The selected theatre or one of the holds was not found (or the hold has expired)"),
+        (status = BAD_REQUEST, description = "Invalid data supplied, the promo or gift card code can't be redeemed for the order or a membership doesn't cover its ticket"),
+        (status = CONFLICT, description = "A seat has been booked in the meantime or the screening isn't on sale anymore"),
+        (status = OK, description = "The holds were converted and the order was returned along with its tickets and payment", body = ExtendedOrder)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID for Theatre"),
+        ("tsid" = uuid::Uuid, description = "Unique storage ID for TheatreScreening")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/{tsid}/hold/confirm")]
+pub async fn confirm_seat_holds(
+    path: web::Path<(uuid::Uuid, uuid::Uuid)>,
+    form: web::Json<FormConfirmSeatHolds>,
+    user_service: web::Data<UserService>,
+    theatre_service: web::Data<TheatreService>,
+    payment_service: web::Data<PaymentService>,
+    claims: JwtClaims,
+) -> HandlerResult<ExtendedOrder> {
+    form.validate()?;
+
+    let (theatre_id, theatre_screening_id) = path.into_inner();
+    let (user_res, _) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if theatre_res
+        .get_theatre_screening(theatre_screening_id)
+        .await?
+        .is_none()
+    {
+        return Err(ErrorType::NotFound);
+    }
+
+    let ticket_type_ids = theatre_res
+        .get_ticket_types()
+        .await?
+        .iter()
+        .map(|x| x.id)
+        .collect::<HashSet<_>>();
+
+    if form
+        .holds
+        .iter()
+        .any(|x| !ticket_type_ids.contains(&x.ticket_type_id))
+    {
+        return Err(ErrorType::InsufficientPermission);
+    }
+
+    let form = form.into_inner();
+    let order = user_res
+        .confirm_seat_holds(
+            theatre_screening_id,
+            form.holds,
+            form.promo_code,
+            form.gift_card_code,
+        )
+        .await?;
+
+    Ok(payment_service.start_payment(order).await?.into())
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(hold_seats)
+        .service(get_seat_holds)
+        .service(release_seat_hold)
+        .service(confirm_seat_holds);
+}
@@ -1,8 +1,11 @@
 use utoipa::IntoParams;
 
 use crate::{
-    model::{FormTicket, Ticket, TicketQuery},
-    services::user::TicketResource,
+    model::{
+        ExtendedTicket, FormOrderTicket, FormTicket, FormTicketScans, Refund, Ticket,
+        TicketCheckIn, TicketQuery, TicketScanResult, User, MAX_TICKETS_PER_SCREENING,
+    },
+    services::{payment::PaymentService, user::TicketResource},
 };
 
 use super::*;
@@ -12,9 +15,12 @@ pub struct NewTicketQuery {
     pub owner_id: Option<uuid::Uuid>,
 }
 
-#[derive(Deserialize, IntoParams, ToSchema)]
+#[derive(Deserialize, IntoParams, ToSchema, Validate)]
 pub struct ValidateTicketQuery {
     pub ticket_jwt: String,
+    /// Checker device which scanned the ticket, recorded in its check-in history when marking
+    #[validate(length(min = 1, max = 64))]
+    pub device_id: Option<String>,
 }
 
 async fn validate_and_get(
@@ -25,9 +31,11 @@ async fn validate_and_get(
     role_service: web::Data<RoleService>,
     bridge_role_service: web::Data<BridgeRoleService>,
     claims: JwtClaims,
-) -> std::result::Result<(TicketResource, Ticket), ErrorType> {
+) -> std::result::Result<(TicketResource, Ticket, User), ErrorType> {
+    query.validate()?;
+
     let theatre_id = theatre_id.into_inner();
-    let ticket_id = TicketResource::verify_jwt(&query.ticket_jwt)?;
+    let ticket_claims = TicketResource::verify_jwt(&query.ticket_jwt)?;
     let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
     let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
         return Err(ErrorType::NotFound);
@@ -43,15 +51,24 @@ async fn validate_and_get(
         );
     }
 
-    let Some(ticket_res) = theatre_res.get_ticket_by_id(ticket_id).await? else {
+    let Some(ticket_res) = theatre_res.get_ticket_by_id(ticket_claims.jti).await? else {
         return Err(ErrorType::ServerError);
     };
     let ticket = Ticket::from(ticket_res.clone());
+    let now = chrono::Utc::now().naive_utc();
 
-    if chrono::Utc::now().naive_utc() > ticket.expires_at {
+    if ticket.is_cancelled {
+        Err(ErrorType::Cancelled)
+    } else if ticket_res.awaits_payment().await? {
+        Err(ErrorType::PaymentRequired)
+    } else if !ticket_claims.is_current(&ticket) {
+        Err(ErrorType::Expired)
+    } else if now < ticket.valid_from {
+        Err(ErrorType::TooEarly)
+    } else if now > ticket.expires_at {
         Err(ErrorType::Expired)
     } else {
-        Ok((ticket_res, ticket))
+        Ok((ticket_res, ticket, user))
     }
 }
 
@@ -67,7 +84,7 @@ async fn validate_and_get(
         (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || TicketManager)"),
         (status = NOT_FOUND, description = "The selected theatre was not found"),
         (status = BAD_REQUEST, description = "Invalid data supplied"),
-        (status = OK, description = "The selected theatre was found and the query returned", body = Vec<Ticket>)
+        (status = OK, description = "The selected theatre was found and the query returned, along with the check-in history of each ticket", body = Vec<ExtendedTicket>)
     ),
     security(
         ("api_key" = [])
@@ -82,7 +99,7 @@ pub async fn query_tickets(
     role_service: web::Data<RoleService>,
     bridge_role_service: web::Data<BridgeRoleService>,
     claims: JwtClaims,
-) -> HandlerResult<Vec<Ticket>> {
+) -> HandlerResult<Vec<ExtendedTicket>> {
     query.validate()?;
 
     let theatre_id = path.into_inner();
@@ -110,10 +127,11 @@ pub async fn query_tickets(
     responses(
         (status = "5XX", description = "Internal server error has occurred (database/misc)"),
         (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
-        (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || TicketManager)"),
-        (status = NOT_FOUND, description = "The selected theatre was not found"),
-        (status = BAD_REQUEST, description = "Invalid data supplied"),
-        (status = OK, description = "The selected theatre was found and the ticket was created", body = Vec<Ticket>)
+        (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || TicketManager), or the membership's allowance for its current billing period is used up"),
+        (status = NOT_FOUND, description = "The selected theatre or one of its screenings was not found"),
+        (status = BAD_REQUEST, description = "Invalid data supplied, the promo or gift card code can't be redeemed for the ticket, a gift card is used for someone else's ticket or the membership doesn't cover the ticket"),
+        (status = CONFLICT, description = "The selected seat has already been booked for this screening or the screening isn't on sale"),
+        (status = OK, description = "The selected theatre was found and the ticket was created, a ticket bought for oneself belongs to an order awaiting payment", body = Ticket)
     ),
     params(
         ("id" = uuid::Uuid, description = "Unique storage ID of Theatre"),
@@ -124,11 +142,14 @@ pub async fn query_tickets(
     )
 )]
 #[post("/new")]
+#[allow(clippy::too_many_arguments)]
 pub async fn create_ticket(
     path: web::Path<uuid::Uuid>,
     query: web::Query<NewTicketQuery>,
     new_ticket: web::Json<FormTicket>,
     user_service: web::Data<UserService>,
+    theatre_service: web::Data<TheatreService>,
+    payment_service: web::Data<PaymentService>,
     role_service: web::Data<RoleService>,
     bridge_role_service: web::Data<BridgeRoleService>,
     claims: JwtClaims,
@@ -146,41 +167,71 @@ pub async fn create_ticket(
                 role_service
             );
 
+            // tickets issued by staff aren't paid for online
+            if new_ticket.gift_card_code.is_some() {
+                return Err(ErrorType::Invalid);
+            }
+
             let Some(receiver_user_res) = user_service.get_by_id(owner_id).await? else {
                 return Err(ErrorType::NotFound);
             };
 
             return Ok(Ticket::from(
                 receiver_user_res
-                    .create_ticket(new_ticket.into_inner(), issuer_user.id)
+                    .create_ticket(theatre_id, new_ticket.into_inner(), issuer_user.id)
                     .await?,
             )
             .into());
         }
     }
 
-    // TODO: implement check whether the ticket type belongs to the theatre
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if theatre_res
+        .get_theatre_screening(new_ticket.theatre_screening_id)
+        .await?
+        .is_none()
+    {
+        return Err(ErrorType::NotFound);
+    }
 
     let owned_ticket_count = issuer_user_res
         .get_tickets_count(Some(new_ticket.theatre_screening_id))
         .await?;
-    if owned_ticket_count > 3 {
+    if owned_ticket_count >= MAX_TICKETS_PER_SCREENING {
         return Err(ErrorType::InsufficientPermission);
     }
 
-    Ok(Ticket::from(
-        issuer_user_res
-            .create_ticket(new_ticket.into_inner(), issuer_user.id)
-            .await?,
-    )
-    .into())
+    // customers buy their tickets, so it's placed as an order awaiting payment
+    let new_ticket = new_ticket.into_inner();
+    let order = issuer_user_res
+        .create_order(
+            new_ticket.theatre_screening_id,
+            vec![FormOrderTicket {
+                ticket_type_id: new_ticket.ticket_type_id,
+                seat_row: new_ticket.seat_row,
+                seat_column: new_ticket.seat_column,
+                membership_subscription_id: new_ticket.membership_subscription_id,
+            }],
+            new_ticket.promo_code,
+            new_ticket.gift_card_code,
+        )
+        .await?;
+    let mut order = payment_service.start_payment(order).await?;
+
+    Ok(order.tickets.remove(0).into())
 }
 
 #[utoipa::path(
     context_path = "/api/v1/theatre/{id}/ticket",
     responses(
         (status = "5XX", description = "Internal server error has occurred (database/misc)"),
-        (status = UNAUTHORIZED, description = "User hasn't authenticated yet or ticket has expired"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet or ticket has expired (its screening has ended or it was reissued after a transfer)"),
+        (status = CONFLICT, description = "The ticket can't be checked in yet, its screening hasn't opened for entry"),
+        (status = GONE, description = "The ticket has been cancelled"),
+        (status = PAYMENT_REQUIRED, description = "The order of the ticket hasn't been paid for yet"),
         (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || TicketManager)"),
         (status = NOT_FOUND, description = "The selected theatre was not found"),
         (status = BAD_REQUEST, description = "Invalid data supplied"),
@@ -222,11 +273,14 @@ pub async fn validate(
     context_path = "/api/v1/theatre/{id}/ticket",
     responses(
         (status = "5XX", description = "Internal server error has occurred (database/misc)"),
-        (status = UNAUTHORIZED, description = "User hasn't authenticated yet or ticket has expired"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet or ticket has expired (its screening has ended or it was reissued after a transfer)"),
+        (status = CONFLICT, description = "The ticket can't be checked in yet, its screening hasn't opened for entry"),
+        (status = GONE, description = "The ticket has been cancelled"),
+        (status = PAYMENT_REQUIRED, description = "The order of the ticket hasn't been paid for yet"),
         (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || TicketManager)"),
         (status = NOT_FOUND, description = "The selected theatre was not found"),
-        (status = BAD_REQUEST, description = "Invalid data supplied"),
-        (status = OK, description = "The selected theatre was found and the ticket was validated and marked", body = Vec<Ticket>)
+        (status = BAD_REQUEST, description = "Invalid data supplied or the ticket is being unmarked without having been used"),
+        (status = OK, description = "The ticket was validated and the check-in event recorded (an entry, re-entry or undo of the latest entry) was returned", body = TicketCheckIn)
     ),
     params(
         ("id" = uuid::Uuid, description = "Unique storage ID of Theatre"),
@@ -245,10 +299,11 @@ pub async fn validate_and_mark(
     role_service: web::Data<RoleService>,
     bridge_role_service: web::Data<BridgeRoleService>,
     claims: JwtClaims,
-) -> HandlerResult<()> {
+) -> HandlerResult<TicketCheckIn> {
     let (theatre_id, state) = path.into_inner();
+    let device_id = query.device_id.clone();
 
-    let (mut ticket_res, _) = validate_and_get(
+    let (mut ticket_res, _, user) = validate_and_get(
         web::Path::from(theatre_id),
         query,
         user_service,
@@ -259,12 +314,135 @@ pub async fn validate_and_mark(
     )
     .await?;
 
-    match state {
-        true => ticket_res.mark_as_used().await?,
-        false => ticket_res.mark_as_unused().await?,
+    Ok(match state {
+        true => ticket_res.mark_as_used(user.id, device_id).await?,
+        false => ticket_res.mark_as_unused(user.id, device_id).await?,
+    }
+    .into())
+}
+
+/// Cancels a ticket on behalf of its owner regardless of the cancellation cutoff,
+/// the ticket is refunded, its seat given back and the owner notified by email
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/ticket",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || TicketManager)"),
+        (status = NOT_FOUND, description = "The selected theatre or ticket was not found"),
+        (status = BAD_REQUEST, description = "The ticket has already been used"),
+        (status = GONE, description = "The ticket has already been cancelled"),
+        (status = OK, description = "The ticket was cancelled and its refund returned, null if the ticket was free", body = Option<Refund>)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID of Theatre"),
+        ("tid" = uuid::Uuid, description = "Unique storage ID of Ticket")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/{tid}/cancel")]
+pub async fn cancel_ticket(
+    path: web::Path<(uuid::Uuid, uuid::Uuid)>,
+    user_service: web::Data<UserService>,
+    theatre_service: web::Data<TheatreService>,
+    role_service: web::Data<RoleService>,
+    bridge_role_service: web::Data<BridgeRoleService>,
+    mailer_service: web::Data<Arc<Mutex<Mailer>>>,
+    claims: JwtClaims,
+) -> HandlerResult<Option<Refund>> {
+    let (theatre_id, ticket_id) = path.into_inner();
+    let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
     };
 
-    Ok(().into())
+    if !user.is_super_user {
+        check_roles_or!(
+            [Role::TheatreOwner, Role::TicketManager],
+            user.id,
+            theatre_id,
+            bridge_role_service,
+            role_service
+        );
+    }
+
+    let Some(ticket_res) = theatre_res.get_ticket_by_id(ticket_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+    let Some(owner_res) = user_service
+        .get_by_id(Ticket::from(ticket_res).owner_user_id)
+        .await?
+    else {
+        return Err(ErrorType::NotFound);
+    };
+
+    let cancellation = owner_res.cancel_ticket(ticket_id, true).await?;
+    queue_notification_mails(&mailer_service, cancellation.notification_mails()).await;
+
+    Ok(cancellation.refund.into())
+}
+
+/// Synchronises tickets scanned by a checker device while it was offline
+///
+/// Scans are applied in the order they happened and can safely be uploaded
+/// more than once. Every scan gets an outcome, conflicts (e.g. a ticket scanned
+/// at two different doors, after it was refunded or with a token that was reissued
+/// since) are reported instead of applied.
+#[utoipa::path(
+    context_path = "/api/v1/theatre/{id}/ticket",
+    request_body = FormTicketScans,
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = FORBIDDEN, description = "User doesn't meet the required permissions (in this case TheatreOwner || TicketChecker)"),
+        (status = NOT_FOUND, description = "The selected theatre was not found"),
+        (status = BAD_REQUEST, description = "Invalid data supplied"),
+        (status = OK, description = "The scans were synchronised and their outcomes returned", body = Vec<TicketScanResult>)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID of Theatre")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/sync")]
+pub async fn sync_ticket_scans(
+    path: web::Path<uuid::Uuid>,
+    form: web::Json<FormTicketScans>,
+    user_service: web::Data<UserService>,
+    theatre_service: web::Data<TheatreService>,
+    role_service: web::Data<RoleService>,
+    bridge_role_service: web::Data<BridgeRoleService>,
+    claims: JwtClaims,
+) -> HandlerResult<Vec<TicketScanResult>> {
+    form.validate()?;
+    for scan in form.scans.iter() {
+        scan.validate()?;
+    }
+
+    let theatre_id = path.into_inner();
+    let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if !user.is_super_user {
+        check_roles_or!(
+            [Role::TheatreOwner, Role::TicketChecker],
+            user.id,
+            theatre_id,
+            bridge_role_service,
+            role_service
+        );
+    }
+
+    Ok(theatre_res
+        .sync_ticket_scans(user.id, form.into_inner().scans)
+        .await?
+        .into())
 }
 
 pub fn config(cfg: &mut web::ServiceConfig) {
@@ -273,6 +451,8 @@ pub fn config(cfg: &mut web::ServiceConfig) {
             .service(query_tickets)
             .service(create_ticket)
             .service(validate)
-            .service(validate_and_mark),
+            .service(validate_and_mark)
+            .service(cancel_ticket)
+            .service(sync_ticket_scans),
     );
 }
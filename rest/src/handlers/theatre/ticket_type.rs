@@ -55,6 +55,8 @@ pub async fn create_ticket_type(
     bridge_role_service: web::Data<BridgeRoleService>,
     claims: JwtClaims,
 ) -> HandlerResult<TicketType> {
+    new_ticket_type.validate()?;
+
     let theatre_id = path.into_inner();
     let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
     let Some(theatre_res) = theatre_service.get_by_id(theatre_id).await? else {
@@ -1,11 +1,17 @@
+use std::sync::Arc;
+
 use utoipa::IntoParams;
 
+use actix_web::http::header::{CacheControl, CacheDirective};
+
 use crate::{
     model::{
-        ExtendedMovieReview, ExtendedUserReview, FormUser, MovieReview, PartialUser, Ticket,
-        UpdateUser, UserTheatreRole,
+        ExtendedMembershipSubscription, ExtendedMovieReview, ExtendedOrder, ExtendedUserReview,
+        FormOrderPayment, FormTicketTransfer, FormUser, MovieReview, PartialUser, Payment, Refund,
+        Ticket, TicketReceipt, TicketTransfer, UpdateUser, UserLoyalty, UserTheatreRole,
     },
-    services::bridge_role::BridgeRoleService,
+    qr, receipt,
+    services::{bridge_role::BridgeRoleService, payment::PaymentService, pdf_attachment_mail},
 };
 
 use super::{theatre::role::UserRoleForm, *};
@@ -17,6 +23,20 @@ pub struct NewPasswordForm {
     pub new_password_repeat: String,
 }
 
+#[derive(Deserialize, ToSchema, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum QrFormat {
+    #[default]
+    Png,
+    Svg,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct TicketQrQuery {
+    /// image format of the QR code, PNG by default
+    pub format: Option<QrFormat>,
+}
+
 /// Fetch information about the logged in user
 #[utoipa::path(
     context_path = "/api/v1/user",
@@ -95,6 +115,554 @@ pub async fn get_self_tickets(
         .into())
 }
 
+/// Fetch the orders placed by the logged in user along with their tickets
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = OK, description = "Orders are returned, newest first", body = Vec<ExtendedOrder>)
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[get("/@me/orders")]
+pub async fn get_self_orders(
+    user_service: web::Data<UserService>,
+    claims: JwtClaims,
+) -> HandlerResult<Vec<ExtendedOrder>> {
+    let (user_res, _) = user_res_from_jwt(&claims, &user_service).await?;
+
+    Ok(user_res.get_orders().await?.into())
+}
+
+/// Fetch an order placed by the logged in user along with its tickets
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = NOT_FOUND, description = "The order wasn't found among the user's orders"),
+        (status = OK, description = "The order is returned", body = ExtendedOrder)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID of Order")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[get("/@me/orders/{id}")]
+pub async fn get_self_order(
+    path: web::Path<uuid::Uuid>,
+    user_service: web::Data<UserService>,
+    claims: JwtClaims,
+) -> HandlerResult<ExtendedOrder> {
+    let (user_res, _) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(order) = user_res.get_order_by_id(path.into_inner()).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    Ok(order.into())
+}
+
+/// Pay for a pending order of the logged in user with the given payment method
+///
+/// Once the payment goes through the order is confirmed and its tickets become usable,
+/// a declined payment cancels the order and gives its seats back
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    request_body = FormOrderPayment,
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc/payment provider)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = NOT_FOUND, description = "The order wasn't found among the user's orders"),
+        (status = BAD_REQUEST, description = "Invalid data supplied or the order isn't awaiting payment"),
+        (status = PAYMENT_REQUIRED, description = "The payment was declined and the order cancelled"),
+        (status = OK, description = "The payment went through and was returned", body = Payment)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID of Order")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/@me/orders/{id}/pay")]
+pub async fn pay_self_order(
+    path: web::Path<uuid::Uuid>,
+    form: web::Json<FormOrderPayment>,
+    user_service: web::Data<UserService>,
+    payment_service: web::Data<PaymentService>,
+    claims: JwtClaims,
+) -> HandlerResult<Payment> {
+    form.validate()?;
+
+    let (_, user) = user_res_from_jwt(&claims, &user_service).await?;
+
+    Ok(payment_service
+        .pay_order(user.id, path.into_inner(), form.into_inner().payment_method)
+        .await?
+        .into())
+}
+
+/// Render a ticket of the logged in user as a QR code of its signed token,
+/// which can be scanned by ticket checkers
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = NOT_FOUND, description = "The ticket wasn't found among the user's tickets"),
+        (status = GONE, description = "The ticket has been cancelled"),
+        (status = PAYMENT_REQUIRED, description = "The order of the ticket hasn't been paid for yet"),
+        (status = OK, description = "The QR code is returned", content(
+            ("image/png" = Vec<u8>),
+            ("image/svg+xml" = String)
+        ))
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID of Ticket"),
+        TicketQrQuery
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[get("/@me/tickets/{id}/qr")]
+pub async fn get_self_ticket_qr(
+    path: web::Path<uuid::Uuid>,
+    query: web::Query<TicketQrQuery>,
+    user_service: web::Data<UserService>,
+    claims: JwtClaims,
+) -> Result<HttpResponse, ErrorType> {
+    let (user_res, _) = user_res_from_jwt(&claims, &user_service).await?;
+    let Some(ticket_res) = user_res.get_ticket_by_id(path.into_inner()).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if Ticket::from(ticket_res.clone()).is_cancelled {
+        return Err(ErrorType::Cancelled);
+    }
+
+    if ticket_res.awaits_payment().await? {
+        return Err(ErrorType::PaymentRequired);
+    }
+
+    let Ok(ticket_jwt) = ticket_res.create_jwt() else {
+        return Err(ErrorType::ServerError);
+    };
+
+    let mut response = HttpResponse::Ok();
+    // the code is as good as the ticket itself, so it shouldn't linger in caches
+    response.insert_header(CacheControl(vec![
+        CacheDirective::Private,
+        CacheDirective::NoStore,
+    ]));
+
+    let rendered = match query.format.unwrap_or_default() {
+        QrFormat::Png => qr::render_png(ticket_jwt.as_bytes())
+            .map(|png| response.content_type("image/png").body(png)),
+        QrFormat::Svg => qr::render_svg(ticket_jwt.as_bytes())
+            .map(|svg| response.content_type("image/svg+xml").body(svg)),
+    };
+
+    rendered.map_err(|e| {
+        log::error!("Error when rendering a ticket QR code: {:?}", e);
+        ErrorType::ServerError
+    })
+}
+
+/// Renders the receipt of a ticket the user bought, unpaid tickets don't have one yet
+async fn render_self_ticket_receipt(
+    user_res: &UserResource,
+    user: &User,
+    ticket_id: uuid::Uuid,
+) -> Result<(TicketReceipt, Vec<u8>), ErrorType> {
+    let Some(ticket_res) = user_res.get_purchased_ticket_by_id(ticket_id).await? else {
+        return Err(ErrorType::NotFound);
+    };
+
+    if ticket_res.awaits_payment().await? {
+        return Err(ErrorType::PaymentRequired);
+    }
+
+    // orders which expired or whose payment was declined never got paid for
+    if !ticket_res.is_paid().await? {
+        return Err(ErrorType::Cancelled);
+    }
+
+    let receipt = ticket_res.receipt(user).await?;
+
+    // the QR code is as good as the ticket itself, so it's left out of receipts of tickets
+    // which have been cancelled or given away since
+    let ticket_jwt = if receipt.ticket.is_cancelled || receipt.ticket.owner_user_id != user.id {
+        None
+    } else {
+        let Ok(ticket_jwt) = ticket_res.create_jwt() else {
+            return Err(ErrorType::ServerError);
+        };
+
+        Some(ticket_jwt)
+    };
+
+    let pdf =
+        receipt::render_pdf(&receipt, ticket_jwt.as_deref().map(str::as_bytes)).map_err(|e| {
+            log::error!("Error when rendering a ticket receipt: {:?}", e);
+            ErrorType::ServerError
+        })?;
+
+    Ok((receipt, pdf))
+}
+
+/// Download the receipt of a ticket the logged in user bought as a PDF
+///
+/// It lists the ticket's screening, seat and price along with the taxes included in it,
+/// and the ticket's QR code unless it has been cancelled or transferred to someone else
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = NOT_FOUND, description = "The ticket wasn't found among the tickets the user bought"),
+        (status = PAYMENT_REQUIRED, description = "The order of the ticket hasn't been paid for yet"),
+        (status = GONE, description = "The order of the ticket was cancelled before it was paid for"),
+        (status = OK, description = "The receipt is returned", content(
+            ("application/pdf" = Vec<u8>)
+        ))
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID of Ticket")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[get("/@me/tickets/{id}/receipt")]
+pub async fn get_self_ticket_receipt(
+    path: web::Path<uuid::Uuid>,
+    user_service: web::Data<UserService>,
+    claims: JwtClaims,
+) -> Result<HttpResponse, ErrorType> {
+    let (user_res, user) = user_res_from_jwt(&claims, &user_service).await?;
+    let (receipt, pdf) = render_self_ticket_receipt(&user_res, &user, path.into_inner()).await?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(CacheControl(vec![
+            CacheDirective::Private,
+            CacheDirective::NoStore,
+        ]))
+        .insert_header((
+            "content-disposition",
+            format!("inline; filename=\"receipt-{}.pdf\"", receipt.ticket.id),
+        ))
+        .content_type("application/pdf")
+        .body(pdf))
+}
+
+/// Email the receipt of a ticket the logged in user bought to them
+///
+/// The receipt is sent as plain text with its PDF version attached
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = NOT_FOUND, description = "The ticket wasn't found among the tickets the user bought"),
+        (status = PAYMENT_REQUIRED, description = "The order of the ticket hasn't been paid for yet"),
+        (status = GONE, description = "The order of the ticket was cancelled before it was paid for"),
+        (status = OK, description = "The receipt was queued to be sent")
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID of Ticket")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/@me/tickets/{id}/receipt/email")]
+pub async fn email_self_ticket_receipt(
+    path: web::Path<uuid::Uuid>,
+    user_service: web::Data<UserService>,
+    mailer_service: web::Data<Arc<Mutex<Mailer>>>,
+    claims: JwtClaims,
+) -> HandlerResult<()> {
+    let (user_res, user) = user_res_from_jwt(&claims, &user_service).await?;
+    let (receipt, pdf) = render_self_ticket_receipt(&user_res, &user, path.into_inner()).await?;
+
+    let text = receipt::render_text(&receipt).map_err(|e| {
+        log::error!("Error when rendering a ticket receipt: {:?}", e);
+        ErrorType::ServerError
+    })?;
+    let message = pdf_attachment_mail(
+        &user.email,
+        &format!("Your receipt from {}", receipt.theatre_name),
+        text,
+        format!("receipt-{}.pdf", receipt.ticket.id),
+        pdf,
+    )
+    .map_err(|e| {
+        log::error!("Error when building a receipt email: {:?}", e);
+        ErrorType::ServerError
+    })?;
+
+    match mailer_service.lock().await.queue_mail(message).await {
+        Ok(v) => Ok(v.into()),
+        Err(either::Either::Left(e)) => Err(ErrorType::Database(DatabaseError::EmailSend(e))),
+        Err(either::Either::Right(_)) => Err(ErrorType::Database(DatabaseError::Other(
+            "Mailing service isn't started".to_string(),
+        ))),
+    }
+}
+
+/// Cancel a ticket of the logged in user, which is refunded and its seat given back
+///
+/// Tickets can only be cancelled up until the cancellation cutoff of the theatre,
+/// a confirmation is sent to the user by email
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = NOT_FOUND, description = "The ticket wasn't found among the user's tickets"),
+        (status = BAD_REQUEST, description = "The ticket has already been used or its order hasn't been paid for"),
+        (status = CONFLICT, description = "The cancellation cutoff of the screening has passed"),
+        (status = GONE, description = "The ticket has already been cancelled"),
+        (status = OK, description = "The ticket was cancelled and its refund returned, null if the ticket was free", body = Option<Refund>)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID of Ticket")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/@me/tickets/{id}/cancel")]
+pub async fn cancel_self_ticket(
+    path: web::Path<uuid::Uuid>,
+    user_service: web::Data<UserService>,
+    mailer_service: web::Data<Arc<Mutex<Mailer>>>,
+    claims: JwtClaims,
+) -> HandlerResult<Option<Refund>> {
+    let (user_res, _) = user_res_from_jwt(&claims, &user_service).await?;
+
+    let cancellation = user_res.cancel_ticket(path.into_inner(), false).await?;
+    queue_notification_mails(&mailer_service, cancellation.notification_mails()).await;
+
+    Ok(cancellation.refund.into())
+}
+
+/// Offer a ticket of the logged in user to another user, who is notified by email
+///
+/// The ticket stays with the logged in user until the recipient accepts the offer,
+/// a ticket can only be offered to one user at a time
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    request_body = FormTicketTransfer,
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = NOT_FOUND, description = "The ticket wasn't found among the user's tickets or the recipient doesn't exist"),
+        (status = BAD_REQUEST, description = "Invalid data supplied, the ticket has been used, has expired or hasn't been paid for, or it's being offered to its owner"),
+        (status = CONFLICT, description = "The ticket has already been offered to someone"),
+        (status = GONE, description = "The ticket has been cancelled"),
+        (status = OK, description = "The ticket was offered and the transfer returned", body = TicketTransfer)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID of Ticket")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/@me/tickets/{id}/transfer")]
+pub async fn transfer_self_ticket(
+    path: web::Path<uuid::Uuid>,
+    form: web::Json<FormTicketTransfer>,
+    user_service: web::Data<UserService>,
+    mailer_service: web::Data<Arc<Mutex<Mailer>>>,
+    claims: JwtClaims,
+) -> HandlerResult<TicketTransfer> {
+    form.validate()?;
+
+    let (user_res, _) = user_res_from_jwt(&claims, &user_service).await?;
+    let recipient = form.into_inner().recipient;
+    let Some(recipient_res) = user_service
+        .get_by_email_or_username(recipient.clone(), recipient)
+        .await?
+    else {
+        return Err(ErrorType::NotFound);
+    };
+
+    let offer = user_res
+        .offer_ticket_transfer(path.into_inner(), User::from(recipient_res))
+        .await?;
+    queue_notification_mails(&mailer_service, offer.notification_mails()).await;
+
+    Ok(offer.transfer.into())
+}
+
+/// Fetch the ticket transfers the logged in user has sent or received
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = OK, description = "Transfers are returned, newest first", body = Vec<TicketTransfer>)
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[get("/@me/transfers")]
+pub async fn get_self_transfers(
+    user_service: web::Data<UserService>,
+    claims: JwtClaims,
+) -> HandlerResult<Vec<TicketTransfer>> {
+    let (user_res, _) = user_res_from_jwt(&claims, &user_service).await?;
+
+    Ok(user_res.get_ticket_transfers().await?.into())
+}
+
+/// Accept a ticket offered to the logged in user
+///
+/// The ticket is reissued to the logged in user, so the tokens
+/// (and QR codes) of the previous owner are no longer accepted
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = NOT_FOUND, description = "The transfer wasn't found among the ones offered to the user"),
+        (status = BAD_REQUEST, description = "The transfer isn't pending anymore, the ticket has been used or has expired, or the user would exceed the maximum amount of tickets for the screening"),
+        (status = GONE, description = "The ticket has been cancelled"),
+        (status = OK, description = "The transfer was accepted and the reissued ticket returned", body = Ticket)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID of TicketTransfer")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/@me/transfers/{id}/accept")]
+pub async fn accept_self_transfer(
+    path: web::Path<uuid::Uuid>,
+    user_service: web::Data<UserService>,
+    claims: JwtClaims,
+) -> HandlerResult<Ticket> {
+    let (user_res, _) = user_res_from_jwt(&claims, &user_service).await?;
+
+    Ok(Ticket::from(user_res.accept_ticket_transfer(path.into_inner()).await?).into())
+}
+
+/// Decline a ticket offered to the logged in user
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = NOT_FOUND, description = "The transfer wasn't found among the ones offered to the user"),
+        (status = BAD_REQUEST, description = "The transfer isn't pending anymore"),
+        (status = OK, description = "The transfer was declined and returned", body = TicketTransfer)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID of TicketTransfer")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/@me/transfers/{id}/decline")]
+pub async fn decline_self_transfer(
+    path: web::Path<uuid::Uuid>,
+    user_service: web::Data<UserService>,
+    claims: JwtClaims,
+) -> HandlerResult<TicketTransfer> {
+    let (user_res, _) = user_res_from_jwt(&claims, &user_service).await?;
+
+    Ok(user_res
+        .reject_ticket_transfer(path.into_inner(), false)
+        .await?
+        .into())
+}
+
+/// Withdraw a ticket offer sent by the logged in user
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = NOT_FOUND, description = "The transfer wasn't found among the ones sent by the user"),
+        (status = BAD_REQUEST, description = "The transfer isn't pending anymore"),
+        (status = OK, description = "The transfer was cancelled and returned", body = TicketTransfer)
+    ),
+    params(
+        ("id" = uuid::Uuid, description = "Unique storage ID of TicketTransfer")
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/@me/transfers/{id}/cancel")]
+pub async fn cancel_self_transfer(
+    path: web::Path<uuid::Uuid>,
+    user_service: web::Data<UserService>,
+    claims: JwtClaims,
+) -> HandlerResult<TicketTransfer> {
+    let (user_res, _) = user_res_from_jwt(&claims, &user_service).await?;
+
+    Ok(user_res
+        .reject_ticket_transfer(path.into_inner(), true)
+        .await?
+        .into())
+}
+
+/// Fetch the loyalty points of the logged in user at every theatre along with their history
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = OK, description = "Points and their ledger are returned, newest entries first", body = UserLoyalty)
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[get("/@me/loyalty")]
+pub async fn get_self_loyalty(
+    user_service: web::Data<UserService>,
+    claims: JwtClaims,
+) -> HandlerResult<UserLoyalty> {
+    let (user_res, _) = user_res_from_jwt(&claims, &user_service).await?;
+
+    Ok(user_res.get_loyalty().await?.into())
+}
+
+/// Fetch the memberships of the logged in user along with their billing periods
+#[utoipa::path(
+    context_path = "/api/v1/user",
+    responses(
+        (status = "5XX", description = "Internal server error has occurred (database/misc)"),
+        (status = UNAUTHORIZED, description = "User hasn't authenticated yet"),
+        (status = OK, description = "Memberships are returned, newest first", body = Vec<ExtendedMembershipSubscription>)
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[get("/@me/memberships")]
+pub async fn get_self_memberships(
+    user_service: web::Data<UserService>,
+    claims: JwtClaims,
+) -> HandlerResult<Vec<ExtendedMembershipSubscription>> {
+    let (user_res, _) = user_res_from_jwt(&claims, &user_service).await?;
+
+    Ok(user_res.get_memberships().await?.into())
+}
+
 /// Fetch the posted movie reviews from the logged in user
 #[utoipa::path(
     context_path = "/api/v1/user",
@@ -230,6 +798,20 @@ pub fn config(cfg: &mut web::ServiceConfig) {
         web::scope("/user")
             .service(get_self_user)
             .service(get_self_tickets)
+            .service(get_self_ticket_qr)
+            .service(get_self_ticket_receipt)
+            .service(email_self_ticket_receipt)
+            .service(get_self_orders)
+            .service(get_self_order)
+            .service(pay_self_order)
+            .service(cancel_self_ticket)
+            .service(transfer_self_ticket)
+            .service(get_self_transfers)
+            .service(accept_self_transfer)
+            .service(decline_self_transfer)
+            .service(cancel_self_transfer)
+            .service(get_self_loyalty)
+            .service(get_self_memberships)
             .service(get_self_reviews)
             .service(update_self_user)
             .service(update_self_password)
@@ -0,0 +1,106 @@
+use std::time::Duration;
+
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio::task::JoinHandle;
+
+use crate::services::payment::PaymentService;
+use crate::services::seat_hold::{seat_hold_ttl, SeatHoldService};
+
+/// Background worker which periodically releases expired seat holds and orders
+/// which weren't paid for in time (they're held as long as seats are), along with
+/// settling the refunds of cancelled tickets with the payment provider
+pub struct HoldSweeper {
+    seat_hold_service: SeatHoldService,
+    payment_service: PaymentService,
+    interval: Duration,
+    killer: Option<Sender<()>>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl HoldSweeper {
+    pub fn new(
+        seat_hold_service: SeatHoldService,
+        payment_service: PaymentService,
+        interval: Duration,
+    ) -> Self {
+        Self {
+            seat_hold_service,
+            payment_service,
+            interval,
+            killer: None,
+            task: None,
+        }
+    }
+
+    pub async fn start(&mut self) {
+        let (killer, kill_signal) = channel::<()>(1);
+        let seat_hold_service = self.seat_hold_service.clone();
+        let payment_service = self.payment_service.clone();
+        let interval = self.interval;
+
+        self.killer = Some(killer);
+        self.task = Some(tokio::spawn(async move {
+            Self::loop_(seat_hold_service, payment_service, interval, kill_signal).await;
+            log::info!("Hold sweeping thread exited successfully");
+        }));
+    }
+
+    pub async fn stop(&mut self) {
+        log::info!("Exiting hold sweeping thread...");
+        let Some(killer) = self.killer.take() else {
+            return;
+        };
+
+        if killer.send(()).await.is_err() {
+            return;
+        }
+
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+    }
+
+    async fn loop_(
+        seat_hold_service: SeatHoldService,
+        payment_service: PaymentService,
+        interval: Duration,
+        mut kill_signal: Receiver<()>,
+    ) {
+        tokio::select! {
+            _ = Self::actual_loop(seat_hold_service, payment_service, interval) => {},
+            _ = kill_signal.recv() => {},
+        }
+    }
+
+    async fn actual_loop(
+        seat_hold_service: SeatHoldService,
+        payment_service: PaymentService,
+        interval: Duration,
+    ) {
+        let mut ticker = tokio::time::interval(interval);
+
+        log::info!("Hold sweeping thread started");
+
+        loop {
+            ticker.tick().await;
+
+            match seat_hold_service.release_expired().await {
+                Ok(0) => {}
+                Ok(released) => log::info!("Released {} expired seat holds", released),
+                Err(e) => log::error!("Error when releasing expired seat holds: {:?}", e),
+            }
+
+            match payment_service.expire_pending_orders(seat_hold_ttl()).await {
+                Ok(0) => {}
+                Ok(expired) => log::info!("Cancelled {} unpaid orders", expired),
+                Err(e) => log::error!("Error when cancelling unpaid orders: {:?}", e),
+            }
+
+            match payment_service.settle_refunds().await {
+                Ok(0) => {}
+                Ok(settled) => log::info!("Settled {} refunds", settled),
+                Err(e) => log::error!("Error when settling refunds: {:?}", e),
+            }
+        }
+    }
+}
@@ -1,21 +1,28 @@
 mod handlers;
 mod model;
 mod password;
+mod payment;
+mod qr;
+mod receipt;
 mod schema;
 mod services;
+mod ticket_keys;
 mod util;
 mod vars;
 
 mod doc;
+mod hold_sweeper;
 mod mailer;
 
 use std::sync::Arc;
 
 use actix_web::{error::ErrorImATeapot, web, App, HttpResponse, HttpServer};
+use hold_sweeper::HoldSweeper;
 use mailer::Mailer;
 use services::{
-    bridge_role::BridgeRoleService, language::LanguageService, movie::MovieService,
-    role::RoleService, theatre::TheatreService, user::UserService,
+    bridge_role::BridgeRoleService, gift_card::GiftCardService, language::LanguageService,
+    movie::MovieService, payment::PaymentService, role::RoleService, seat_event::SeatEventBus,
+    seat_hold::SeatHoldService, theatre::TheatreService, user::UserService,
 };
 use tokio::sync::Mutex;
 use util::{get_connection_pool, hash_mock_passwords};
@@ -34,12 +41,27 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let pool = get_connection_pool();
 
+    // fail early on misconfigured ticket signing keys
+    lazy_static::initialize(&ticket_keys::TICKET_KEYS);
+
     let movie_service = MovieService::new(pool.clone());
-    let theatre_service = TheatreService::new(pool.clone());
-    let user_service = UserService::new(pool.clone());
+    let seat_events = SeatEventBus::new();
+    let theatre_service = TheatreService::new(pool.clone(), seat_events.clone());
+
+    let user_service = UserService::new(pool.clone(), seat_events.clone());
     let bridge_role_service = BridgeRoleService::new(pool.clone());
     let role_service = RoleService::new(pool.clone());
     let language_service = LanguageService::new(pool.clone());
+    let seat_hold_service = SeatHoldService::new(pool.clone(), seat_events.clone());
+    let payment_provider = match payment::provider_from_env() {
+        Ok(x) => x,
+        Err(e) => {
+            log::error!("Couldn't set up payments: {}", e);
+            return Err(e.into());
+        }
+    };
+    let payment_service = PaymentService::new(pool.clone(), seat_events.clone(), payment_provider);
+    let gift_card_service = GiftCardService::new(pool.clone());
 
     let mailer = Arc::new(Mutex::new(Mailer::new(mailer::MailerConfig {
         host: "smtp.gmail.com".to_string(),
@@ -49,7 +71,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     })));
 
     mailer.lock().await.start().await;
-    
+
+    let mut hold_sweeper = HoldSweeper::new(
+        seat_hold_service.clone(),
+        payment_service.clone(),
+        std::time::Duration::from_secs(30),
+    );
+    hold_sweeper.start().await;
+
     {
         let mailer_clone = mailer.clone();
 
@@ -64,6 +93,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .app_data(web::Data::new(bridge_role_service.clone()))
                 .app_data(web::Data::new(role_service.clone()))
                 .app_data(web::Data::new(language_service.clone()))
+                .app_data(web::Data::new(seat_hold_service.clone()))
+                .app_data(web::Data::new(payment_service.clone()))
+                .app_data(web::Data::new(gift_card_service.clone()))
+                .app_data(web::Data::new(seat_events.clone()))
                 .app_data(web::Data::new(mailer_clone.clone()))
                 .service(web::scope("/api/v1").configure(handlers::config))
                 .service(
@@ -78,6 +111,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     mailer.lock().await.stop().await;
+    hold_sweeper.stop().await;
 
     Ok(())
 }
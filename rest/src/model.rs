@@ -1,12 +1,18 @@
 use actix_web::dev::Payload;
 use actix_web::{http, FromRequest, HttpRequest};
-use chrono::Utc;
+use chrono::{Datelike, Utc};
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
 use diesel::prelude::*;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Jsonb;
 use jsonwebtoken::{decode, DecodingKey, Validation};
 use serde::{Deserialize, Serialize};
 use std::future::{ready, Ready};
+use std::io::Write;
 use utoipa::{IntoParams, ToSchema};
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
 use crate::schema::*;
 use crate::util::JWT_ALGO;
@@ -112,6 +118,7 @@ pub struct LoginUser {
 #[diesel(belongs_to(User, foreign_key = owner_user_id))]
 #[diesel(belongs_to(TheatreScreening))]
 #[diesel(belongs_to(TicketType))]
+#[diesel(belongs_to(Order))]
 pub struct Ticket {
     pub id: uuid::Uuid,
     pub owner_user_id: uuid::Uuid,
@@ -123,15 +130,72 @@ pub struct Ticket {
     pub issued_at: chrono::NaiveDateTime,
     pub expires_at: chrono::NaiveDateTime,
     pub used: bool,
+    pub is_cancelled: bool,
+    pub valid_from: chrono::NaiveDateTime,
+    /// the purchase the ticket was booked in, if any
+    pub order_id: Option<uuid::Uuid>,
+    /// what the ticket was sold for, in minor units of its currency
+    pub price: i64,
+    pub currency: String,
+    /// promo code the ticket was booked with, its discount is already taken off of `price`
+    pub promo_code_id: Option<uuid::Uuid>,
+    pub discount: i64,
+    /// membership the ticket was booked with instead of being paid for
+    pub membership_subscription_id: Option<uuid::Uuid>,
+    /// rate of the taxes included in `price` in basis points, as of when the ticket was issued
+    pub tax_rate: i32,
 }
 
-#[derive(Deserialize, AsChangeset, IntoParams, ToSchema)]
-#[diesel(table_name = tickets)]
+impl Ticket {
+    pub fn price(&self) -> Result<Money, MoneyError> {
+        Money::new(self.price, &self.currency)
+    }
+
+    /// the part of the price which goes to taxes, rounded to the nearest minor unit
+    pub fn tax(&self) -> Result<Money, MoneyError> {
+        let rate = i128::from(self.tax_rate.max(0));
+        let gross = 10000 + rate;
+        let tax = (i128::from(self.price.max(0)) * rate * 2 + gross) / (gross * 2);
+
+        Money::new(i64::try_from(tax).unwrap_or(0), &self.currency)
+    }
+
+    /// loyalty points the ticket earns at the given rate per whole unit of currency,
+    /// rounded down, so that partial units don't earn anything
+    pub fn loyalty_points(&self, points_per_unit: i32) -> i32 {
+        let scale = 10i64.pow(currency_exponent(&self.currency).unwrap_or(2));
+        let points =
+            i128::from(self.price.max(0)) * i128::from(points_per_unit) / i128::from(scale);
+
+        i32::try_from(points).unwrap_or(i32::MAX)
+    }
+}
+
+/// Everything printed on a ticket's receipt
+#[derive(Debug, Clone)]
+pub struct TicketReceipt {
+    pub ticket: Ticket,
+    pub theatre_name: String,
+    pub movie_name: String,
+    pub hall_name: String,
+    pub ticket_type: String,
+    pub starting_time: chrono::NaiveDateTime,
+    /// whoever bought the ticket, who may have transferred it since
+    pub customer_name: String,
+    pub customer_email: String,
+}
+
+#[derive(Deserialize, IntoParams, ToSchema)]
 pub struct FormTicket {
     pub theatre_screening_id: uuid::Uuid,
     pub ticket_type_id: uuid::Uuid,
     pub seat_row: i32,
     pub seat_column: i32,
+    pub promo_code: Option<String>,
+    /// only for tickets users buy for themselves
+    pub gift_card_code: Option<String>,
+    /// books the seat with one of the owner's memberships instead of paying for it
+    pub membership_subscription_id: Option<uuid::Uuid>,
 }
 
 #[derive(Insertable)]
@@ -143,6 +207,425 @@ pub struct CreateTicket {
     pub issuer_user_id: uuid::Uuid,
     pub seat_row: i32,
     pub seat_column: i32,
+    pub valid_from: chrono::NaiveDateTime,
+    pub expires_at: chrono::NaiveDateTime,
+    pub order_id: Option<uuid::Uuid>,
+    pub price: i64,
+    pub currency: String,
+    pub promo_code_id: Option<uuid::Uuid>,
+    pub discount: i64,
+    pub membership_subscription_id: Option<uuid::Uuid>,
+    pub tax_rate: i32,
+}
+
+#[derive(
+    Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, AsExpression, FromSqlRow, ToSchema,
+)]
+#[diesel(sql_type = crate::schema::sql_types::OrderStatus)]
+pub enum OrderStatus {
+    /// booked, but waiting for the payment to go through
+    Pending,
+    Confirmed,
+    /// every ticket of the order has been cancelled or its payment didn't go through
+    Cancelled,
+}
+
+/// Several tickets for a screening booked (and paid for) together in one purchase
+#[derive(Selectable, Identifiable, Queryable, Serialize, Debug, Clone, Associations, ToSchema)]
+#[diesel(belongs_to(User))]
+#[diesel(belongs_to(TheatreScreening))]
+pub struct Order {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub theatre_screening_id: uuid::Uuid,
+    pub status: OrderStatus,
+    /// sum of the prices of the order's tickets, in minor units
+    pub total_price: i64,
+    pub currency: String,
+    pub created_at: chrono::NaiveDateTime,
+    /// promo code the order was placed with, its discount is already taken off of `total_price`
+    pub promo_code_id: Option<uuid::Uuid>,
+    pub discount: i64,
+    /// gift card which covers `gift_card_amount` of `total_price`
+    pub gift_card_id: Option<uuid::Uuid>,
+    pub gift_card_amount: i64,
+}
+
+impl Order {
+    pub fn total_price(&self) -> Result<Money, MoneyError> {
+        Money::new(self.total_price, &self.currency)
+    }
+
+    /// what's left to pay through the payment provider after the gift card
+    pub fn amount_due(&self) -> Result<Money, MoneyError> {
+        Money::new(self.total_price - self.gift_card_amount, &self.currency)
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = orders)]
+pub struct CreateOrder {
+    pub user_id: uuid::Uuid,
+    pub theatre_screening_id: uuid::Uuid,
+    pub status: OrderStatus,
+    pub total_price: i64,
+    pub currency: String,
+    pub promo_code_id: Option<uuid::Uuid>,
+    pub discount: i64,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, ToSchema)]
+pub struct FormOrderTicket {
+    pub ticket_type_id: uuid::Uuid,
+    pub seat_row: i32,
+    pub seat_column: i32,
+    /// books the seat with one of the user's memberships instead of paying for it
+    pub membership_subscription_id: Option<uuid::Uuid>,
+}
+
+/// how many tickets a user can own for a single screening, seats they hold included
+pub const MAX_TICKETS_PER_SCREENING: i64 = 4;
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct FormOrder {
+    // `MAX_TICKETS_PER_SCREENING`, validator only takes unsigned lengths
+    #[validate(length(min = 1, max = 4))]
+    pub tickets: Vec<FormOrderTicket>,
+    pub promo_code: Option<String>,
+    pub gift_card_code: Option<String>,
+}
+
+#[derive(Serialize, Clone, ToSchema)]
+pub struct ExtendedOrder {
+    pub order: Order,
+    pub tickets: Vec<Ticket>,
+    pub payment: Option<Payment>,
+}
+
+#[derive(
+    Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, AsExpression, FromSqlRow, ToSchema,
+)]
+#[diesel(sql_type = crate::schema::sql_types::PaymentStatus)]
+pub enum PaymentStatus {
+    RequiresConfirmation,
+    Succeeded,
+    /// the payment was declined by the provider
+    Failed,
+    /// the order was abandoned before it got paid for
+    Cancelled,
+}
+
+/// A payment intent created with the payment provider for an order
+#[derive(Selectable, Identifiable, Queryable, Serialize, Debug, Clone, Associations, ToSchema)]
+#[diesel(belongs_to(Order))]
+pub struct Payment {
+    pub id: uuid::Uuid,
+    pub order_id: uuid::Uuid,
+    /// name of the provider the intent was created with
+    pub provider: String,
+    pub intent_id: String,
+    /// handed to the client so it can confirm the payment with the provider directly
+    pub client_secret: String,
+    /// in minor units of the currency
+    pub amount: i64,
+    pub currency: String,
+    pub status: PaymentStatus,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+impl Payment {
+    pub fn amount(&self) -> Result<Money, MoneyError> {
+        Money::new(self.amount, &self.currency)
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = payments)]
+pub struct CreatePayment {
+    pub order_id: uuid::Uuid,
+    pub provider: String,
+    pub intent_id: String,
+    pub client_secret: String,
+    pub amount: i64,
+    pub currency: String,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct FormOrderPayment {
+    /// provider specific payment method, e.g. a tokenized card
+    #[validate(length(min = 1, max = 255))]
+    pub payment_method: String,
+}
+
+#[derive(
+    Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, AsExpression, FromSqlRow, ToSchema,
+)]
+#[diesel(sql_type = crate::schema::sql_types::RefundReason)]
+pub enum RefundReason {
+    ScreeningCancelled,
+    TicketCancelled,
+}
+
+/// Money owed to a user for a ticket which got cancelled,
+/// it's settled once the payment has actually been returned
+#[derive(Selectable, Identifiable, Queryable, Serialize, Debug, Clone, Associations, ToSchema)]
+#[diesel(belongs_to(User))]
+#[diesel(belongs_to(Ticket))]
+pub struct Refund {
+    pub id: uuid::Uuid,
+    pub ticket_id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    /// in minor units of the currency
+    pub amount: i64,
+    pub currency: String,
+    pub reason: RefundReason,
+    pub created_at: chrono::NaiveDateTime,
+    pub settled_at: Option<chrono::NaiveDateTime>,
+}
+
+impl Refund {
+    pub fn amount(&self) -> Result<Money, MoneyError> {
+        Money::new(self.amount, &self.currency)
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = refunds)]
+pub struct CreateRefund {
+    pub ticket_id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub amount: i64,
+    pub currency: String,
+    pub reason: RefundReason,
+}
+
+#[derive(
+    Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, AsExpression, FromSqlRow, ToSchema,
+)]
+#[diesel(sql_type = crate::schema::sql_types::CheckInAction)]
+pub enum CheckInAction {
+    Enter,
+    /// the ticket was scanned again while already being used, e.g. after stepping out
+    ReEnter,
+    /// reverts the latest entry, e.g. when a ticket was scanned by mistake
+    Undo,
+}
+
+/// A check-in event of a ticket, the latest one determines whether the ticket is used
+#[derive(Selectable, Identifiable, Queryable, Serialize, Debug, Clone, Associations, ToSchema)]
+#[diesel(belongs_to(User, foreign_key = checker_user_id))]
+#[diesel(belongs_to(Ticket))]
+pub struct TicketCheckIn {
+    pub id: uuid::Uuid,
+    pub ticket_id: uuid::Uuid,
+    pub checker_user_id: uuid::Uuid,
+    pub device_id: Option<String>,
+    pub scanned_at: chrono::NaiveDateTime,
+    pub synced_at: chrono::NaiveDateTime,
+    pub action: CheckInAction,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = ticket_check_ins)]
+pub struct CreateTicketCheckIn {
+    pub ticket_id: uuid::Uuid,
+    pub checker_user_id: uuid::Uuid,
+    pub device_id: Option<String>,
+    pub scanned_at: chrono::NaiveDateTime,
+    pub action: CheckInAction,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ExtendedTicket {
+    pub ticket: Ticket,
+    /// check-in history of the ticket, oldest first
+    pub check_ins: Vec<TicketCheckIn>,
+}
+
+#[derive(
+    Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, AsExpression, FromSqlRow, ToSchema,
+)]
+#[diesel(sql_type = crate::schema::sql_types::TicketTransferStatus)]
+pub enum TicketTransferStatus {
+    /// waiting for the recipient to accept or decline it
+    Pending,
+    Accepted,
+    Declined,
+    /// withdrawn by the sender or voided by the ticket getting cancelled
+    Cancelled,
+}
+
+/// An offer of a ticket from its owner to another user, kept around for audit once resolved
+#[derive(Selectable, Identifiable, Queryable, Serialize, Debug, Clone, Associations, ToSchema)]
+#[diesel(belongs_to(Ticket))]
+pub struct TicketTransfer {
+    pub id: uuid::Uuid,
+    pub ticket_id: uuid::Uuid,
+    pub from_user_id: uuid::Uuid,
+    pub to_user_id: uuid::Uuid,
+    pub status: TicketTransferStatus,
+    pub created_at: chrono::NaiveDateTime,
+    pub resolved_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = ticket_transfers)]
+pub struct CreateTicketTransfer {
+    pub ticket_id: uuid::Uuid,
+    pub from_user_id: uuid::Uuid,
+    pub to_user_id: uuid::Uuid,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct FormTicketTransfer {
+    /// email or username of the user the ticket is offered to
+    #[schema(example = "friend@example.com")]
+    #[validate(length(min = 1, max = 255))]
+    pub recipient: String,
+}
+
+/// A ticket scanned by a checker device while it was offline
+#[derive(Serialize, Deserialize, Validate, Clone, ToSchema)]
+pub struct TicketScan {
+    /// `jti` of the scanned ticket token
+    pub ticket_id: uuid::Uuid,
+    /// `iat` of the scanned ticket token
+    pub issued_at: i64,
+    #[validate(length(min = 1, max = 64))]
+    pub device_id: String,
+    pub scanned_at: chrono::NaiveDateTime,
+}
+
+impl TicketScan {
+    /// whether the scanned token is still the ticket's, see [`TicketClaims::is_current`]
+    pub fn is_current(&self, ticket: &Ticket) -> bool {
+        self.ticket_id == ticket.id && self.issued_at == ticket.issued_at.timestamp()
+    }
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct FormTicketScans {
+    #[validate(length(min = 1, max = 1000))]
+    pub scans: Vec<TicketScan>,
+}
+
+#[derive(Serialize, Copy, Clone, Debug, PartialEq, Eq, ToSchema)]
+pub enum TicketScanOutcome {
+    /// the ticket got checked in by this scan
+    Applied,
+    /// the very same scan has already been synced
+    AlreadyApplied,
+    /// the ticket has already been checked in by the same device,
+    /// the scan is recorded as a re-entry
+    Rescanned,
+    /// the ticket has already been checked in by another device
+    ScannedElsewhere,
+    /// the ticket has been cancelled (and refunded)
+    Cancelled,
+    /// the scanned token has been superseded, because the ticket changed hands
+    Rejected,
+    /// the scan happened outside of the ticket's validity window,
+    /// or is dated ahead of the server's clock
+    OutsideValidity,
+    /// the ticket doesn't exist or belongs to another theatre
+    NotFound,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TicketScanResult {
+    #[serde(flatten)]
+    pub scan: TicketScan,
+    pub outcome: TicketScanOutcome,
+    /// the check-in the scan was recorded as, or the entry it clashed with
+    pub check_in: Option<TicketCheckIn>,
+}
+
+#[derive(Selectable, Identifiable, Queryable, Serialize, Debug, Clone, Associations, ToSchema)]
+#[diesel(belongs_to(User))]
+#[diesel(belongs_to(TheatreScreening))]
+pub struct SeatHold {
+    pub id: uuid::Uuid,
+    pub theatre_screening_id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub seat_row: i32,
+    pub seat_column: i32,
+    pub created_at: chrono::NaiveDateTime,
+    pub expires_at: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = seat_holds)]
+pub struct CreateSeatHold {
+    pub theatre_screening_id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub seat_row: i32,
+    pub seat_column: i32,
+    pub expires_at: chrono::NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ToSchema)]
+pub struct SeatPosition {
+    pub seat_row: i32,
+    pub seat_column: i32,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct FormSeatHold {
+    #[validate(length(min = 1))]
+    pub seats: Vec<SeatPosition>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ConfirmSeatHold {
+    pub seat_hold_id: uuid::Uuid,
+    pub ticket_type_id: uuid::Uuid,
+    /// books the seat with one of the user's memberships instead of paying for it
+    pub membership_subscription_id: Option<uuid::Uuid>,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct FormConfirmSeatHolds {
+    #[validate(length(min = 1))]
+    pub holds: Vec<ConfirmSeatHold>,
+    pub promo_code: Option<String>,
+    pub gift_card_code: Option<String>,
+}
+
+#[derive(Serialize, Copy, Clone, Debug, PartialEq, Eq, ToSchema)]
+pub enum SeatStatus {
+    Free,
+    /// temporarily reserved by someone going through checkout
+    Held,
+    Sold,
+    /// the seat exists but cannot be booked
+    Unavailable,
+}
+
+#[derive(Serialize, Copy, Clone, Debug, PartialEq, Eq, ToSchema)]
+pub enum SeatEventKind {
+    Booked,
+    Released,
+    Held,
+}
+
+/// A change in the status of a seat, pushed to clients watching the screening
+#[derive(Serialize, Clone, Debug, ToSchema)]
+pub struct SeatEvent {
+    pub theatre_screening_id: uuid::Uuid,
+    pub seat_row: i32,
+    pub seat_column: i32,
+    pub kind: SeatEventKind,
+}
+
+/// Availability of a single seat of a screening,
+/// `kind` doubles as the price category of the seat
+#[derive(Serialize, Clone, Debug, ToSchema)]
+pub struct SeatAvailability {
+    pub seat_row: i32,
+    pub seat_column: i32,
+    pub kind: SeatKind,
+    pub status: SeatStatus,
 }
 
 #[derive(
@@ -168,9 +651,10 @@ pub struct TheatreScreening {
     pub audio_language_id: uuid::Uuid,
     pub starting_time: chrono::NaiveDateTime,
     pub is_3d: bool,
-    pub status: i32,
+    pub status: ScreeningStatus,
     #[serde(skip)]
     pub is_deleted: bool,
+    pub schedule_id: Option<uuid::Uuid>,
 }
 
 #[derive(Insertable, AsChangeset, Validate, ToSchema)]
@@ -183,6 +667,8 @@ pub struct CreateTheatreScreening {
     pub audio_language_id: uuid::Uuid,
     pub starting_time: chrono::NaiveDateTime,
     pub is_3d: Option<bool>,
+    pub status: Option<ScreeningStatus>,
+    pub schedule_id: Option<uuid::Uuid>,
 }
 
 #[derive(AsChangeset, Deserialize, Validate, ToSchema)]
@@ -194,6 +680,94 @@ pub struct FormTheatreScreening {
     pub audio_language_id: uuid::Uuid,
     pub starting_time: chrono::NaiveDateTime,
     pub is_3d: Option<bool>,
+    /// when left out, new screenings start off as `Scheduled`
+    /// and existing ones keep their current status
+    pub status: Option<ScreeningStatus>,
+}
+
+/// Lifecycle of a screening, tickets can only be bought while it's `OnSale`
+#[derive(
+    Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, AsExpression, FromSqlRow, ToSchema,
+)]
+#[diesel(sql_type = crate::schema::sql_types::ScreeningStatus)]
+pub enum ScreeningStatus {
+    Scheduled,
+    OnSale,
+    SoldOut,
+    Started,
+    Finished,
+    Cancelled,
+}
+
+#[derive(Selectable, Identifiable, Queryable, Serialize, Debug, Clone, Associations, ToSchema)]
+#[diesel(belongs_to(Theatre))]
+pub struct ScreeningSchedule {
+    pub id: uuid::Uuid,
+    pub movie_id: uuid::Uuid,
+    pub theatre_id: uuid::Uuid,
+    pub hall_id: uuid::Uuid,
+    pub subtitles_language_id: Option<uuid::Uuid>,
+    pub audio_language_id: uuid::Uuid,
+    pub is_3d: bool,
+    /// ISO 8601 weekday numbers, 1 being Monday
+    pub weekdays: Vec<i32>,
+    pub times_of_day: Vec<chrono::NaiveTime>,
+    pub start_date: chrono::NaiveDate,
+    pub end_date: chrono::NaiveDate,
+    #[serde(skip)]
+    pub is_deleted: bool,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = screening_schedules)]
+pub struct CreateScreeningSchedule {
+    pub movie_id: uuid::Uuid,
+    pub theatre_id: uuid::Uuid,
+    pub hall_id: uuid::Uuid,
+    pub subtitles_language_id: Option<uuid::Uuid>,
+    pub audio_language_id: uuid::Uuid,
+    pub is_3d: Option<bool>,
+    pub weekdays: Vec<i32>,
+    pub times_of_day: Vec<chrono::NaiveTime>,
+    pub start_date: chrono::NaiveDate,
+    pub end_date: chrono::NaiveDate,
+}
+
+#[derive(Deserialize, Clone, Validate, ToSchema)]
+#[validate(schema(function = "validate_screening_schedule"))]
+pub struct FormScreeningSchedule {
+    pub movie_id: uuid::Uuid,
+    pub hall_id: uuid::Uuid,
+    pub subtitles_language_id: Option<uuid::Uuid>,
+    pub audio_language_id: uuid::Uuid,
+    pub is_3d: Option<bool>,
+    /// ISO 8601 weekday numbers, 1 being Monday
+    #[validate(length(min = 1, max = 7))]
+    #[schema(example = json!([5, 6, 7]))]
+    pub weekdays: Vec<i32>,
+    #[validate(length(min = 1, max = 24))]
+    #[schema(example = json!(["18:00:00", "21:00:00"]))]
+    pub times_of_day: Vec<chrono::NaiveTime>,
+    pub start_date: chrono::NaiveDate,
+    pub end_date: chrono::NaiveDate,
+    /// status the generated screenings start off with, `Scheduled` by default
+    pub status: Option<ScreeningStatus>,
+}
+
+/// A single screening a schedule would generate
+#[derive(Serialize, ToSchema)]
+pub struct ScheduledScreening {
+    pub starting_time: chrono::NaiveDateTime,
+    /// already existing screenings the occurrence overlaps with
+    pub conflicting_screening_ids: Vec<uuid::Uuid>,
+    /// whether the occurrence overlaps with another occurrence of the same schedule
+    pub overlaps_schedule: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ExtendedScreeningSchedule {
+    pub schedule: ScreeningSchedule,
+    pub screenings: Vec<TheatreScreening>,
 }
 
 #[derive(Serialize, Queryable, ToSchema)]
@@ -207,9 +781,29 @@ pub struct TheatreScreeningEvent {
     pub movie_poster_url: Option<String>,
 }
 
-#[derive(Serialize, Deserialize)]
+/// A single cell of a hall's seat grid
+#[derive(
+    Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, AsExpression, FromSqlRow, ToSchema,
+)]
+#[diesel(sql_type = crate::schema::sql_types::SeatKind)]
+pub enum SeatKind {
+    /// no seat at all (aisles, gaps between seat blocks)
+    Aisle,
+    Standard,
+    Vip,
+    Wheelchair,
+    Companion,
+    /// a seat that exists but cannot be booked
+    Broken,
+}
+
+/// Seat layout of a hall, where `rows[seat_row][seat_column]`
+/// describes the seat at the given coordinates
+#[derive(Serialize, Deserialize, Clone, Debug, AsExpression, FromSqlRow, ToSchema)]
+#[diesel(sql_type = Jsonb)]
 pub struct SeatData {
-    data: Vec<Vec<u16>>,
+    #[schema(example = json!([["Standard", "Aisle", "Standard"], ["Wheelchair", "Aisle", "Companion"]]))]
+    pub rows: Vec<Vec<SeatKind>>,
 }
 
 #[derive(
@@ -228,9 +822,11 @@ pub struct Hall {
     pub id: uuid::Uuid,
     pub theatre_id: uuid::Uuid,
     pub name: String,
-    pub seat_data: serde_json::Value,
+    pub seat_data: SeatData,
     #[serde(skip)]
     pub is_deleted: bool,
+    /// time needed to clean the hall between two screenings
+    pub cleaning_buffer_minutes: i32,
 }
 
 #[derive(Deserialize, AsChangeset, Validate, ToSchema)]
@@ -239,7 +835,11 @@ pub struct FormHall {
     #[validate(length(max = 50))]
     #[schema(example = "Apollo 1")]
     pub name: String,
-    pub seat_data: serde_json::Value,
+    #[validate(custom(function = "validate_seat_data"))]
+    pub seat_data: SeatData,
+    #[validate(range(min = 0, max = 240))]
+    #[schema(example = 15)]
+    pub cleaning_buffer_minutes: Option<i32>,
 }
 
 #[derive(Insertable)]
@@ -247,7 +847,8 @@ pub struct FormHall {
 pub struct CreateHall {
     pub name: String,
     pub theatre_id: uuid::Uuid,
-    pub seat_data: serde_json::Value
+    pub seat_data: SeatData,
+    pub cleaning_buffer_minutes: Option<i32>,
 }
 
 #[derive(Selectable, Identifiable, Queryable, QueryableByName, Serialize, Debug, Clone, AsChangeset, ToSchema)]
@@ -260,6 +861,12 @@ pub struct Theatre {
     pub is_deleted: bool,
     pub logo_image_url: Option<String>,
     pub cover_image_url: Option<String>,
+    /// customers can cancel their tickets up until this many minutes before a screening starts
+    pub cancellation_cutoff_minutes: i32,
+    /// loyalty points earned per whole unit of currency a checked in ticket was sold for
+    pub loyalty_points_per_unit: i32,
+    /// rate of the taxes included in ticket prices, in basis points
+    pub tax_rate: i32,
 }
 
 #[derive(Serialize, Queryable, Clone, ToSchema)]
@@ -270,6 +877,9 @@ pub struct ExtendedTheatre {
     pub location_lon: f64,
     pub logo_image_url: Option<String>,
     pub cover_image_url: Option<String>,
+    pub cancellation_cutoff_minutes: i32,
+    pub loyalty_points_per_unit: i32,
+    pub tax_rate: i32,
     pub screenings_count: i64,
     pub halls_count: i64,
     pub tickets_count: i64,
@@ -291,6 +901,21 @@ pub struct FormTheatre {
     pub logo_image_url: Option<String>,
     #[validate(url)]
     pub cover_image_url: Option<String>,
+    /// how many minutes before a screening customers can no longer cancel their tickets,
+    /// defaults to 60 for new theatres and is left unchanged on updates when omitted
+    #[schema(example = 60)]
+    #[validate(range(min = 0, max = 10080))]
+    pub cancellation_cutoff_minutes: Option<i32>,
+    /// loyalty points customers earn per whole unit of currency their checked in
+    /// tickets were sold for, 0 (the default for new theatres) turns earning off
+    #[schema(example = 10)]
+    #[validate(range(min = 0, max = 10000))]
+    pub loyalty_points_per_unit: Option<i32>,
+    /// rate of the taxes included in ticket prices in basis points (e.g. 2000 for 20%),
+    /// defaults to 0 for new theatres and is left unchanged on updates when omitted
+    #[schema(example = 2000)]
+    #[validate(range(min = 0, max = 10000))]
+    pub tax_rate: Option<i32>,
 }
 
 #[derive(Selectable, Identifiable, Queryable, Serialize, Debug, Clone, AsChangeset, ToSchema)]
@@ -416,6 +1041,143 @@ pub struct UpdateMovieReview {
     pub rating: f64,
 }
 
+/// Active ISO 4217 currency codes, most of them have 2 minor units
+const ISO_4217_CURRENCIES: &[&str] = &[
+    "AED", "AFN", "ALL", "AMD", "ANG", "AOA", "ARS", "AUD", "AWG", "AZN", "BAM", "BBD", "BDT",
+    "BGN", "BHD", "BIF", "BMD", "BND", "BOB", "BOV", "BRL", "BSD", "BTN", "BWP", "BYN", "BZD",
+    "CAD", "CDF", "CHE", "CHF", "CHW", "CLF", "CLP", "CNY", "COP", "COU", "CRC", "CUC", "CUP",
+    "CVE", "CZK", "DJF", "DKK", "DOP", "DZD", "EGP", "ERN", "ETB", "EUR", "FJD", "FKP", "GBP",
+    "GEL", "GHS", "GIP", "GMD", "GNF", "GTQ", "GYD", "HKD", "HNL", "HTG", "HUF", "IDR", "ILS",
+    "INR", "IQD", "IRR", "ISK", "JMD", "JOD", "JPY", "KES", "KGS", "KHR", "KMF", "KPW", "KRW",
+    "KWD", "KYD", "KZT", "LAK", "LBP", "LKR", "LRD", "LSL", "LYD", "MAD", "MDL", "MGA", "MKD",
+    "MMK", "MNT", "MOP", "MRU", "MUR", "MVR", "MWK", "MXN", "MXV", "MYR", "MZN", "NAD", "NGN",
+    "NIO", "NOK", "NPR", "NZD", "OMR", "PAB", "PEN", "PGK", "PHP", "PKR", "PLN", "PYG", "QAR",
+    "RON", "RSD", "RUB", "RWF", "SAR", "SBD", "SCR", "SDG", "SEK", "SGD", "SHP", "SLE", "SLL",
+    "SOS", "SRD", "SSP", "STN", "SVC", "SYP", "SZL", "THB", "TJS", "TMT", "TND", "TOP", "TRY",
+    "TTD", "TWD", "TZS", "UAH", "UGX", "USD", "USN", "UYI", "UYU", "UYW", "UZS", "VED", "VES",
+    "VND", "VUV", "WST", "XAF", "XCD", "XOF", "XPF", "YER", "ZAR", "ZMW", "ZWL",
+];
+
+const ZERO_DECIMAL_CURRENCIES: &[&str] = &[
+    "BIF", "CLP", "DJF", "GNF", "ISK", "JPY", "KMF", "KRW", "PYG", "RWF", "UGX", "UYI", "VND",
+    "VUV", "XAF", "XOF", "XPF",
+];
+const THREE_DECIMAL_CURRENCIES: &[&str] = &["BHD", "IQD", "JOD", "KWD", "LYD", "OMR", "TND"];
+const FOUR_DECIMAL_CURRENCIES: &[&str] = &["CLF", "UYW"];
+
+/// Number of minor units (decimal places) of an ISO 4217 currency,
+/// `None` if the code isn't one
+pub fn currency_exponent(currency: &str) -> Option<u32> {
+    ISO_4217_CURRENCIES.binary_search(&currency).ok()?;
+
+    Some(if ZERO_DECIMAL_CURRENCIES.contains(&currency) {
+        0
+    } else if THREE_DECIMAL_CURRENCIES.contains(&currency) {
+        3
+    } else if FOUR_DECIMAL_CURRENCIES.contains(&currency) {
+        4
+    } else {
+        2
+    })
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum MoneyError {
+    #[error("{0} isn't an ISO 4217 currency code")]
+    UnknownCurrency(String),
+    #[error("amounts in {0} and {1} can't be combined")]
+    CurrencyMismatch(String, String),
+    #[error("amount is out of range")]
+    Overflow,
+}
+
+/// An exact amount of money in the minor units (e.g. cents) of an ISO 4217 currency,
+/// amounts in different currencies are never combined
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, ToSchema)]
+pub struct Money {
+    /// in minor units of the currency, e.g. 1250 for 12.50 EUR
+    amount: i64,
+    currency: String,
+}
+
+impl Money {
+    pub fn new(amount: i64, currency: &str) -> Result<Self, MoneyError> {
+        if currency_exponent(currency).is_none() {
+            return Err(MoneyError::UnknownCurrency(currency.to_owned()));
+        }
+
+        Ok(Self {
+            amount,
+            currency: currency.to_owned(),
+        })
+    }
+
+    pub fn amount(&self) -> i64 {
+        self.amount
+    }
+
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+
+    pub fn checked_add(&self, other: &Money) -> Result<Money, MoneyError> {
+        if self.currency != other.currency {
+            return Err(MoneyError::CurrencyMismatch(
+                self.currency.clone(),
+                other.currency.clone(),
+            ));
+        }
+
+        Ok(Money {
+            amount: self
+                .amount
+                .checked_add(other.amount)
+                .ok_or(MoneyError::Overflow)?,
+            currency: self.currency.clone(),
+        })
+    }
+
+    /// changes the amount by the given basis points (-2000 being 20% off),
+    /// rounding half away from zero
+    pub fn checked_change_by_basis_points(&self, basis_points: i64) -> Result<Money, MoneyError> {
+        let change = i128::from(self.amount) * i128::from(basis_points);
+        let change = (change + change.signum() * 5_000) / 10_000;
+
+        Ok(Money {
+            amount: i64::try_from(i128::from(self.amount) + change)
+                .map_err(|_| MoneyError::Overflow)?,
+            currency: self.currency.clone(),
+        })
+    }
+}
+
+impl std::fmt::Display for Money {
+    /// formats the amount in major units, e.g. "12.50 EUR" or "1500 JPY"
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let exponent = currency_exponent(&self.currency).unwrap_or(2);
+        let sign = if self.amount < 0 { "-" } else { "" };
+        let scale = 10u64.pow(exponent);
+        let (major, minor) = (
+            self.amount.unsigned_abs() / scale,
+            self.amount.unsigned_abs() % scale,
+        );
+
+        if exponent == 0 {
+            write!(f, "{}{} {}", sign, major, self.currency)
+        } else {
+            write!(
+                f,
+                "{}{}.{:0width$} {}",
+                sign,
+                major,
+                minor,
+                self.currency,
+                width = exponent as usize
+            )
+        }
+    }
+}
+
 #[derive(
     Selectable,
     Identifiable,
@@ -436,9 +1198,18 @@ pub struct TicketType {
     pub description: Option<String>,
     pub theatre_id: uuid::Uuid,
     pub currency: String,
-    pub price: f64,
+    /// in minor units of the currency, e.g. 1250 for 12.50 EUR
+    pub price: i64,
     #[serde(skip)]
     pub is_deleted: bool,
+    /// loyalty points the ticket type can be redeemed for, `None` if it can't be
+    pub loyalty_points_price: Option<i32>,
+}
+
+impl TicketType {
+    pub fn price(&self) -> Result<Money, MoneyError> {
+        Money::new(self.price, &self.currency)
+    }
 }
 
 #[derive(Insertable)]
@@ -448,34 +1219,608 @@ pub struct CreateTicketType {
     pub description: String,
     pub theatre_id: uuid::Uuid,
     pub currency: String,
-    pub price: f64,
+    pub price: i64,
+    pub loyalty_points_price: Option<i32>,
 }
 
-#[derive(Deserialize, AsChangeset, ToSchema)]
-#[diesel(table_name = ticket_types)]
+#[derive(Deserialize, AsChangeset, Validate, ToSchema)]
+#[diesel(table_name = ticket_types, treat_none_as_null = true)]
 pub struct FormTicketType {
     #[serde(alias = "type")]
     #[serde(rename(serialize = "type"))]
     pub type_: String,
     pub description: String,
+    /// ISO 4217 code of the currency
+    #[validate(custom(function = "validate_currency"))]
+    #[schema(example = "EUR")]
     pub currency: String,
-    pub price: f64,
+    /// in minor units of the currency, e.g. 1250 for 12.50 EUR
+    #[validate(range(min = 0))]
+    #[schema(example = 1250)]
+    pub price: i64,
+    /// loyalty points customers can redeem for a ticket of the type,
+    /// leave it out for ticket types which can't be redeemed
+    #[validate(range(min = 1))]
+    #[schema(example = 500)]
+    pub loyalty_points_price: Option<i32>,
+}
+
+#[derive(
+    Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, AsExpression, FromSqlRow, ToSchema,
+)]
+#[diesel(sql_type = crate::schema::sql_types::PriceAdjustment)]
+pub enum PriceAdjustment {
+    /// replaces the price with `value`
+    Set,
+    /// adds `value` (which can be negative) to the price
+    Add,
+    /// changes the price by `value` basis points, e.g. -2000 for 20% off
+    Percent,
 }
 
+/// An adjustment of a ticket type's price, which applies to a ticket
+/// when every condition it sets matches the ticket's screening and seat
 #[derive(
     Selectable,
     Identifiable,
-    Insertable,
     Queryable,
     Serialize,
-    Deserialize,
     Debug,
     Clone,
+    AsChangeset,
     Associations,
     ToSchema,
 )]
-#[diesel(belongs_to(User))]
-#[diesel(belongs_to(Theatre))]
+#[diesel(belongs_to(TicketType))]
+pub struct PricingRule {
+    pub id: uuid::Uuid,
+    pub ticket_type_id: uuid::Uuid,
+    pub name: String,
+    pub theatre_screening_id: Option<uuid::Uuid>,
+    pub seat_kind: Option<SeatKind>,
+    /// ISO 8601 weekday numbers the screening starts on (in UTC), empty for every day
+    pub weekdays: Vec<i32>,
+    /// band of the screening's starting time in UTC (until is exclusive),
+    /// which wraps around midnight when it starts later than it ends
+    pub starts_from: Option<chrono::NaiveTime>,
+    pub starts_until: Option<chrono::NaiveTime>,
+    pub is_3d: Option<bool>,
+    pub adjustment: PriceAdjustment,
+    /// minor units for `Set` and `Add`, basis points for `Percent`
+    pub value: i64,
+    /// rules are applied in ascending order
+    pub priority: i32,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = pricing_rules)]
+pub struct CreatePricingRule {
+    pub ticket_type_id: uuid::Uuid,
+    pub name: String,
+    pub theatre_screening_id: Option<uuid::Uuid>,
+    pub seat_kind: Option<SeatKind>,
+    pub weekdays: Vec<i32>,
+    pub starts_from: Option<chrono::NaiveTime>,
+    pub starts_until: Option<chrono::NaiveTime>,
+    pub is_3d: Option<bool>,
+    pub adjustment: PriceAdjustment,
+    pub value: i64,
+    pub priority: i32,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+#[validate(schema(function = "validate_pricing_rule"))]
+pub struct FormPricingRule {
+    pub ticket_type_id: uuid::Uuid,
+    #[validate(length(min = 1, max = 100))]
+    #[schema(example = "Matinee")]
+    pub name: String,
+    pub theatre_screening_id: Option<uuid::Uuid>,
+    pub seat_kind: Option<SeatKind>,
+    /// ISO 8601 weekday numbers, 1 being Monday, of the screening's starting time in UTC
+    #[validate(length(max = 7))]
+    #[schema(example = json!([1, 2, 3, 4, 5]))]
+    #[serde(default)]
+    pub weekdays: Vec<i32>,
+    /// screenings starting from this time of day in UTC, which screening times are kept in
+    #[schema(example = "10:00:00")]
+    pub starts_from: Option<chrono::NaiveTime>,
+    /// screenings starting before this time of day in UTC, a band ending earlier than
+    /// it starts wraps around midnight (e.g. 22:00 until 02:00)
+    #[schema(example = "16:00:00")]
+    pub starts_until: Option<chrono::NaiveTime>,
+    pub is_3d: Option<bool>,
+    pub adjustment: PriceAdjustment,
+    #[schema(example = -2000)]
+    pub value: i64,
+    pub priority: Option<i32>,
+}
+
+/// A pricing rule which was applied to a quoted price, and the price after it
+#[derive(Serialize, ToSchema)]
+pub struct PriceStep {
+    pub rule: PricingRule,
+    pub price: Money,
+}
+
+/// The price of a ticket for a screening, along with how it was derived
+#[derive(Serialize, ToSchema)]
+pub struct PriceQuote {
+    pub ticket_type: TicketType,
+    /// kind of the quoted seat, if one was given
+    pub seat_kind: Option<SeatKind>,
+    pub base_price: Money,
+    /// matching rules in the order they were applied
+    pub steps: Vec<PriceStep>,
+    pub price: Money,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct PriceQuoteQuery {
+    pub ticket_type_id: uuid::Uuid,
+    /// seat to quote, seat specific rules are skipped without one
+    pub seat_row: Option<i32>,
+    pub seat_column: Option<i32>,
+}
+
+#[derive(
+    Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, AsExpression, FromSqlRow, ToSchema,
+)]
+#[diesel(sql_type = crate::schema::sql_types::PromoDiscount)]
+pub enum PromoDiscount {
+    /// takes `value` basis points off of every ticket it applies to, e.g. 1500 for 15% off
+    Percent,
+    /// takes `value` minor units off of the whole booking
+    Fixed,
+}
+
+/// A code customers enter while booking to get a discount at one of the theatres
+#[derive(
+    Selectable,
+    Identifiable,
+    Queryable,
+    Serialize,
+    Debug,
+    Clone,
+    AsChangeset,
+    Associations,
+    ToSchema,
+)]
+#[diesel(belongs_to(Theatre))]
+pub struct PromoCode {
+    pub id: uuid::Uuid,
+    pub theatre_id: uuid::Uuid,
+    pub code: String,
+    pub discount: PromoDiscount,
+    pub value: i64,
+    /// currency of a `Fixed` discount
+    pub currency: Option<String>,
+    /// how many bookings the code can be redeemed in, at all and by a single user
+    pub max_uses: Option<i32>,
+    pub max_uses_per_user: Option<i32>,
+    pub valid_from: Option<chrono::NaiveDateTime>,
+    pub valid_until: Option<chrono::NaiveDateTime>,
+    /// movies and ticket types the code applies to, empty for any
+    pub movie_ids: Vec<uuid::Uuid>,
+    pub ticket_type_ids: Vec<uuid::Uuid>,
+    #[serde(skip)]
+    pub is_deleted: bool,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = promo_codes)]
+pub struct CreatePromoCode {
+    pub theatre_id: uuid::Uuid,
+    pub code: String,
+    pub discount: PromoDiscount,
+    pub value: i64,
+    pub currency: Option<String>,
+    pub max_uses: Option<i32>,
+    pub max_uses_per_user: Option<i32>,
+    pub valid_from: Option<chrono::NaiveDateTime>,
+    pub valid_until: Option<chrono::NaiveDateTime>,
+    pub movie_ids: Vec<uuid::Uuid>,
+    pub ticket_type_ids: Vec<uuid::Uuid>,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+#[validate(schema(function = "validate_promo_code"))]
+pub struct FormPromoCode {
+    /// letters, digits, dashes and underscores, it's matched case insensitively
+    #[validate(length(min = 3, max = 32))]
+    #[schema(example = "SUMMER-15")]
+    pub code: String,
+    pub discount: PromoDiscount,
+    #[schema(example = 1500)]
+    pub value: i64,
+    /// ISO 4217 code of the currency of a `Fixed` discount
+    pub currency: Option<String>,
+    #[validate(range(min = 1))]
+    pub max_uses: Option<i32>,
+    #[validate(range(min = 1))]
+    pub max_uses_per_user: Option<i32>,
+    pub valid_from: Option<chrono::NaiveDateTime>,
+    pub valid_until: Option<chrono::NaiveDateTime>,
+    #[serde(default)]
+    pub movie_ids: Vec<uuid::Uuid>,
+    #[serde(default)]
+    pub ticket_type_ids: Vec<uuid::Uuid>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ExtendedPromoCode {
+    pub promo_code: PromoCode,
+    /// bookings the code is currently redeemed in
+    pub uses: i64,
+}
+
+#[derive(
+    Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, AsExpression, FromSqlRow, ToSchema,
+)]
+#[diesel(sql_type = crate::schema::sql_types::GiftCardEntryKind)]
+pub enum GiftCardEntryKind {
+    /// the card was bought and loaded with its initial balance
+    Purchase,
+    /// part of an order was paid for with the card
+    Redemption,
+    /// money of a cancelled ticket or an order which fell through was put back on the card
+    Refund,
+}
+
+/// A stored-value card which can pay for orders at the theatre which issued it
+#[derive(
+    Selectable,
+    Identifiable,
+    Queryable,
+    Serialize,
+    Debug,
+    Clone,
+    AsChangeset,
+    Associations,
+    ToSchema,
+)]
+#[diesel(belongs_to(Theatre))]
+pub struct GiftCard {
+    pub id: uuid::Uuid,
+    pub theatre_id: uuid::Uuid,
+    #[serde(skip)]
+    pub code_hash: String,
+    /// last characters of the code, which is only shown when the card is issued
+    pub code_suffix: String,
+    pub currency: String,
+    /// in minor units of the currency, never negative
+    pub balance: i64,
+    pub issuer_user_id: uuid::Uuid,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+impl GiftCard {
+    pub const CODE_LENGTH: usize = 16;
+    /// Crockford's base 32, each character carries 5 bits
+    pub const CODE_ALPHABET: &'static [u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+    pub fn balance(&self) -> Result<Money, MoneyError> {
+        Money::new(self.balance, &self.currency)
+    }
+
+    /// codes are matched regardless of case and of the dashes they're printed with,
+    /// the letters Crockford's base 32 leaves out are read as the digits they resemble
+    pub fn normalize_code(code: &str) -> String {
+        code.chars()
+            .filter(|x| !x.is_whitespace() && *x != '-')
+            .map(|x| match x.to_ascii_uppercase() {
+                'I' | 'L' => '1',
+                'O' => '0',
+                x => x,
+            })
+            .collect::<String>()
+            .to_uppercase()
+    }
+
+    pub fn hash_code(code: &str) -> String {
+        use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+        let digest =
+            ring::digest::digest(&ring::digest::SHA256, Self::normalize_code(code).as_bytes());
+        URL_SAFE_NO_PAD.encode(digest)
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = gift_cards)]
+pub struct CreateGiftCard {
+    pub theatre_id: uuid::Uuid,
+    pub code_hash: String,
+    pub code_suffix: String,
+    pub currency: String,
+    pub balance: i64,
+    pub issuer_user_id: uuid::Uuid,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct FormGiftCard {
+    /// ISO 4217 code of the currency
+    #[validate(custom(function = "validate_currency"))]
+    #[schema(example = "EUR")]
+    pub currency: String,
+    /// initial balance in minor units of the currency
+    #[validate(range(min = 1))]
+    #[schema(example = 5000)]
+    pub amount: i64,
+}
+
+/// A freshly issued gift card along with its code, which can't be retrieved later on
+#[derive(Serialize, ToSchema)]
+pub struct IssuedGiftCard {
+    pub gift_card: GiftCard,
+    #[schema(example = "7KQ2-M9XD-4WBH-PZ3C")]
+    pub code: String,
+}
+
+#[derive(Selectable, Identifiable, Queryable, Serialize, Debug, Clone, Associations, ToSchema)]
+#[diesel(belongs_to(GiftCard))]
+#[diesel(table_name = gift_card_entries)]
+pub struct GiftCardEntry {
+    pub id: uuid::Uuid,
+    pub gift_card_id: uuid::Uuid,
+    pub kind: GiftCardEntryKind,
+    /// positive for money put on the card, negative for money taken off of it
+    pub amount: i64,
+    pub order_id: Option<uuid::Uuid>,
+    pub refund_id: Option<uuid::Uuid>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = gift_card_entries)]
+pub struct CreateGiftCardEntry {
+    pub gift_card_id: uuid::Uuid,
+    pub kind: GiftCardEntryKind,
+    pub amount: i64,
+    pub order_id: Option<uuid::Uuid>,
+    pub refund_id: Option<uuid::Uuid>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ExtendedGiftCard {
+    pub gift_card: GiftCard,
+    /// the card's ledger, oldest first
+    pub entries: Vec<GiftCardEntry>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct FormGiftCardCode {
+    #[schema(example = "7KQ2-M9XD-4WBH-PZ3C")]
+    pub code: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct GiftCardBalance {
+    /// the theatre the card can be used at
+    pub theatre_id: uuid::Uuid,
+    pub code_suffix: String,
+    pub balance: Money,
+}
+
+#[derive(
+    Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, AsExpression, FromSqlRow, ToSchema,
+)]
+#[diesel(sql_type = crate::schema::sql_types::LoyaltyEntryKind)]
+pub enum LoyaltyEntryKind {
+    /// points were earned for a checked in ticket
+    Earn,
+    /// points were taken back because the ticket's check-in was undone
+    Revoke,
+    /// points were spent on a free ticket
+    Redemption,
+    /// points of a cancelled free ticket were put back on the account
+    Refund,
+}
+
+/// A user's points at a single theatre, they're earned and redeemed at that theatre only
+#[derive(Selectable, Identifiable, Queryable, Serialize, Debug, Clone, ToSchema)]
+#[diesel(primary_key(user_id, theatre_id))]
+#[diesel(table_name = loyalty_accounts)]
+pub struct LoyaltyAccount {
+    pub user_id: uuid::Uuid,
+    pub theatre_id: uuid::Uuid,
+    pub points: i32,
+}
+
+#[derive(Selectable, Identifiable, Queryable, Serialize, Debug, Clone, ToSchema)]
+#[diesel(table_name = loyalty_entries)]
+pub struct LoyaltyEntry {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub theatre_id: uuid::Uuid,
+    pub kind: LoyaltyEntryKind,
+    /// positive for points added to the account, negative for points taken off of it
+    pub points: i32,
+    pub ticket_id: Option<uuid::Uuid>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = loyalty_entries)]
+pub struct CreateLoyaltyEntry {
+    pub user_id: uuid::Uuid,
+    pub theatre_id: uuid::Uuid,
+    pub kind: LoyaltyEntryKind,
+    pub points: i32,
+    pub ticket_id: Option<uuid::Uuid>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct UserLoyalty {
+    /// the user's points at each theatre they've earned any at
+    pub accounts: Vec<LoyaltyAccount>,
+    /// the user's ledger across all theatres, newest first
+    pub entries: Vec<LoyaltyEntry>,
+}
+
+/// A seat booked with loyalty points instead of money
+#[derive(Deserialize, ToSchema)]
+pub struct FormLoyaltyRedemption {
+    /// has to be a ticket type which can be redeemed for points
+    pub ticket_type_id: uuid::Uuid,
+    pub seat_row: i32,
+    pub seat_column: i32,
+}
+
+/// A pass sold per billing period, which lets members book tickets
+/// at the theatre without paying for them, up to an allowance
+#[derive(Selectable, Identifiable, Queryable, Serialize, Debug, Clone, Associations, ToSchema)]
+#[diesel(belongs_to(Theatre))]
+pub struct MembershipPlan {
+    pub id: uuid::Uuid,
+    pub theatre_id: uuid::Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    /// what a billing period costs, in minor units of the currency
+    pub price: i64,
+    pub currency: String,
+    pub period_days: i32,
+    /// tickets a member can book per billing period, `None` for unlimited
+    pub tickets_per_period: Option<i32>,
+    /// ticket types the plan covers, empty for any
+    pub ticket_type_ids: Vec<uuid::Uuid>,
+    #[serde(skip)]
+    pub is_deleted: bool,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+impl MembershipPlan {
+    pub fn price(&self) -> Result<Money, MoneyError> {
+        Money::new(self.price, &self.currency)
+    }
+
+    pub fn covers(&self, ticket_type_id: uuid::Uuid) -> bool {
+        self.ticket_type_ids.is_empty() || self.ticket_type_ids.contains(&ticket_type_id)
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = membership_plans)]
+pub struct CreateMembershipPlan {
+    pub theatre_id: uuid::Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub price: i64,
+    pub currency: String,
+    pub period_days: i32,
+    pub tickets_per_period: Option<i32>,
+    pub ticket_type_ids: Vec<uuid::Uuid>,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct FormMembershipPlan {
+    #[validate(length(min = 1, max = 100))]
+    #[schema(example = "Unlimited")]
+    pub name: String,
+    pub description: Option<String>,
+    /// ISO 4217 code of the currency
+    #[validate(custom(function = "validate_currency"))]
+    #[schema(example = "EUR")]
+    pub currency: String,
+    /// what a billing period costs, in minor units of the currency
+    #[validate(range(min = 0))]
+    #[schema(example = 2490)]
+    pub price: i64,
+    #[validate(range(min = 1, max = 366))]
+    #[schema(example = 30)]
+    pub period_days: i32,
+    /// leave it out for unlimited plans
+    #[validate(range(min = 1))]
+    #[schema(example = 4)]
+    pub tickets_per_period: Option<i32>,
+    #[serde(default)]
+    pub ticket_type_ids: Vec<uuid::Uuid>,
+}
+
+/// A user's membership in one of the plans, it covers bookings
+/// as long as one of its billing periods has been paid for
+#[derive(Selectable, Identifiable, Queryable, Serialize, Debug, Clone, Associations, ToSchema)]
+#[diesel(belongs_to(MembershipPlan))]
+pub struct MembershipSubscription {
+    pub id: uuid::Uuid,
+    pub membership_plan_id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub issuer_user_id: uuid::Uuid,
+    pub created_at: chrono::NaiveDateTime,
+    /// a cancelled membership isn't renewed anymore, its paid periods still cover bookings
+    pub cancelled_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = membership_subscriptions)]
+pub struct CreateMembershipSubscription {
+    pub membership_plan_id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub issuer_user_id: uuid::Uuid,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct FormMembershipSubscription {
+    pub membership_plan_id: uuid::Uuid,
+    /// the member
+    pub user_id: uuid::Uuid,
+}
+
+/// A billing period of a membership, charged at the plan's price at the time
+#[derive(Selectable, Identifiable, Queryable, Serialize, Debug, Clone, Associations, ToSchema)]
+#[diesel(belongs_to(MembershipSubscription))]
+pub struct MembershipPeriod {
+    pub id: uuid::Uuid,
+    pub membership_subscription_id: uuid::Uuid,
+    pub starts_at: chrono::NaiveDateTime,
+    pub ends_at: chrono::NaiveDateTime,
+    pub price: i64,
+    pub currency: String,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+impl MembershipPeriod {
+    pub fn contains(&self, at: chrono::NaiveDateTime) -> bool {
+        self.starts_at <= at && at < self.ends_at
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = membership_periods)]
+pub struct CreateMembershipPeriod {
+    pub membership_subscription_id: uuid::Uuid,
+    pub starts_at: chrono::NaiveDateTime,
+    pub ends_at: chrono::NaiveDateTime,
+    pub price: i64,
+    pub currency: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ExtendedMembershipSubscription {
+    pub subscription: MembershipSubscription,
+    pub plan: MembershipPlan,
+    /// the paid billing periods, oldest first
+    pub periods: Vec<MembershipPeriod>,
+    /// tickets booked with the membership in the current billing period
+    pub tickets_used: i64,
+}
+
+#[derive(
+    Selectable,
+    Identifiable,
+    Insertable,
+    Queryable,
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Associations,
+    ToSchema,
+)]
+#[diesel(belongs_to(User))]
+#[diesel(belongs_to(Theatre))]
 #[diesel(belongs_to(TheatreRole, foreign_key = role_id))]
 #[diesel(primary_key(user_id, role_id, theatre_id))]
 #[diesel(table_name = users_theatre_roles)]
@@ -511,46 +1856,451 @@ pub enum JwtType {
     User(uuid::Uuid),
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-pub struct JwtClaims {
-    pub dat: JwtType,
-    pub sub: uuid::Uuid,
-    pub iat: i64,
-    pub exp: i64,
+/// Claims of a signed ticket, they carry everything a checker device
+/// needs to accept the ticket without reaching the server
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+pub struct TicketClaims {
+    /// ID of the ticket
+    pub jti: uuid::Uuid,
+    /// ID of the ticket owner
+    pub sub: uuid::Uuid,
+    pub theatre_screening_id: uuid::Uuid,
+    pub seat_row: i32,
+    pub seat_column: i32,
+    pub iat: i64,
+    pub nbf: i64,
+    pub exp: i64,
+}
+
+impl TicketClaims {
+    /// tokens are reissued whenever a ticket changes hands,
+    /// so only the ones issued to its current owner are accepted
+    pub fn is_current(&self, ticket: &Ticket) -> bool {
+        self.jti == ticket.id
+            && self.sub == ticket.owner_user_id
+            && self.iat == ticket.issued_at.timestamp()
+    }
+}
+
+impl From<&Ticket> for TicketClaims {
+    fn from(value: &Ticket) -> Self {
+        Self {
+            jti: value.id,
+            sub: value.owner_user_id,
+            theatre_screening_id: value.theatre_screening_id,
+            seat_row: value.seat_row,
+            seat_column: value.seat_column,
+            iat: value.issued_at.timestamp(),
+            nbf: value.valid_from.timestamp(),
+            exp: value.expires_at.timestamp(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct JwtClaims {
+    pub dat: JwtType,
+    pub sub: uuid::Uuid,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+#[derive(
+    Selectable, Identifiable, Insertable, Queryable, Serialize, Deserialize, Debug, Clone, ToSchema,
+)]
+pub struct Language {
+    pub id: uuid::Uuid,
+    pub code: String,
+    pub name: String,
+}
+
+#[derive(Validate, Deserialize, ToSchema, IntoParams)]
+pub struct TicketQuery {
+    pub owner_id: Option<uuid::Uuid>,
+    pub screening_id: Option<uuid::Uuid>,
+    pub ticket_type_id: Option<uuid::Uuid>,
+    pub issuer_id: Option<uuid::Uuid>,
+    pub hall_id: Option<uuid::Uuid>,
+    pub movie_id: Option<uuid::Uuid>,
+    #[validate(range(min = 1, max = 100))]
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl Role {
+    pub fn try_from_str(value: &str) -> Option<Self> {
+        match value {
+            "TheatreOwner" => Some(Role::TheatreOwner),
+            "TicketManager" => Some(Role::TicketManager),
+            "TicketChecker" => Some(Role::TicketChecker),
+            "ScreeningsManager" => Some(Role::ScreeningsManager),
+            "UserManager" => Some(Role::UserManager),
+            _ => None,
+        }
+    }
+}
+
+impl ScreeningStatus {
+    /// statuses a screening can be created with
+    pub fn is_initial(&self) -> bool {
+        matches!(self, ScreeningStatus::Scheduled | ScreeningStatus::OnSale)
+    }
+
+    pub fn can_transition_to(&self, next: ScreeningStatus) -> bool {
+        use ScreeningStatus::*;
+
+        matches!(
+            (self, next),
+            (Scheduled, OnSale | Cancelled)
+                | (OnSale, Scheduled | SoldOut | Started | Cancelled)
+                | (SoldOut, OnSale | Started | Cancelled)
+                | (Started, Finished)
+        )
+    }
+
+    fn as_sql(&self) -> &'static str {
+        match self {
+            ScreeningStatus::Scheduled => "scheduled",
+            ScreeningStatus::OnSale => "on_sale",
+            ScreeningStatus::SoldOut => "sold_out",
+            ScreeningStatus::Started => "started",
+            ScreeningStatus::Finished => "finished",
+            ScreeningStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl FromSql<crate::schema::sql_types::ScreeningStatus, Pg> for ScreeningStatus {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"scheduled" => Ok(ScreeningStatus::Scheduled),
+            b"on_sale" => Ok(ScreeningStatus::OnSale),
+            b"sold_out" => Ok(ScreeningStatus::SoldOut),
+            b"started" => Ok(ScreeningStatus::Started),
+            b"finished" => Ok(ScreeningStatus::Finished),
+            b"cancelled" => Ok(ScreeningStatus::Cancelled),
+            _ => Err("unrecognized screening status".into()),
+        }
+    }
+}
+
+impl ToSql<crate::schema::sql_types::ScreeningStatus, Pg> for ScreeningStatus {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_sql().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<crate::schema::sql_types::RefundReason, Pg> for RefundReason {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"screening_cancelled" => Ok(RefundReason::ScreeningCancelled),
+            b"ticket_cancelled" => Ok(RefundReason::TicketCancelled),
+            _ => Err("unrecognized refund reason".into()),
+        }
+    }
+}
+
+impl ToSql<crate::schema::sql_types::RefundReason, Pg> for RefundReason {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let value: &[u8] = match self {
+            RefundReason::ScreeningCancelled => b"screening_cancelled",
+            RefundReason::TicketCancelled => b"ticket_cancelled",
+        };
+
+        out.write_all(value)?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<crate::schema::sql_types::CheckInAction, Pg> for CheckInAction {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"enter" => Ok(CheckInAction::Enter),
+            b"re_enter" => Ok(CheckInAction::ReEnter),
+            b"undo" => Ok(CheckInAction::Undo),
+            _ => Err("unrecognized check-in action".into()),
+        }
+    }
+}
+
+impl ToSql<crate::schema::sql_types::CheckInAction, Pg> for CheckInAction {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let value: &[u8] = match self {
+            CheckInAction::Enter => b"enter",
+            CheckInAction::ReEnter => b"re_enter",
+            CheckInAction::Undo => b"undo",
+        };
+
+        out.write_all(value)?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<crate::schema::sql_types::OrderStatus, Pg> for OrderStatus {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"pending" => Ok(OrderStatus::Pending),
+            b"confirmed" => Ok(OrderStatus::Confirmed),
+            b"cancelled" => Ok(OrderStatus::Cancelled),
+            _ => Err("unrecognized order status".into()),
+        }
+    }
+}
+
+impl ToSql<crate::schema::sql_types::OrderStatus, Pg> for OrderStatus {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let value: &[u8] = match self {
+            OrderStatus::Pending => b"pending",
+            OrderStatus::Confirmed => b"confirmed",
+            OrderStatus::Cancelled => b"cancelled",
+        };
+
+        out.write_all(value)?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<crate::schema::sql_types::PaymentStatus, Pg> for PaymentStatus {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"requires_confirmation" => Ok(PaymentStatus::RequiresConfirmation),
+            b"succeeded" => Ok(PaymentStatus::Succeeded),
+            b"failed" => Ok(PaymentStatus::Failed),
+            b"cancelled" => Ok(PaymentStatus::Cancelled),
+            _ => Err("unrecognized payment status".into()),
+        }
+    }
+}
+
+impl ToSql<crate::schema::sql_types::PaymentStatus, Pg> for PaymentStatus {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let value: &[u8] = match self {
+            PaymentStatus::RequiresConfirmation => b"requires_confirmation",
+            PaymentStatus::Succeeded => b"succeeded",
+            PaymentStatus::Failed => b"failed",
+            PaymentStatus::Cancelled => b"cancelled",
+        };
+
+        out.write_all(value)?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<crate::schema::sql_types::TicketTransferStatus, Pg> for TicketTransferStatus {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"pending" => Ok(TicketTransferStatus::Pending),
+            b"accepted" => Ok(TicketTransferStatus::Accepted),
+            b"declined" => Ok(TicketTransferStatus::Declined),
+            b"cancelled" => Ok(TicketTransferStatus::Cancelled),
+            _ => Err("unrecognized ticket transfer status".into()),
+        }
+    }
+}
+
+impl ToSql<crate::schema::sql_types::TicketTransferStatus, Pg> for TicketTransferStatus {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let value: &[u8] = match self {
+            TicketTransferStatus::Pending => b"pending",
+            TicketTransferStatus::Accepted => b"accepted",
+            TicketTransferStatus::Declined => b"declined",
+            TicketTransferStatus::Cancelled => b"cancelled",
+        };
+
+        out.write_all(value)?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<crate::schema::sql_types::SeatKind, Pg> for SeatKind {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"aisle" => Ok(SeatKind::Aisle),
+            b"standard" => Ok(SeatKind::Standard),
+            b"vip" => Ok(SeatKind::Vip),
+            b"wheelchair" => Ok(SeatKind::Wheelchair),
+            b"companion" => Ok(SeatKind::Companion),
+            b"broken" => Ok(SeatKind::Broken),
+            _ => Err("unrecognized seat kind".into()),
+        }
+    }
+}
+
+impl ToSql<crate::schema::sql_types::SeatKind, Pg> for SeatKind {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let value: &[u8] = match self {
+            SeatKind::Aisle => b"aisle",
+            SeatKind::Standard => b"standard",
+            SeatKind::Vip => b"vip",
+            SeatKind::Wheelchair => b"wheelchair",
+            SeatKind::Companion => b"companion",
+            SeatKind::Broken => b"broken",
+        };
+
+        out.write_all(value)?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<crate::schema::sql_types::PriceAdjustment, Pg> for PriceAdjustment {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"set" => Ok(PriceAdjustment::Set),
+            b"add" => Ok(PriceAdjustment::Add),
+            b"percent" => Ok(PriceAdjustment::Percent),
+            _ => Err("unrecognized price adjustment".into()),
+        }
+    }
+}
+
+impl ToSql<crate::schema::sql_types::PriceAdjustment, Pg> for PriceAdjustment {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let value: &[u8] = match self {
+            PriceAdjustment::Set => b"set",
+            PriceAdjustment::Add => b"add",
+            PriceAdjustment::Percent => b"percent",
+        };
+
+        out.write_all(value)?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<crate::schema::sql_types::PromoDiscount, Pg> for PromoDiscount {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"percent" => Ok(PromoDiscount::Percent),
+            b"fixed" => Ok(PromoDiscount::Fixed),
+            _ => Err("unrecognized promo discount".into()),
+        }
+    }
+}
+
+impl ToSql<crate::schema::sql_types::PromoDiscount, Pg> for PromoDiscount {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let value: &[u8] = match self {
+            PromoDiscount::Percent => b"percent",
+            PromoDiscount::Fixed => b"fixed",
+        };
+
+        out.write_all(value)?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<crate::schema::sql_types::GiftCardEntryKind, Pg> for GiftCardEntryKind {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"purchase" => Ok(GiftCardEntryKind::Purchase),
+            b"redemption" => Ok(GiftCardEntryKind::Redemption),
+            b"refund" => Ok(GiftCardEntryKind::Refund),
+            _ => Err("unrecognized gift card entry kind".into()),
+        }
+    }
+}
+
+impl ToSql<crate::schema::sql_types::GiftCardEntryKind, Pg> for GiftCardEntryKind {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let value: &[u8] = match self {
+            GiftCardEntryKind::Purchase => b"purchase",
+            GiftCardEntryKind::Redemption => b"redemption",
+            GiftCardEntryKind::Refund => b"refund",
+        };
+
+        out.write_all(value)?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<crate::schema::sql_types::LoyaltyEntryKind, Pg> for LoyaltyEntryKind {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"earn" => Ok(LoyaltyEntryKind::Earn),
+            b"revoke" => Ok(LoyaltyEntryKind::Revoke),
+            b"redemption" => Ok(LoyaltyEntryKind::Redemption),
+            b"refund" => Ok(LoyaltyEntryKind::Refund),
+            _ => Err("unrecognized loyalty entry kind".into()),
+        }
+    }
+}
+
+impl ToSql<crate::schema::sql_types::LoyaltyEntryKind, Pg> for LoyaltyEntryKind {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let value: &[u8] = match self {
+            LoyaltyEntryKind::Earn => b"earn",
+            LoyaltyEntryKind::Revoke => b"revoke",
+            LoyaltyEntryKind::Redemption => b"redemption",
+            LoyaltyEntryKind::Refund => b"refund",
+        };
+
+        out.write_all(value)?;
+        Ok(IsNull::No)
+    }
+}
+
+impl SeatKind {
+    pub fn is_bookable(&self) -> bool {
+        !matches!(self, SeatKind::Aisle | SeatKind::Broken)
+    }
+}
+
+impl SeatData {
+    pub const MAX_ROWS: usize = 100;
+    pub const MAX_COLUMNS: usize = 100;
+
+    /// fetches the kind of the cell at the given coordinates,
+    /// `None` if they're outside of the grid
+    pub fn get(&self, row: i32, column: i32) -> Option<SeatKind> {
+        let row = usize::try_from(row).ok()?;
+        let column = usize::try_from(column).ok()?;
+
+        self.rows.get(row)?.get(column).copied()
+    }
+
+    pub fn is_bookable(&self, row: i32, column: i32) -> bool {
+        self.get(row, column).is_some_and(|x| x.is_bookable())
+    }
 }
 
-#[derive(
-    Selectable, Identifiable, Insertable, Queryable, Serialize, Deserialize, Debug, Clone, ToSchema,
-)]
-pub struct Language {
-    pub id: uuid::Uuid,
-    pub code: String,
-    pub name: String,
+fn validate_currency(currency: &str) -> Result<(), ValidationError> {
+    match currency_exponent(currency) {
+        Some(_) => Ok(()),
+        None => Err(ValidationError::new("unknown_currency")),
+    }
 }
 
-#[derive(Validate, Deserialize, ToSchema, IntoParams)]
-pub struct TicketQuery {
-    pub owner_id: Option<uuid::Uuid>,
-    pub screening_id: Option<uuid::Uuid>,
-    pub ticket_type_id: Option<uuid::Uuid>,
-    pub issuer_id: Option<uuid::Uuid>,
-    pub hall_id: Option<uuid::Uuid>,
-    pub movie_id: Option<uuid::Uuid>,
-    #[validate(range(min = 1, max = 100))]
-    pub limit: i64,
-    pub offset: i64,
+fn validate_seat_data(seat_data: &SeatData) -> Result<(), ValidationError> {
+    let Some(first_row) = seat_data.rows.first() else {
+        return Err(ValidationError::new("seat_data_empty"));
+    };
+
+    if seat_data.rows.len() > SeatData::MAX_ROWS || first_row.len() > SeatData::MAX_COLUMNS {
+        return Err(ValidationError::new("seat_data_too_large"));
+    }
+
+    if first_row.is_empty() || seat_data.rows.iter().any(|x| x.len() != first_row.len()) {
+        return Err(ValidationError::new("seat_data_not_rectangular"));
+    }
+
+    if !seat_data.rows.iter().flatten().any(|x| x.is_bookable()) {
+        return Err(ValidationError::new("seat_data_no_seats"));
+    }
+
+    Ok(())
 }
 
-impl Role {
-    pub fn try_from_str(value: &str) -> Option<Self> {
-        match value {
-            "TheatreOwner" => Some(Role::TheatreOwner),
-            "TicketManager" => Some(Role::TicketManager),
-            "TicketChecker" => Some(Role::TicketChecker),
-            "ScreeningsManager" => Some(Role::ScreeningsManager),
-            "UserManager" => Some(Role::UserManager),
-            _ => None,
-        }
+impl FromSql<Jsonb, Pg> for SeatData {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        let value = <serde_json::Value as FromSql<Jsonb, Pg>>::from_sql(bytes)?;
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+impl ToSql<Jsonb, Pg> for SeatData {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let value = serde_json::to_value(self)?;
+        <serde_json::Value as ToSql<Jsonb, Pg>>::to_sql(&value, &mut out.reborrow())
     }
 }
 
@@ -630,10 +2380,248 @@ impl CreateTheatreScreening {
             audio_language_id: value.audio_language_id,
             starting_time: value.starting_time,
             is_3d: value.is_3d,
+            status: value.status,
+            schedule_id: None,
+        }
+    }
+}
+
+impl CreateScreeningSchedule {
+    pub fn from_form(value: FormScreeningSchedule, theatre_id: uuid::Uuid) -> Self {
+        Self {
+            movie_id: value.movie_id,
+            theatre_id,
+            hall_id: value.hall_id,
+            subtitles_language_id: value.subtitles_language_id,
+            audio_language_id: value.audio_language_id,
+            is_3d: value.is_3d,
+            weekdays: value.weekdays,
+            times_of_day: value.times_of_day,
+            start_date: value.start_date,
+            end_date: value.end_date,
+        }
+    }
+}
+
+impl FormScreeningSchedule {
+    pub const MAX_DAYS: i64 = 366;
+
+    /// expands the schedule into the starting times of all of its screenings
+    pub fn occurrences(&self) -> Vec<chrono::NaiveDateTime> {
+        let mut times_of_day = self.times_of_day.clone();
+        times_of_day.sort();
+        times_of_day.dedup();
+
+        self.start_date
+            .iter_days()
+            .take_while(|x| *x <= self.end_date)
+            .filter(|x| {
+                self.weekdays
+                    .contains(&(x.weekday().number_from_monday() as i32))
+            })
+            .flat_map(|x| times_of_day.iter().map(move |t| x.and_time(*t)))
+            .collect()
+    }
+}
+
+fn validate_screening_schedule(form: &FormScreeningSchedule) -> Result<(), ValidationError> {
+    if form.weekdays.iter().any(|x| !(1..=7).contains(x)) {
+        return Err(ValidationError::new("schedule_invalid_weekday"));
+    }
+
+    if form.end_date < form.start_date {
+        return Err(ValidationError::new("schedule_end_before_start"));
+    }
+
+    if form.status.is_some_and(|x| !x.is_initial()) {
+        return Err(ValidationError::new("schedule_invalid_status"));
+    }
+
+    if (form.end_date - form.start_date).num_days() >= FormScreeningSchedule::MAX_DAYS {
+        return Err(ValidationError::new("schedule_too_long"));
+    }
+
+    Ok(())
+}
+
+impl PricingRule {
+    /// whether the rule applies to a ticket for the screening, seat specific rules never
+    /// apply without a seat. Weekdays and time bands are matched in UTC, which screenings'
+    /// starting times are stored in
+    pub fn matches(&self, screening: &TheatreScreening, seat_kind: Option<SeatKind>) -> bool {
+        let weekday = screening.starting_time.weekday().number_from_monday() as i32;
+        let time = screening.starting_time.time();
+
+        let in_band = match (self.starts_from, self.starts_until) {
+            (Some(from), Some(until)) if from <= until => from <= time && time < until,
+            (Some(from), Some(until)) => from <= time || time < until,
+            _ => true,
+        };
+
+        self.theatre_screening_id.is_none_or(|x| x == screening.id)
+            && self.seat_kind.is_none_or(|x| Some(x) == seat_kind)
+            && (self.weekdays.is_empty() || self.weekdays.contains(&weekday))
+            && in_band
+            && self.is_3d.is_none_or(|x| x == screening.is_3d)
+    }
+
+    /// adjusts the price, which never drops below zero
+    pub fn apply(&self, price: &Money) -> Result<Money, MoneyError> {
+        let adjusted = match self.adjustment {
+            PriceAdjustment::Set => Money::new(self.value, price.currency())?,
+            PriceAdjustment::Add => {
+                price.checked_add(&Money::new(self.value, price.currency())?)?
+            }
+            PriceAdjustment::Percent => price.checked_change_by_basis_points(self.value)?,
+        };
+
+        Money::new(adjusted.amount().max(0), adjusted.currency())
+    }
+}
+
+impl CreatePricingRule {
+    pub fn from_form(value: FormPricingRule) -> Self {
+        Self {
+            ticket_type_id: value.ticket_type_id,
+            name: value.name,
+            theatre_screening_id: value.theatre_screening_id,
+            seat_kind: value.seat_kind,
+            weekdays: value.weekdays,
+            starts_from: value.starts_from,
+            starts_until: value.starts_until,
+            is_3d: value.is_3d,
+            adjustment: value.adjustment,
+            value: value.value,
+            priority: value.priority.unwrap_or(0),
+        }
+    }
+}
+
+fn validate_pricing_rule(form: &FormPricingRule) -> Result<(), ValidationError> {
+    if form.weekdays.iter().any(|x| !(1..=7).contains(x)) {
+        return Err(ValidationError::new("pricing_rule_invalid_weekday"));
+    }
+
+    if form.starts_from.is_some() != form.starts_until.is_some() {
+        return Err(ValidationError::new("pricing_rule_incomplete_time_band"));
+    }
+
+    let valid_value = match form.adjustment {
+        PriceAdjustment::Set => form.value >= 0,
+        PriceAdjustment::Add => true,
+        PriceAdjustment::Percent => form.value >= -10_000,
+    };
+
+    if !valid_value {
+        return Err(ValidationError::new("pricing_rule_invalid_value"));
+    }
+
+    Ok(())
+}
+
+impl PromoCode {
+    /// promo codes are matched case insensitively, so they're kept in upper case
+    pub fn normalize_code(code: &str) -> String {
+        code.trim().to_uppercase()
+    }
+
+    pub fn is_valid_at(&self, time: chrono::NaiveDateTime) -> bool {
+        self.valid_from.is_none_or(|x| x <= time) && self.valid_until.is_none_or(|x| time < x)
+    }
+
+    pub fn applies_to(&self, movie_id: uuid::Uuid, ticket_type_id: uuid::Uuid) -> bool {
+        (self.movie_ids.is_empty() || self.movie_ids.contains(&movie_id))
+            && (self.ticket_type_ids.is_empty() || self.ticket_type_ids.contains(&ticket_type_id))
+    }
+
+    /// Splits the code's discount over a booking's ticket prices, `None` for the tickets it
+    /// doesn't apply to. A fixed discount is taken off of the tickets in order, none of
+    /// which drop below zero, and can't be used for prices in another currency
+    pub fn discounts(&self, prices: &[Option<&Money>]) -> Result<Vec<i64>, MoneyError> {
+        let mut remaining = self.value;
+        let mut discounts = vec![];
+
+        for price in prices {
+            let Some(price) = price else {
+                discounts.push(0);
+                continue;
+            };
+
+            let discount = match self.discount {
+                PromoDiscount::Percent => {
+                    price.amount() - price.checked_change_by_basis_points(-self.value)?.amount()
+                }
+                PromoDiscount::Fixed => {
+                    let currency = self.currency.clone().unwrap_or_default();
+                    if currency != price.currency() {
+                        return Err(MoneyError::CurrencyMismatch(
+                            currency,
+                            price.currency().to_owned(),
+                        ));
+                    }
+
+                    remaining.min(price.amount())
+                }
+            };
+
+            remaining -= discount;
+            discounts.push(discount);
+        }
+
+        Ok(discounts)
+    }
+}
+
+impl CreatePromoCode {
+    pub fn from_form(value: FormPromoCode, theatre_id: uuid::Uuid) -> Self {
+        Self {
+            theatre_id,
+            code: PromoCode::normalize_code(&value.code),
+            discount: value.discount,
+            value: value.value,
+            currency: value.currency,
+            max_uses: value.max_uses,
+            max_uses_per_user: value.max_uses_per_user,
+            valid_from: value.valid_from,
+            valid_until: value.valid_until,
+            movie_ids: value.movie_ids,
+            ticket_type_ids: value.ticket_type_ids,
         }
     }
 }
 
+fn validate_promo_code(form: &FormPromoCode) -> Result<(), ValidationError> {
+    if !form
+        .code
+        .trim()
+        .chars()
+        .all(|x| x.is_ascii_alphanumeric() || x == '-' || x == '_')
+    {
+        return Err(ValidationError::new("promo_code_invalid_code"));
+    }
+
+    if let Some(currency) = &form.currency {
+        validate_currency(currency)?;
+    }
+
+    let valid_value = match form.discount {
+        PromoDiscount::Percent => (1..=10_000).contains(&form.value) && form.currency.is_none(),
+        PromoDiscount::Fixed => form.value > 0 && form.currency.is_some(),
+    };
+
+    if !valid_value {
+        return Err(ValidationError::new("promo_code_invalid_value"));
+    }
+
+    if let (Some(from), Some(until)) = (form.valid_from, form.valid_until) {
+        if until <= from {
+            return Err(ValidationError::new("promo_code_ends_before_start"));
+        }
+    }
+
+    Ok(())
+}
+
 impl CreateTicketType {
     pub fn from_form(value: FormTicketType, theatre_id: uuid::Uuid) -> Self {
         Self {
@@ -642,6 +2630,22 @@ impl CreateTicketType {
             theatre_id,
             currency: value.currency,
             price: value.price,
+            loyalty_points_price: value.loyalty_points_price,
+        }
+    }
+}
+
+impl CreateMembershipPlan {
+    pub fn from_form(value: FormMembershipPlan, theatre_id: uuid::Uuid) -> Self {
+        Self {
+            theatre_id,
+            name: value.name,
+            description: value.description,
+            price: value.price,
+            currency: value.currency,
+            period_days: value.period_days,
+            tickets_per_period: value.tickets_per_period,
+            ticket_type_ids: value.ticket_type_ids,
         }
     }
 }
@@ -651,7 +2655,475 @@ impl CreateHall {
         Self {
             name: value.name,
             seat_data: value.seat_data,
-            theatre_id
+            theatre_id,
+            cleaning_buffer_minutes: value.cleaning_buffer_minutes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveTime, Timelike};
+
+    use super::*;
+
+    fn screening(starting_time: chrono::NaiveDateTime) -> TheatreScreening {
+        TheatreScreening {
+            id: uuid::Uuid::nil(),
+            movie_id: uuid::Uuid::nil(),
+            theatre_id: uuid::Uuid::nil(),
+            hall_id: uuid::Uuid::nil(),
+            subtitles_language_id: None,
+            audio_language_id: uuid::Uuid::nil(),
+            starting_time,
+            is_3d: false,
+            status: ScreeningStatus::OnSale,
+            is_deleted: false,
+            schedule_id: None,
+        }
+    }
+
+    fn rule(weekdays: Vec<i32>, band: Option<(u32, u32)>) -> PricingRule {
+        let time = |hour| NaiveTime::from_hms_opt(hour, 0, 0);
+
+        PricingRule {
+            id: uuid::Uuid::nil(),
+            ticket_type_id: uuid::Uuid::nil(),
+            name: "Test".to_owned(),
+            theatre_screening_id: None,
+            seat_kind: None,
+            weekdays,
+            starts_from: band.and_then(|(from, _)| time(from)),
+            starts_until: band.and_then(|(_, until)| time(until)),
+            is_3d: None,
+            adjustment: PriceAdjustment::Add,
+            value: 0,
+            priority: 0,
+            created_at: chrono::Utc::now().naive_utc(),
+        }
+    }
+
+    fn money(amount: i64, currency: &str) -> Money {
+        Money::new(amount, currency).unwrap()
+    }
+
+    #[test]
+    fn money_formats_minor_units() {
+        assert_eq!(money(1250, "EUR").to_string(), "12.50 EUR");
+        assert_eq!(money(5, "EUR").to_string(), "0.05 EUR");
+        assert_eq!(money(-1205, "USD").to_string(), "-12.05 USD");
+        assert_eq!(money(1500, "JPY").to_string(), "1500 JPY");
+        assert_eq!(money(1234, "KWD").to_string(), "1.234 KWD");
+        assert_eq!(money(10000, "CLF").to_string(), "1.0000 CLF");
+    }
+
+    #[test]
+    fn money_rejects_unknown_currencies() {
+        assert!(matches!(
+            Money::new(100, "XYZ"),
+            Err(MoneyError::UnknownCurrency(_))
+        ));
+        assert!(Money::new(100, "eur").is_err());
+    }
+
+    #[test]
+    fn money_checked_add() {
+        assert_eq!(
+            money(1250, "EUR").checked_add(&money(750, "EUR")).unwrap(),
+            money(2000, "EUR")
+        );
+        assert!(matches!(
+            money(1250, "EUR").checked_add(&money(750, "USD")),
+            Err(MoneyError::CurrencyMismatch(..))
+        ));
+        assert!(matches!(
+            money(i64::MAX, "EUR").checked_add(&money(1, "EUR")),
+            Err(MoneyError::Overflow)
+        ));
+    }
+
+    #[test]
+    fn money_basis_points_round_half_away_from_zero() {
+        let change = |amount, basis_points| {
+            money(amount, "EUR")
+                .checked_change_by_basis_points(basis_points)
+                .unwrap()
+        };
+
+        assert_eq!(change(1250, -2000), money(1000, "EUR"));
+        assert_eq!(change(5, -1000), money(4, "EUR"));
+        assert_eq!(change(5, 1000), money(6, "EUR"));
+    }
+
+    fn ticket(price: i64, currency: &str, tax_rate: i32) -> Ticket {
+        let now = chrono::Utc::now().naive_utc();
+
+        Ticket {
+            id: uuid::Uuid::nil(),
+            owner_user_id: uuid::Uuid::nil(),
+            theatre_screening_id: uuid::Uuid::nil(),
+            ticket_type_id: uuid::Uuid::nil(),
+            issuer_user_id: uuid::Uuid::nil(),
+            seat_row: 0,
+            seat_column: 0,
+            issued_at: now,
+            expires_at: now,
+            used: false,
+            is_cancelled: false,
+            valid_from: now,
+            order_id: None,
+            price,
+            currency: currency.to_owned(),
+            promo_code_id: None,
+            discount: 0,
+            membership_subscription_id: None,
+            tax_rate,
+        }
+    }
+
+    #[test]
+    fn ticket_tax_is_included_in_the_price() {
+        assert_eq!(ticket(1000, "EUR", 2000).tax().unwrap(), money(167, "EUR"));
+        assert_eq!(ticket(1250, "EUR", 2000).tax().unwrap(), money(208, "EUR"));
+        assert_eq!(ticket(1070, "USD", 700).tax().unwrap(), money(70, "USD"));
+        assert_eq!(ticket(1500, "JPY", 1000).tax().unwrap(), money(136, "JPY"));
+        assert_eq!(ticket(1000, "EUR", 0).tax().unwrap(), money(0, "EUR"));
+    }
+
+    #[test]
+    fn ticket_tax_rounds_half_up() {
+        // 10.5 minor units of taxes at 100%
+        assert_eq!(ticket(21, "EUR", 10000).tax().unwrap(), money(11, "EUR"));
+        // 0.4 and 0.6 minor units
+        assert_eq!(ticket(2, "EUR", 2500).tax().unwrap(), money(0, "EUR"));
+        assert_eq!(ticket(3, "EUR", 2500).tax().unwrap(), money(1, "EUR"));
+    }
+
+    fn promo_code(discount: PromoDiscount, value: i64, currency: Option<&str>) -> PromoCode {
+        PromoCode {
+            id: uuid::Uuid::nil(),
+            theatre_id: uuid::Uuid::nil(),
+            code: "TEST".to_owned(),
+            discount,
+            value,
+            currency: currency.map(str::to_owned),
+            max_uses: None,
+            max_uses_per_user: None,
+            valid_from: None,
+            valid_until: None,
+            movie_ids: vec![],
+            ticket_type_ids: vec![],
+            is_deleted: false,
+            created_at: chrono::Utc::now().naive_utc(),
+        }
+    }
+
+    fn promo_code_form(
+        discount: PromoDiscount,
+        value: i64,
+        currency: Option<&str>,
+    ) -> FormPromoCode {
+        FormPromoCode {
+            code: "SUMMER-15".to_owned(),
+            discount,
+            value,
+            currency: currency.map(str::to_owned),
+            max_uses: None,
+            max_uses_per_user: None,
+            valid_from: None,
+            valid_until: None,
+            movie_ids: vec![],
+            ticket_type_ids: vec![],
+        }
+    }
+
+    #[test]
+    fn promo_code_percent_discount_rounds_half_away_from_zero() {
+        let code = promo_code(PromoDiscount::Percent, 1500, None);
+        let prices = [money(1250, "EUR"), money(999, "EUR"), money(1000, "EUR")];
+
+        // 187.5 and 149.85 minor units
+        let applicable = prices.iter().map(Some).collect::<Vec<_>>();
+        assert_eq!(code.discounts(&applicable).unwrap(), [188, 150, 150]);
+
+        let everything = promo_code(PromoDiscount::Percent, 10_000, None);
+        let price = money(1250, "EUR");
+        assert_eq!(everything.discounts(&[Some(&price)]).unwrap(), [1250]);
+    }
+
+    #[test]
+    fn promo_code_fixed_discount_is_spread_over_the_tickets() {
+        let code = promo_code(PromoDiscount::Fixed, 1000, Some("EUR"));
+        let (a, b, c) = (money(600, "EUR"), money(300, "EUR"), money(500, "EUR"));
+
+        let discounts = code.discounts(&[Some(&a), Some(&b), Some(&c)]).unwrap();
+        assert_eq!(discounts, [600, 300, 100]);
+        // no ticket drops below zero, even if the discount isn't used up
+        assert_eq!(code.discounts(&[Some(&a), Some(&b)]).unwrap(), [600, 300]);
+        // tickets the code doesn't apply to are skipped
+        let discounts = code.discounts(&[Some(&a), None, Some(&c)]).unwrap();
+        assert_eq!(discounts, [600, 0, 400]);
+    }
+
+    #[test]
+    fn promo_code_fixed_discount_rejects_other_currencies() {
+        let code = promo_code(PromoDiscount::Fixed, 1000, Some("EUR"));
+
+        assert!(matches!(
+            code.discounts(&[Some(&money(1000, "USD"))]),
+            Err(MoneyError::CurrencyMismatch(..))
+        ));
+    }
+
+    #[test]
+    fn promo_code_restrictions() {
+        let (movie, other_movie) = (uuid::Uuid::from_u128(1), uuid::Uuid::from_u128(2));
+        let (adult, child) = (uuid::Uuid::from_u128(3), uuid::Uuid::from_u128(4));
+
+        let mut code = promo_code(PromoDiscount::Percent, 1500, None);
+        assert!(code.applies_to(movie, adult));
+
+        code.ticket_type_ids = vec![child];
+        assert!(code.applies_to(movie, child));
+        assert!(!code.applies_to(movie, adult));
+
+        code.movie_ids = vec![movie];
+        assert!(code.applies_to(movie, child));
+        assert!(!code.applies_to(other_movie, child));
+    }
+
+    #[test]
+    fn promo_code_validation() {
+        use PromoDiscount::*;
+
+        assert!(validate_promo_code(&promo_code_form(Percent, 1500, None)).is_ok());
+        assert!(validate_promo_code(&promo_code_form(Percent, 10_000, None)).is_ok());
+        assert!(validate_promo_code(&promo_code_form(Percent, 10_001, None)).is_err());
+        assert!(validate_promo_code(&promo_code_form(Percent, 0, None)).is_err());
+        assert!(validate_promo_code(&promo_code_form(Percent, 1500, Some("EUR"))).is_err());
+        assert!(validate_promo_code(&promo_code_form(Fixed, 500, Some("EUR"))).is_ok());
+        assert!(validate_promo_code(&promo_code_form(Fixed, 500, None)).is_err());
+        assert!(validate_promo_code(&promo_code_form(Fixed, 0, Some("EUR"))).is_err());
+        assert!(validate_promo_code(&promo_code_form(Fixed, 500, Some("XYZ"))).is_err());
+
+        let mut form = promo_code_form(Percent, 1500, None);
+        form.code = "SUMMER 15".to_owned();
+        assert!(validate_promo_code(&form).is_err());
+    }
+
+    #[test]
+    fn gift_card_code_normalization() {
+        assert_eq!(
+            GiftCard::normalize_code("abcd-efgh 1234-5678"),
+            "ABCDEFGH12345678"
+        );
+        // letters left out of the alphabet are read as the digits they look like
+        assert_eq!(GiftCard::normalize_code("iLoO-ilo0"), "11001100");
+    }
+
+    #[test]
+    fn gift_card_code_hash_ignores_formatting() {
+        let hash = GiftCard::hash_code("0123456789ABCDEF");
+
+        assert_eq!(GiftCard::hash_code("0123-4567-89ab-cdef"), hash);
+        assert_eq!(GiftCard::hash_code(" oI23 4567 89ab CDEF "), hash);
+        assert_ne!(GiftCard::hash_code("0123456789ABCDEE"), hash);
+    }
+
+    #[test]
+    fn screening_initial_statuses() {
+        use ScreeningStatus::*;
+
+        assert!(Scheduled.is_initial());
+        assert!(OnSale.is_initial());
+        for status in [SoldOut, Started, Finished, Cancelled] {
+            assert!(!status.is_initial(), "{:?}", status);
+        }
+    }
+
+    #[test]
+    fn screening_status_transitions() {
+        use ScreeningStatus::*;
+
+        let all = [Scheduled, OnSale, SoldOut, Started, Finished, Cancelled];
+        let allowed = [
+            (Scheduled, OnSale),
+            (Scheduled, Cancelled),
+            (OnSale, Scheduled),
+            (OnSale, SoldOut),
+            (OnSale, Started),
+            (OnSale, Cancelled),
+            (SoldOut, OnSale),
+            (SoldOut, Started),
+            (SoldOut, Cancelled),
+            (Started, Finished),
+        ];
+
+        for from in all {
+            for to in all {
+                assert_eq!(
+                    from.can_transition_to(to),
+                    allowed.contains(&(from, to)),
+                    "{:?} -> {:?}",
+                    from,
+                    to
+                );
+            }
+        }
+
+        // finished and cancelled screenings are final
+        assert!(!Cancelled.can_transition_to(OnSale));
+        assert!(!Finished.can_transition_to(Started));
+        assert!(!Started.can_transition_to(Cancelled));
+    }
+
+    fn schedule(
+        weekdays: Vec<i32>,
+        hours: &[u32],
+        start: (u32, u32),
+        end: (u32, u32),
+    ) -> FormScreeningSchedule {
+        let date = |(month, day)| NaiveDate::from_ymd_opt(2024, month, day).unwrap();
+
+        FormScreeningSchedule {
+            movie_id: uuid::Uuid::nil(),
+            hall_id: uuid::Uuid::nil(),
+            subtitles_language_id: None,
+            audio_language_id: uuid::Uuid::nil(),
+            is_3d: None,
+            weekdays,
+            times_of_day: hours
+                .iter()
+                .map(|x| NaiveTime::from_hms_opt(*x, 0, 0).unwrap())
+                .collect(),
+            start_date: date(start),
+            end_date: date(end),
+            status: None,
+        }
+    }
+
+    #[test]
+    fn schedule_occurrences_on_weekdays() {
+        // weekends from Monday the 4th up to and including Sunday the 17th of March
+        let weekends = schedule(vec![6, 7], &[21, 18, 18], (3, 4), (3, 17));
+        let starting_times = weekends
+            .occurrences()
+            .into_iter()
+            .map(|x| (x.day(), x.hour()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            starting_times,
+            [
+                (9, 18),
+                (9, 21),
+                (10, 18),
+                (10, 21),
+                (16, 18),
+                (16, 21),
+                (17, 18),
+                (17, 21)
+            ]
+        );
+    }
+
+    #[test]
+    fn schedule_occurrences_include_the_end_date() {
+        let single_day = schedule(vec![1], &[20], (3, 4), (3, 4));
+        assert_eq!(single_day.occurrences().len(), 1);
+
+        // 2024 is a leap year
+        let whole_year = schedule(vec![1, 2, 3, 4, 5, 6, 7], &[20], (1, 1), (12, 31));
+        assert_eq!(whole_year.occurrences().len(), 366);
+    }
+
+    #[test]
+    fn schedule_validation() {
+        let valid = schedule(vec![1, 7], &[20], (3, 4), (3, 17));
+        assert!(validate_screening_schedule(&valid).is_ok());
+
+        for weekdays in [vec![0], vec![8], vec![1, 8]] {
+            let invalid = FormScreeningSchedule {
+                weekdays,
+                ..schedule(vec![], &[20], (3, 4), (3, 17))
+            };
+            assert!(validate_screening_schedule(&invalid).is_err());
         }
+
+        let ends_before_start = schedule(vec![1], &[20], (3, 17), (3, 4));
+        assert!(validate_screening_schedule(&ends_before_start).is_err());
+
+        let mut started = schedule(vec![1], &[20], (3, 4), (3, 17));
+        started.status = Some(ScreeningStatus::Started);
+        assert!(validate_screening_schedule(&started).is_err());
+    }
+
+    #[test]
+    fn schedule_is_capped_at_max_days() {
+        let longest = schedule(vec![1], &[20], (1, 1), (12, 31));
+        assert_eq!(
+            (longest.end_date - longest.start_date).num_days() + 1,
+            FormScreeningSchedule::MAX_DAYS
+        );
+        assert!(validate_screening_schedule(&longest).is_ok());
+
+        let too_long = FormScreeningSchedule {
+            end_date: NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+            ..schedule(vec![1], &[20], (1, 1), (1, 1))
+        };
+        assert!(validate_screening_schedule(&too_long).is_err());
+    }
+
+    /// 2024-03-04 is a Monday
+    fn at(day: u32, hour: u32, minute: u32) -> TheatreScreening {
+        screening(
+            NaiveDate::from_ymd_opt(2024, 3, day)
+                .and_then(|x| x.and_hms_opt(hour, minute, 0))
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn pricing_rule_band() {
+        let matinee = rule(vec![], Some((10, 16)));
+
+        assert!(!matinee.matches(&at(4, 9, 59), None));
+        assert!(matinee.matches(&at(4, 10, 0), None));
+        assert!(matinee.matches(&at(4, 15, 59), None));
+        assert!(!matinee.matches(&at(4, 16, 0), None));
+    }
+
+    #[test]
+    fn pricing_rule_band_wraps_around_midnight() {
+        let late_night = rule(vec![], Some((22, 2)));
+
+        assert!(!late_night.matches(&at(4, 21, 59), None));
+        assert!(late_night.matches(&at(4, 22, 0), None));
+        assert!(late_night.matches(&at(4, 23, 30), None));
+        assert!(late_night.matches(&at(5, 0, 0), None));
+        assert!(late_night.matches(&at(5, 1, 59), None));
+        assert!(!late_night.matches(&at(5, 2, 0), None));
+        assert!(!late_night.matches(&at(5, 12, 0), None));
+    }
+
+    #[test]
+    fn pricing_rule_weekdays() {
+        let weekend = rule(vec![6, 7], None);
+
+        assert!(!weekend.matches(&at(8, 20, 0), None));
+        assert!(weekend.matches(&at(9, 20, 0), None));
+        assert!(weekend.matches(&at(10, 20, 0), None));
+        assert!(!weekend.matches(&at(11, 20, 0), None));
+        assert!(rule(vec![], None).matches(&at(11, 20, 0), None));
+    }
+
+    #[test]
+    fn pricing_rule_weekday_and_band() {
+        // the weekday is the one the screening starts on, even past midnight
+        let friday_night = rule(vec![5], Some((22, 2)));
+
+        assert!(friday_night.matches(&at(8, 23, 0), None));
+        assert!(!friday_night.matches(&at(9, 1, 0), None));
+        assert!(!friday_night.matches(&at(8, 20, 0), None));
     }
 }
@@ -0,0 +1,130 @@
+use async_trait::async_trait;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ring::{hmac, rand::SecureRandom, rand::SystemRandom};
+
+use super::{PaymentError, PaymentIntent, PaymentProvider, WebhookEvent};
+use crate::model::{Money, PaymentStatus};
+
+/// payment method which the mock provider always declines
+pub const DECLINED_PAYMENT_METHOD: &str = "mock_declined";
+
+const INTENT_PREFIX: &str = "mock_pi_";
+
+/// In-process provider for development and testing, nothing leaves the server.
+/// Every payment method except [`DECLINED_PAYMENT_METHOD`] succeeds, webhooks
+/// are signed with the hex encoded HMAC-SHA256 of their body.
+///
+/// It keeps no state, so intents outlive restarts of the server: an intent's amount is
+/// part of its ID, and whether it was confirmed or refunded is only tracked by our tables
+pub struct MockProvider {
+    webhook_key: Option<hmac::Key>,
+    rng: SystemRandom,
+}
+
+impl MockProvider {
+    pub fn new(webhook_secret: Option<String>) -> Self {
+        Self {
+            webhook_key: webhook_secret.map(|x| hmac::Key::new(hmac::HMAC_SHA256, x.as_bytes())),
+            rng: SystemRandom::new(),
+        }
+    }
+
+    fn random_token(&self) -> Result<String, PaymentError> {
+        let mut bytes = [0u8; 18];
+        self.rng
+            .fill(&mut bytes)
+            .map_err(|_| PaymentError::Provider("couldn't generate a token".to_owned()))?;
+
+        Ok(URL_SAFE_NO_PAD.encode(bytes))
+    }
+
+    /// the amount an intent was created for, read back from its ID
+    fn intent_amount(intent_id: &str) -> Result<Money, PaymentError> {
+        let unknown = || PaymentError::UnknownIntent(intent_id.to_owned());
+
+        let mut parts = intent_id
+            .strip_prefix(INTENT_PREFIX)
+            .ok_or_else(unknown)?
+            .splitn(3, '_');
+        let (Some(amount), Some(currency), Some(_)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(unknown());
+        };
+
+        let amount = amount.parse().map_err(|_| unknown())?;
+        Money::new(amount, currency).map_err(|_| unknown())
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for MockProvider {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    async fn create_intent(
+        &self,
+        amount: &Money,
+        _reference: uuid::Uuid,
+    ) -> Result<PaymentIntent, PaymentError> {
+        let id = format!(
+            "{}{}_{}_{}",
+            INTENT_PREFIX,
+            amount.amount(),
+            amount.currency(),
+            self.random_token()?
+        );
+        let client_secret = format!("{}_secret_{}", id, self.random_token()?);
+
+        Ok(PaymentIntent { id, client_secret })
+    }
+
+    async fn confirm(
+        &self,
+        intent_id: &str,
+        payment_method: &str,
+    ) -> Result<PaymentStatus, PaymentError> {
+        Self::intent_amount(intent_id)?;
+
+        if payment_method == DECLINED_PAYMENT_METHOD {
+            Ok(PaymentStatus::Failed)
+        } else {
+            Ok(PaymentStatus::Succeeded)
+        }
+    }
+
+    async fn refund(&self, intent_id: &str, amount: &Money) -> Result<(), PaymentError> {
+        let paid = Self::intent_amount(intent_id)?;
+
+        if paid.currency() != amount.currency() || amount.amount() > paid.amount() {
+            return Err(PaymentError::InvalidState);
+        }
+
+        Ok(())
+    }
+
+    fn verify_webhook(
+        &self,
+        signature: &str,
+        payload: &[u8],
+    ) -> Result<WebhookEvent, PaymentError> {
+        let Some(webhook_key) = &self.webhook_key else {
+            return Err(PaymentError::InvalidSignature);
+        };
+
+        let signature = (0..signature.len())
+            .step_by(2)
+            .map(|i| {
+                signature
+                    .get(i..i + 2)
+                    .and_then(|x| u8::from_str_radix(x, 16).ok())
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or(PaymentError::InvalidSignature)?;
+
+        hmac::verify(webhook_key, payload, &signature)
+            .map_err(|_| PaymentError::InvalidSignature)?;
+
+        Ok(serde_json::from_slice(payload)?)
+    }
+}
@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::model::{Money, PaymentStatus};
+use crate::vars::{payment_provider, payment_webhook_secret};
+
+pub mod mock;
+
+#[derive(thiserror::Error, Debug)]
+pub enum PaymentError {
+    #[error("payment intent {0} doesn't exist")]
+    UnknownIntent(String),
+    #[error("payment intent is in the wrong state for this operation")]
+    InvalidState,
+    #[error("webhook signature didn't match its payload")]
+    InvalidSignature,
+    #[error("webhook payload couldn't be parsed")]
+    InvalidPayload(#[from] serde_json::Error),
+    #[error("payment provider failed: {0}")]
+    Provider(String),
+    #[error("unknown payment provider '{0}'")]
+    UnknownProvider(String),
+}
+
+/// An intent to collect a payment, which gets confirmed either by us or
+/// by the client directly with the provider (using the client secret)
+#[derive(Debug, Clone)]
+pub struct PaymentIntent {
+    pub id: String,
+    pub client_secret: String,
+}
+
+/// A payment state change the provider notified us about
+#[derive(Deserialize, Debug, Clone)]
+pub struct WebhookEvent {
+    pub intent_id: String,
+    pub status: PaymentStatus,
+}
+
+/// Abstraction over the payment processors orders can be paid through
+#[async_trait]
+pub trait PaymentProvider: Send + Sync {
+    /// stored along with every payment, so intents can be told apart between providers
+    fn name(&self) -> &'static str;
+
+    /// `reference` is our ID of what's being paid for (an order)
+    async fn create_intent(
+        &self,
+        amount: &Money,
+        reference: uuid::Uuid,
+    ) -> Result<PaymentIntent, PaymentError>;
+
+    async fn confirm(
+        &self,
+        intent_id: &str,
+        payment_method: &str,
+    ) -> Result<PaymentStatus, PaymentError>;
+
+    /// returns (part of) a succeeded payment back to the payer
+    async fn refund(&self, intent_id: &str, amount: &Money) -> Result<(), PaymentError>;
+
+    /// checks that a webhook request really comes from the provider and parses it
+    fn verify_webhook(&self, signature: &str, payload: &[u8])
+        -> Result<WebhookEvent, PaymentError>;
+}
+
+/// builds the provider selected through `PAYMENT_PROVIDER`, defaulting to the mock one
+pub fn provider_from_env() -> Result<Arc<dyn PaymentProvider>, PaymentError> {
+    // webhooks aren't accepted at all without a secret to verify them with
+    let webhook_secret = payment_webhook_secret().filter(|x| !x.is_empty());
+
+    match payment_provider().as_deref() {
+        None | Some("") | Some("mock") => Ok(Arc::new(mock::MockProvider::new(webhook_secret))),
+        Some(other) => Err(PaymentError::UnknownProvider(other.to_owned())),
+    }
+}
@@ -0,0 +1,35 @@
+use std::io::Cursor;
+
+use image::{ImageFormat, Luma};
+use qrcode::{render::svg, types::QrError, QrCode};
+
+/// the smallest width/height (in pixels) a rendered QR code can have,
+/// big enough to be scanned off a phone screen or a printed ticket
+const MIN_DIMENSIONS: u32 = 384;
+
+#[derive(thiserror::Error, Debug)]
+pub enum RenderError {
+    #[error("data can't be encoded as a QR code")]
+    Encode(#[from] QrError),
+    #[error("png encoding was unsuccessful")]
+    Image(#[from] image::ImageError),
+}
+
+pub fn render_png(data: &[u8]) -> Result<Vec<u8>, RenderError> {
+    let image = QrCode::new(data)?
+        .render::<Luma<u8>>()
+        .min_dimensions(MIN_DIMENSIONS, MIN_DIMENSIONS)
+        .build();
+
+    let mut png = Cursor::new(vec![]);
+    image.write_to(&mut png, ImageFormat::Png)?;
+
+    Ok(png.into_inner())
+}
+
+pub fn render_svg(data: &[u8]) -> Result<String, RenderError> {
+    Ok(QrCode::new(data)?
+        .render::<svg::Color>()
+        .min_dimensions(MIN_DIMENSIONS, MIN_DIMENSIONS)
+        .build())
+}
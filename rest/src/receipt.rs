@@ -0,0 +1,260 @@
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+use qrcode::{types::QrError, Color, QrCode};
+
+use crate::model::{Money, MoneyError, TicketReceipt};
+
+/// A4, in points
+const PAGE_WIDTH: f32 = 595.;
+const PAGE_HEIGHT: f32 = 842.;
+const MARGIN: f32 = 60.;
+const LINE_HEIGHT: f32 = 20.;
+/// the QR code is printed as big as it's shown in the app, quiet zone included
+const QR_SIZE: f32 = 200.;
+const QR_QUIET_ZONE: usize = 4;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReceiptError {
+    #[error("data can't be encoded as a QR code")]
+    Encode(#[from] QrError),
+    #[error("the ticket's price can't be formatted")]
+    Money(#[from] MoneyError),
+}
+
+/// formats a rate in basis points as a percentage, e.g. 2000 as "20%" and 750 as "7.5%"
+fn format_rate(basis_points: i32) -> String {
+    let fraction = format!("{:02}", basis_points % 100);
+    let fraction = fraction.trim_end_matches('0');
+
+    if fraction.is_empty() {
+        format!("{}%", basis_points / 100)
+    } else {
+        format!("{}.{}%", basis_points / 100, fraction)
+    }
+}
+
+/// the labelled lines of a receipt, shared by its PDF and plain text versions
+fn receipt_lines(receipt: &TicketReceipt) -> Result<Vec<(&'static str, String)>, MoneyError> {
+    let ticket = &receipt.ticket;
+    let price = ticket.price()?;
+    let tax = ticket.tax()?;
+
+    let mut lines = vec![
+        ("Receipt no.", ticket.id.to_string()),
+        (
+            "Issued at",
+            ticket.issued_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+        ),
+        (
+            "Customer",
+            format!("{} <{}>", receipt.customer_name, receipt.customer_email),
+        ),
+        ("Theatre", receipt.theatre_name.clone()),
+        ("Movie", receipt.movie_name.clone()),
+        (
+            "Screening",
+            receipt
+                .starting_time
+                .format("%Y-%m-%d %H:%M UTC")
+                .to_string(),
+        ),
+        ("Hall", receipt.hall_name.clone()),
+        // seats are counted from 0 internally, but from 1 on the hall's signs
+        (
+            "Seat",
+            format!(
+                "row {}, seat {}",
+                ticket.seat_row + 1,
+                ticket.seat_column + 1
+            ),
+        ),
+        ("Ticket type", receipt.ticket_type.clone()),
+    ];
+
+    if ticket.discount > 0 {
+        lines.push((
+            "Discount",
+            Money::new(ticket.discount, &ticket.currency)?.to_string(),
+        ));
+    }
+
+    if ticket.membership_subscription_id.is_some() {
+        lines.push(("Paid with", "membership".to_owned()));
+    }
+
+    lines.extend([
+        (
+            "Net price",
+            Money::new(price.amount() - tax.amount(), price.currency())?.to_string(),
+        ),
+        (
+            "Taxes",
+            format!("{} ({})", tax, format_rate(ticket.tax_rate)),
+        ),
+        ("Total", price.to_string()),
+    ]);
+
+    if ticket.is_cancelled {
+        lines.push(("Status", "cancelled".to_owned()));
+    }
+
+    Ok(lines)
+}
+
+/// The receipt as plain text, e.g. for the body of an email
+pub fn render_text(receipt: &TicketReceipt) -> Result<String, MoneyError> {
+    let lines = receipt_lines(receipt)?;
+    let width = lines
+        .iter()
+        .map(|(label, _)| label.len())
+        .max()
+        .unwrap_or(0);
+
+    let mut text = format!("Receipt for your ticket at {}\n\n", receipt.theatre_name);
+    for (label, value) in lines {
+        text.push_str(&format!(
+            "{:<width$}  {}\n",
+            format!("{}:", label),
+            value,
+            width = width + 1
+        ));
+    }
+
+    Ok(text)
+}
+
+/// Encodes text for the PDF standard fonts (WinAnsiEncoding), which only cover
+/// Latin scripts, anything they can't show is replaced by a question mark
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c as u32 {
+            0x20..=0x7e | 0xa0..=0xff => c as u8,
+            0x20ac => 0x80,
+            _ => b'?',
+        })
+        .collect()
+}
+
+/// The receipt as a single page PDF, with the ticket's QR code (`qr_data`) under its details
+pub fn render_pdf(
+    receipt: &TicketReceipt,
+    qr_data: Option<&[u8]>,
+) -> Result<Vec<u8>, ReceiptError> {
+    let lines = receipt_lines(receipt)?;
+
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let page_id = Ref::new(3);
+    let content_id = Ref::new(4);
+    let regular_font_id = Ref::new(5);
+    let bold_font_id = Ref::new(6);
+    let info_id = Ref::new(7);
+    let regular_font = Name(b"F1");
+    let bold_font = Name(b"F2");
+
+    let mut pdf = Pdf::new();
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id).kids([page_id]).count(1);
+    pdf.document_info(info_id)
+        .title(TextStr(&format!("Receipt {}", receipt.ticket.id)));
+
+    let mut page = pdf.page(page_id);
+    page.media_box(Rect::new(0., 0., PAGE_WIDTH, PAGE_HEIGHT));
+    page.parent(page_tree_id);
+    page.contents(content_id);
+    page.resources()
+        .fonts()
+        .pair(regular_font, regular_font_id)
+        .pair(bold_font, bold_font_id);
+    page.finish();
+
+    pdf.type1_font(regular_font_id)
+        .base_font(Name(b"Helvetica"))
+        .encoding_predefined(Name(b"WinAnsiEncoding"));
+    pdf.type1_font(bold_font_id)
+        .base_font(Name(b"Helvetica-Bold"))
+        .encoding_predefined(Name(b"WinAnsiEncoding"));
+
+    let mut content = Content::new();
+    let mut y = PAGE_HEIGHT - MARGIN - 18.;
+
+    content
+        .begin_text()
+        .set_font(bold_font, 18.)
+        .next_line(MARGIN, y)
+        .show(Str(&win_ansi(&receipt.theatre_name)))
+        .end_text();
+    y -= LINE_HEIGHT * 2.;
+
+    for (label, value) in &lines {
+        content
+            .begin_text()
+            .set_font(bold_font, 11.)
+            .next_line(MARGIN, y)
+            .show(Str(&win_ansi(label)))
+            .end_text();
+        content
+            .begin_text()
+            .set_font(regular_font, 11.)
+            .next_line(MARGIN + 110., y)
+            .show(Str(&win_ansi(value)))
+            .end_text();
+        y -= LINE_HEIGHT;
+    }
+
+    // cancelled tickets and ones which haven't been paid for can't be used to get in
+    if let Some(qr_data) = qr_data {
+        let code = QrCode::new(qr_data)?;
+        let width = code.width();
+        let module = QR_SIZE / (width + QR_QUIET_ZONE * 2) as f32;
+        let top = y - QR_QUIET_ZONE as f32 * module;
+        let left = MARGIN + QR_QUIET_ZONE as f32 * module;
+
+        // adjacent dark modules of a row are drawn as one rectangle, to keep the file small
+        content.set_fill_gray(0.);
+        for (row, colors) in code.to_colors().chunks(width).enumerate() {
+            let mut column = 0;
+            while column < width {
+                let run = colors[column..]
+                    .iter()
+                    .take_while(|x| **x == Color::Dark)
+                    .count();
+
+                if run > 0 {
+                    content.rect(
+                        left + column as f32 * module,
+                        top - (row + 1) as f32 * module,
+                        run as f32 * module,
+                        module,
+                    );
+                }
+                column += run.max(1);
+            }
+        }
+        content.fill_nonzero();
+    }
+
+    pdf.stream(content_id, &content.finish());
+
+    Ok(pdf.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates_are_formatted_as_percentages() {
+        assert_eq!(format_rate(2000), "20%");
+        assert_eq!(format_rate(750), "7.5%");
+        assert_eq!(format_rate(1234), "12.34%");
+        assert_eq!(format_rate(5), "0.05%");
+        assert_eq!(format_rate(0), "0%");
+        assert_eq!(format_rate(10000), "100%");
+    }
+
+    #[test]
+    fn only_latin_text_is_encoded() {
+        assert_eq!(win_ansi("Café 12.50 €"), b"Caf\xe9 12.50 \x80");
+        assert_eq!(win_ansi("Łódź"), b"?\xf3d?");
+    }
+}
@@ -1,5 +1,51 @@
 // @generated automatically by Diesel CLI.
 
+pub mod sql_types {
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "check_in_action"))]
+    pub struct CheckInAction;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "gift_card_entry_kind"))]
+    pub struct GiftCardEntryKind;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "loyalty_entry_kind"))]
+    pub struct LoyaltyEntryKind;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "order_status"))]
+    pub struct OrderStatus;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "payment_status"))]
+    pub struct PaymentStatus;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "price_adjustment"))]
+    pub struct PriceAdjustment;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "promo_discount"))]
+    pub struct PromoDiscount;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "refund_reason"))]
+    pub struct RefundReason;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "screening_status"))]
+    pub struct ScreeningStatus;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "seat_kind"))]
+    pub struct SeatKind;
+
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "ticket_transfer_status"))]
+    pub struct TicketTransferStatus;
+}
+
 diesel::table! {
     external_credentials (id) {
         id -> Uuid,
@@ -9,6 +55,34 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::GiftCardEntryKind;
+
+    gift_card_entries (id) {
+        id -> Uuid,
+        gift_card_id -> Uuid,
+        kind -> GiftCardEntryKind,
+        amount -> Int8,
+        order_id -> Nullable<Uuid>,
+        refund_id -> Nullable<Uuid>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    gift_cards (id) {
+        id -> Uuid,
+        theatre_id -> Uuid,
+        code_hash -> Varchar,
+        code_suffix -> Varchar,
+        currency -> Varchar,
+        balance -> Int8,
+        issuer_user_id -> Uuid,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     halls (id) {
         id -> Uuid,
@@ -16,6 +90,7 @@ diesel::table! {
         name -> Varchar,
         seat_data -> Jsonb,
         is_deleted -> Bool,
+        cleaning_buffer_minutes -> Int4,
     }
 }
 
@@ -27,6 +102,68 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    loyalty_accounts (user_id, theatre_id) {
+        user_id -> Uuid,
+        theatre_id -> Uuid,
+        points -> Int4,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::LoyaltyEntryKind;
+
+    loyalty_entries (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        theatre_id -> Uuid,
+        kind -> LoyaltyEntryKind,
+        points -> Int4,
+        ticket_id -> Nullable<Uuid>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    membership_periods (id) {
+        id -> Uuid,
+        membership_subscription_id -> Uuid,
+        starts_at -> Timestamp,
+        ends_at -> Timestamp,
+        price -> Int8,
+        currency -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    membership_plans (id) {
+        id -> Uuid,
+        theatre_id -> Uuid,
+        name -> Varchar,
+        description -> Nullable<Text>,
+        price -> Int8,
+        currency -> Varchar,
+        period_days -> Int4,
+        tickets_per_period -> Nullable<Int4>,
+        ticket_type_ids -> Array<Uuid>,
+        is_deleted -> Bool,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    membership_subscriptions (id) {
+        id -> Uuid,
+        membership_plan_id -> Uuid,
+        user_id -> Uuid,
+        issuer_user_id -> Uuid,
+        created_at -> Timestamp,
+        cancelled_at -> Nullable<Timestamp>,
+    }
+}
+
 diesel::table! {
     movie_reviews (id) {
         id -> Uuid,
@@ -53,6 +190,132 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::OrderStatus;
+
+    orders (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        theatre_screening_id -> Uuid,
+        status -> OrderStatus,
+        total_price -> Int8,
+        currency -> Varchar,
+        created_at -> Timestamp,
+        promo_code_id -> Nullable<Uuid>,
+        discount -> Int8,
+        gift_card_id -> Nullable<Uuid>,
+        gift_card_amount -> Int8,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::PaymentStatus;
+
+    payments (id) {
+        id -> Uuid,
+        order_id -> Uuid,
+        provider -> Varchar,
+        intent_id -> Varchar,
+        client_secret -> Varchar,
+        amount -> Int8,
+        currency -> Varchar,
+        status -> PaymentStatus,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::SeatKind;
+    use super::sql_types::PriceAdjustment;
+
+    pricing_rules (id) {
+        id -> Uuid,
+        ticket_type_id -> Uuid,
+        name -> Varchar,
+        theatre_screening_id -> Nullable<Uuid>,
+        seat_kind -> Nullable<SeatKind>,
+        weekdays -> Array<Int4>,
+        starts_from -> Nullable<Time>,
+        starts_until -> Nullable<Time>,
+        is_3d -> Nullable<Bool>,
+        adjustment -> PriceAdjustment,
+        value -> Int8,
+        priority -> Int4,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::PromoDiscount;
+
+    promo_codes (id) {
+        id -> Uuid,
+        theatre_id -> Uuid,
+        code -> Varchar,
+        discount -> PromoDiscount,
+        value -> Int8,
+        currency -> Nullable<Varchar>,
+        max_uses -> Nullable<Int4>,
+        max_uses_per_user -> Nullable<Int4>,
+        valid_from -> Nullable<Timestamp>,
+        valid_until -> Nullable<Timestamp>,
+        movie_ids -> Array<Uuid>,
+        ticket_type_ids -> Array<Uuid>,
+        is_deleted -> Bool,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::RefundReason;
+
+    refunds (id) {
+        id -> Uuid,
+        ticket_id -> Uuid,
+        user_id -> Uuid,
+        amount -> Int8,
+        currency -> Varchar,
+        reason -> RefundReason,
+        created_at -> Timestamp,
+        settled_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    screening_schedules (id) {
+        id -> Uuid,
+        movie_id -> Uuid,
+        theatre_id -> Uuid,
+        hall_id -> Uuid,
+        subtitles_language_id -> Nullable<Uuid>,
+        audio_language_id -> Uuid,
+        is_3d -> Bool,
+        weekdays -> Array<Int4>,
+        times_of_day -> Array<Time>,
+        start_date -> Date,
+        end_date -> Date,
+        is_deleted -> Bool,
+    }
+}
+
+diesel::table! {
+    seat_holds (id) {
+        id -> Uuid,
+        theatre_screening_id -> Uuid,
+        user_id -> Uuid,
+        seat_row -> Int4,
+        seat_column -> Int4,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     theatre_roles (id) {
         id -> Uuid,
@@ -61,6 +324,9 @@ diesel::table! {
 }
 
 diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::ScreeningStatus;
+
     theatre_screenings (id) {
         id -> Uuid,
         movie_id -> Uuid,
@@ -70,8 +336,9 @@ diesel::table! {
         audio_language_id -> Uuid,
         starting_time -> Timestamp,
         is_3d -> Bool,
-        status -> Int4,
+        status -> ScreeningStatus,
         is_deleted -> Bool,
+        schedule_id -> Nullable<Uuid>,
     }
 }
 
@@ -84,6 +351,39 @@ diesel::table! {
         is_deleted -> Bool,
         logo_image_url -> Nullable<Varchar>,
         cover_image_url -> Nullable<Varchar>,
+        cancellation_cutoff_minutes -> Int4,
+        loyalty_points_per_unit -> Int4,
+        tax_rate -> Int4,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::CheckInAction;
+
+    ticket_check_ins (id) {
+        id -> Uuid,
+        ticket_id -> Uuid,
+        checker_user_id -> Uuid,
+        device_id -> Nullable<Varchar>,
+        scanned_at -> Timestamp,
+        synced_at -> Timestamp,
+        action -> CheckInAction,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::TicketTransferStatus;
+
+    ticket_transfers (id) {
+        id -> Uuid,
+        ticket_id -> Uuid,
+        from_user_id -> Uuid,
+        to_user_id -> Uuid,
+        status -> TicketTransferStatus,
+        created_at -> Timestamp,
+        resolved_at -> Nullable<Timestamp>,
     }
 }
 
@@ -95,8 +395,9 @@ diesel::table! {
         description -> Nullable<Varchar>,
         theatre_id -> Uuid,
         currency -> Varchar,
-        price -> Float8,
+        price -> Int8,
         is_deleted -> Bool,
+        loyalty_points_price -> Nullable<Int4>,
     }
 }
 
@@ -112,6 +413,15 @@ diesel::table! {
         issued_at -> Timestamp,
         expires_at -> Timestamp,
         used -> Bool,
+        is_cancelled -> Bool,
+        valid_from -> Timestamp,
+        order_id -> Nullable<Uuid>,
+        price -> Int8,
+        currency -> Varchar,
+        promo_code_id -> Nullable<Uuid>,
+        discount -> Int8,
+        membership_subscription_id -> Nullable<Uuid>,
+        tax_rate -> Int4,
     }
 }
 
@@ -140,13 +450,48 @@ diesel::table! {
 }
 
 diesel::joinable!(external_credentials -> users (user_id));
+diesel::joinable!(gift_card_entries -> gift_cards (gift_card_id));
+diesel::joinable!(gift_card_entries -> orders (order_id));
+diesel::joinable!(gift_card_entries -> refunds (refund_id));
+diesel::joinable!(gift_cards -> theatres (theatre_id));
+diesel::joinable!(gift_cards -> users (issuer_user_id));
 diesel::joinable!(halls -> theatres (theatre_id));
+diesel::joinable!(loyalty_accounts -> theatres (theatre_id));
+diesel::joinable!(loyalty_accounts -> users (user_id));
+diesel::joinable!(loyalty_entries -> theatres (theatre_id));
+diesel::joinable!(loyalty_entries -> tickets (ticket_id));
+diesel::joinable!(loyalty_entries -> users (user_id));
+diesel::joinable!(membership_periods -> membership_subscriptions (membership_subscription_id));
+diesel::joinable!(membership_plans -> theatres (theatre_id));
+diesel::joinable!(membership_subscriptions -> membership_plans (membership_plan_id));
 diesel::joinable!(movie_reviews -> movies (movie_id));
 diesel::joinable!(movie_reviews -> users (author_user_id));
+diesel::joinable!(orders -> gift_cards (gift_card_id));
+diesel::joinable!(orders -> promo_codes (promo_code_id));
+diesel::joinable!(orders -> theatre_screenings (theatre_screening_id));
+diesel::joinable!(orders -> users (user_id));
+diesel::joinable!(payments -> orders (order_id));
+diesel::joinable!(pricing_rules -> theatre_screenings (theatre_screening_id));
+diesel::joinable!(pricing_rules -> ticket_types (ticket_type_id));
+diesel::joinable!(promo_codes -> theatres (theatre_id));
+diesel::joinable!(refunds -> tickets (ticket_id));
+diesel::joinable!(refunds -> users (user_id));
+diesel::joinable!(screening_schedules -> halls (hall_id));
+diesel::joinable!(screening_schedules -> movies (movie_id));
+diesel::joinable!(screening_schedules -> theatres (theatre_id));
+diesel::joinable!(seat_holds -> theatre_screenings (theatre_screening_id));
+diesel::joinable!(seat_holds -> users (user_id));
 diesel::joinable!(theatre_screenings -> halls (hall_id));
 diesel::joinable!(theatre_screenings -> movies (movie_id));
+diesel::joinable!(theatre_screenings -> screening_schedules (schedule_id));
 diesel::joinable!(theatre_screenings -> theatres (theatre_id));
+diesel::joinable!(ticket_check_ins -> tickets (ticket_id));
+diesel::joinable!(ticket_check_ins -> users (checker_user_id));
+diesel::joinable!(ticket_transfers -> tickets (ticket_id));
 diesel::joinable!(ticket_types -> theatres (theatre_id));
+diesel::joinable!(tickets -> membership_subscriptions (membership_subscription_id));
+diesel::joinable!(tickets -> orders (order_id));
+diesel::joinable!(tickets -> promo_codes (promo_code_id));
 diesel::joinable!(tickets -> theatre_screenings (theatre_screening_id));
 diesel::joinable!(tickets -> ticket_types (ticket_type_id));
 diesel::joinable!(tickets -> users (owner_user_id));
@@ -156,13 +501,29 @@ diesel::joinable!(users_theatre_roles -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     external_credentials,
+    gift_card_entries,
+    gift_cards,
     halls,
     languages,
+    loyalty_accounts,
+    loyalty_entries,
+    membership_periods,
+    membership_plans,
+    membership_subscriptions,
     movie_reviews,
     movies,
+    orders,
+    payments,
+    pricing_rules,
+    promo_codes,
+    refunds,
+    screening_schedules,
+    seat_holds,
     theatre_roles,
     theatre_screenings,
     theatres,
+    ticket_check_ins,
+    ticket_transfers,
     ticket_types,
     tickets,
     users,
@@ -0,0 +1,217 @@
+use deadpool_diesel::postgres::Pool;
+use diesel::prelude::*;
+use ring::rand::{SecureRandom, SystemRandom};
+
+use super::{DatabaseError, TransactionError};
+use crate::model::*;
+use crate::schema::*;
+
+/// Generates an unguessable gift card code (80 random bits), printed in groups of four
+pub(super) fn generate_gift_card_code() -> Result<String, ring::error::Unspecified> {
+    let mut bytes = [0u8; GiftCard::CODE_LENGTH];
+    SystemRandom::new().fill(&mut bytes)?;
+
+    // the alphabet has 32 characters, so the low 5 bits of a byte pick one uniformly
+    let chars = bytes
+        .iter()
+        .map(|x| GiftCard::CODE_ALPHABET[(x & 0x1f) as usize] as char)
+        .collect::<Vec<_>>();
+
+    Ok(chars
+        .chunks(4)
+        .map(|x| x.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("-"))
+}
+
+/// Changes a gift card's balance by `amount`, recording the change in its ledger.
+/// The database refuses changes which would leave the balance negative
+pub(super) fn record_gift_card_entry(
+    conn: &mut PgConnection,
+    entry: CreateGiftCardEntry,
+) -> QueryResult<GiftCardEntry> {
+    diesel::update(gift_cards::table.find(entry.gift_card_id))
+        .set(gift_cards::balance.eq(gift_cards::balance + entry.amount))
+        .execute(conn)?;
+
+    diesel::insert_into(gift_card_entries::table)
+        .values(entry)
+        .returning(GiftCardEntry::as_returning())
+        .get_result(conn)
+}
+
+/// Pays as much of a freshly placed order as the balance of the theatre's gift card covers,
+/// confirming the order right away if the card covers all of it
+pub(super) fn redeem_gift_card(
+    conn: &mut PgConnection,
+    theatre_id: uuid::Uuid,
+    code: &str,
+    order: Order,
+) -> Result<Order, TransactionError> {
+    let Some(gift_card) = gift_cards::table
+        .filter(gift_cards::code_hash.eq(GiftCard::hash_code(code)))
+        .filter(gift_cards::theatre_id.eq(theatre_id))
+        .select(GiftCard::as_select())
+        .for_update()
+        .first::<GiftCard>(conn)
+        .optional()?
+    else {
+        return Err(TransactionError::Invalid);
+    };
+
+    let amount = gift_card.balance.min(order.total_price);
+    if gift_card.currency != order.currency || amount <= 0 {
+        return Err(TransactionError::Invalid);
+    }
+
+    record_gift_card_entry(
+        conn,
+        CreateGiftCardEntry {
+            gift_card_id: gift_card.id,
+            kind: GiftCardEntryKind::Redemption,
+            amount: -amount,
+            order_id: Some(order.id),
+            refund_id: None,
+        },
+    )?;
+
+    let status = if amount == order.total_price {
+        OrderStatus::Confirmed
+    } else {
+        order.status
+    };
+
+    Ok(diesel::update(orders::table.find(order.id))
+        .set((
+            orders::gift_card_id.eq(gift_card.id),
+            orders::gift_card_amount.eq(amount),
+            orders::status.eq(status),
+        ))
+        .returning(Order::as_returning())
+        .get_result(conn)?)
+}
+
+/// Puts what orders which fell through before being paid for took off of their
+/// gift cards back on them, each order is only ever given its money back once
+pub(super) fn return_gift_card_redemptions(
+    conn: &mut PgConnection,
+    order_ids: &[uuid::Uuid],
+) -> QueryResult<()> {
+    let orders = orders::table
+        .filter(orders::id.eq_any(order_ids))
+        .filter(orders::gift_card_amount.gt(0))
+        .filter(diesel::dsl::not(diesel::dsl::exists(
+            gift_card_entries::table
+                .filter(gift_card_entries::order_id.eq(orders::id.nullable()))
+                .filter(gift_card_entries::kind.eq(GiftCardEntryKind::Refund))
+                .filter(gift_card_entries::refund_id.is_null()),
+        )))
+        .select(Order::as_select())
+        .load::<Order>(conn)?;
+
+    for order in orders {
+        let Some(gift_card_id) = order.gift_card_id else {
+            continue;
+        };
+
+        record_gift_card_entry(
+            conn,
+            CreateGiftCardEntry {
+                gift_card_id,
+                kind: GiftCardEntryKind::Refund,
+                amount: order.gift_card_amount,
+                order_id: Some(order.id),
+                refund_id: None,
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Puts the gift card's share of a refund back on the card, that is the refund up to what's
+/// left of the order's gift card amount. Returns the amount put on the card, which is
+/// only recorded once per refund. The order has to be locked by the caller
+pub(super) fn credit_refund_to_gift_card(
+    conn: &mut PgConnection,
+    refund: &Refund,
+    order: &Order,
+) -> QueryResult<i64> {
+    let Some(gift_card_id) = order.gift_card_id else {
+        return Ok(0);
+    };
+
+    if let Some(credited) = gift_card_entries::table
+        .filter(gift_card_entries::refund_id.eq(refund.id))
+        .select(gift_card_entries::amount)
+        .first::<i64>(conn)
+        .optional()?
+    {
+        return Ok(credited);
+    }
+
+    let returned = gift_card_entries::table
+        .filter(gift_card_entries::order_id.eq(order.id))
+        .filter(gift_card_entries::kind.eq(GiftCardEntryKind::Refund))
+        .select(gift_card_entries::amount)
+        .load::<i64>(conn)?
+        .iter()
+        .sum::<i64>();
+
+    let credited = refund.amount.min(order.gift_card_amount - returned).max(0);
+    if credited > 0 {
+        record_gift_card_entry(
+            conn,
+            CreateGiftCardEntry {
+                gift_card_id,
+                kind: GiftCardEntryKind::Refund,
+                amount: credited,
+                order_id: Some(order.id),
+                refund_id: Some(refund.id),
+            },
+        )?;
+    }
+
+    Ok(credited)
+}
+
+/// This service represents the 'gift_cards' table for customers,
+/// theatres issue and manage their cards through `TheatreResource`
+#[derive(Clone)]
+pub struct GiftCardService {
+    pool: Pool,
+}
+
+impl GiftCardService {
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+
+    /// looks up the balance of a gift card by its code, `None` if there's no such card
+    pub async fn get_balance(
+        &self,
+        code: String,
+    ) -> Result<Option<GiftCardBalance>, DatabaseError> {
+        let conn = self.pool.get().await?;
+
+        let gift_card = conn
+            .interact(move |conn| {
+                gift_cards::table
+                    .filter(gift_cards::code_hash.eq(GiftCard::hash_code(&code)))
+                    .select(GiftCard::as_select())
+                    .first::<GiftCard>(conn)
+                    .optional()
+            })
+            .await??;
+
+        let Some(gift_card) = gift_card else {
+            return Ok(None);
+        };
+
+        Ok(Some(GiftCardBalance {
+            balance: gift_card.balance()?,
+            theatre_id: gift_card.theatre_id,
+            code_suffix: gift_card.code_suffix,
+        }))
+    }
+}
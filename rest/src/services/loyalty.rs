@@ -0,0 +1,190 @@
+use diesel::prelude::*;
+
+use super::TransactionError;
+use crate::model::*;
+use crate::schema::*;
+
+/// Changes the user's points at a theatre by `entry.points`, recording the change in their
+/// ledger. The database refuses changes which would leave the account with negative points
+pub(super) fn record_loyalty_entry(
+    conn: &mut PgConnection,
+    entry: CreateLoyaltyEntry,
+) -> QueryResult<LoyaltyEntry> {
+    // the balance check applies to the inserted row as well, so accounts start out empty
+    diesel::insert_into(loyalty_accounts::table)
+        .values((
+            loyalty_accounts::user_id.eq(entry.user_id),
+            loyalty_accounts::theatre_id.eq(entry.theatre_id),
+        ))
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+    diesel::update(loyalty_accounts::table.find((entry.user_id, entry.theatre_id)))
+        .set(loyalty_accounts::points.eq(loyalty_accounts::points + entry.points))
+        .execute(conn)?;
+
+    diesel::insert_into(loyalty_entries::table)
+        .values(entry)
+        .returning(LoyaltyEntry::as_returning())
+        .get_result(conn)
+}
+
+/// Locks the user's account at the theatre for the rest of the transaction,
+/// returning its points. Users who haven't earned any points yet have no account
+fn lock_loyalty_account(
+    conn: &mut PgConnection,
+    user_id: uuid::Uuid,
+    theatre_id: uuid::Uuid,
+) -> QueryResult<i32> {
+    Ok(loyalty_accounts::table
+        .find((user_id, theatre_id))
+        .select(loyalty_accounts::points)
+        .for_update()
+        .first::<i32>(conn)
+        .optional()?
+        .unwrap_or(0))
+}
+
+/// Keeps the points earned for a (locked) ticket in line with its `used` state after a
+/// check-in. The owner earns the ticket's points the first time it's used and they're
+/// taken back when undoing leaves it unused, as far as they haven't been spent yet
+pub(super) fn sync_loyalty_points(
+    conn: &mut PgConnection,
+    ticket_id: uuid::Uuid,
+    used: bool,
+) -> QueryResult<()> {
+    let entries = loyalty_entries::table
+        .filter(loyalty_entries::ticket_id.eq(ticket_id))
+        .order(loyalty_entries::created_at)
+        .select(LoyaltyEntry::as_select())
+        .load::<LoyaltyEntry>(conn)?;
+    let earned = entries
+        .iter()
+        .filter(|x| matches!(x.kind, LoyaltyEntryKind::Earn | LoyaltyEntryKind::Revoke))
+        .map(|x| x.points)
+        .sum::<i32>();
+
+    if used && earned == 0 {
+        // tickets redeemed for points are free, so they don't earn any
+        if entries
+            .iter()
+            .any(|x| x.kind == LoyaltyEntryKind::Redemption)
+        {
+            return Ok(());
+        }
+
+        let (ticket, theatre_id, points_per_unit) = tickets::table
+            .inner_join(theatre_screenings::table.inner_join(theatres::table))
+            .filter(tickets::id.eq(ticket_id))
+            .select((
+                Ticket::as_select(),
+                theatres::id,
+                theatres::loyalty_points_per_unit,
+            ))
+            .first::<(Ticket, uuid::Uuid, i32)>(conn)?;
+
+        let points = ticket.loyalty_points(points_per_unit);
+        if points > 0 {
+            record_loyalty_entry(
+                conn,
+                CreateLoyaltyEntry {
+                    user_id: ticket.owner_user_id,
+                    theatre_id,
+                    kind: LoyaltyEntryKind::Earn,
+                    points,
+                    ticket_id: Some(ticket_id),
+                },
+            )?;
+        }
+    } else if !used && earned > 0 {
+        let Some(earn) = entries
+            .iter()
+            .rev()
+            .find(|x| x.kind == LoyaltyEntryKind::Earn)
+        else {
+            return Ok(());
+        };
+
+        let available = lock_loyalty_account(conn, earn.user_id, earn.theatre_id)?;
+        let points = earned.min(available);
+        if points > 0 {
+            record_loyalty_entry(
+                conn,
+                CreateLoyaltyEntry {
+                    user_id: earn.user_id,
+                    theatre_id: earn.theatre_id,
+                    kind: LoyaltyEntryKind::Revoke,
+                    points: -points,
+                    ticket_id: Some(ticket_id),
+                },
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Takes the price of a free ticket off of the user's points at the theatre,
+/// fails with `Invalid` if they don't have enough of them
+pub(super) fn redeem_loyalty_points(
+    conn: &mut PgConnection,
+    user_id: uuid::Uuid,
+    theatre_id: uuid::Uuid,
+    points: i32,
+    ticket_id: uuid::Uuid,
+) -> Result<LoyaltyEntry, TransactionError> {
+    if lock_loyalty_account(conn, user_id, theatre_id)? < points {
+        return Err(TransactionError::Invalid);
+    }
+
+    Ok(record_loyalty_entry(
+        conn,
+        CreateLoyaltyEntry {
+            user_id,
+            theatre_id,
+            kind: LoyaltyEntryKind::Redemption,
+            points: -points,
+            ticket_id: Some(ticket_id),
+        },
+    )?)
+}
+
+/// Puts the points which cancelled free tickets were redeemed for back
+/// on the accounts they were taken from, each ticket's points are only returned once
+pub(super) fn return_loyalty_redemptions(
+    conn: &mut PgConnection,
+    ticket_ids: &[uuid::Uuid],
+) -> QueryResult<()> {
+    let entries = loyalty_entries::table
+        .filter(loyalty_entries::ticket_id.eq_any(ticket_ids))
+        .filter(
+            loyalty_entries::kind.eq_any([LoyaltyEntryKind::Redemption, LoyaltyEntryKind::Refund]),
+        )
+        .select(LoyaltyEntry::as_select())
+        .load::<LoyaltyEntry>(conn)?;
+
+    let redeemed = entries
+        .iter()
+        .filter(|x| x.kind == LoyaltyEntryKind::Redemption)
+        .filter(|x| {
+            !entries
+                .iter()
+                .any(|y| y.kind == LoyaltyEntryKind::Refund && y.ticket_id == x.ticket_id)
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+
+    for entry in redeemed {
+        record_loyalty_entry(
+            conn,
+            CreateLoyaltyEntry {
+                user_id: entry.user_id,
+                theatre_id: entry.theatre_id,
+                kind: LoyaltyEntryKind::Refund,
+                points: -entry.points,
+                ticket_id: entry.ticket_id,
+            },
+        )?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use diesel::prelude::*;
+
+use super::TransactionError;
+use crate::model::*;
+use crate::schema::*;
+
+/// The billing period of the membership which `at` falls into, if it's been paid for
+fn membership_period_at(
+    conn: &mut PgConnection,
+    subscription_id: uuid::Uuid,
+    at: chrono::NaiveDateTime,
+) -> QueryResult<Option<MembershipPeriod>> {
+    membership_periods::table
+        .filter(membership_periods::membership_subscription_id.eq(subscription_id))
+        .filter(membership_periods::starts_at.le(at))
+        .filter(membership_periods::ends_at.gt(at))
+        .select(MembershipPeriod::as_select())
+        .first(conn)
+        .optional()
+}
+
+/// Number of valid tickets booked with the membership during the billing period
+fn membership_tickets_used(conn: &mut PgConnection, period: &MembershipPeriod) -> QueryResult<i64> {
+    tickets::table
+        .filter(tickets::membership_subscription_id.eq(period.membership_subscription_id))
+        .filter(tickets::is_cancelled.eq(false))
+        .filter(tickets::issued_at.ge(period.starts_at))
+        .filter(tickets::issued_at.lt(period.ends_at))
+        .count()
+        .get_result(conn)
+}
+
+/// Checks that the user's memberships the tickets (given as the membership and ticket type
+/// of each) are booked with cover them, locking the memberships for the rest of the
+/// transaction. A membership covers a single seat of a screening which starts while it's
+/// paid for, fails with `AllowanceExceeded` if the tickets don't fit in its allowance
+pub(super) fn check_membership_coverage(
+    conn: &mut PgConnection,
+    user_id: uuid::Uuid,
+    screening: &TheatreScreening,
+    tickets: &[(uuid::Uuid, uuid::Uuid)],
+) -> Result<(), TransactionError> {
+    let now = chrono::Utc::now().naive_utc();
+
+    let mut subscription_ids = tickets.iter().map(|(x, _)| *x).collect::<Vec<_>>();
+    subscription_ids.sort();
+    subscription_ids.dedup();
+
+    for subscription_id in subscription_ids {
+        let Some((subscription, plan)) = membership_subscriptions::table
+            .inner_join(membership_plans::table)
+            .filter(membership_subscriptions::id.eq(subscription_id))
+            .filter(membership_subscriptions::user_id.eq(user_id))
+            .select((
+                MembershipSubscription::as_select(),
+                MembershipPlan::as_select(),
+            ))
+            .for_update()
+            .first::<(MembershipSubscription, MembershipPlan)>(conn)
+            .optional()?
+        else {
+            return Err(TransactionError::Invalid);
+        };
+
+        let booked = tickets
+            .iter()
+            .filter(|(x, _)| *x == subscription.id)
+            .collect::<Vec<_>>();
+        if plan.theatre_id != screening.theatre_id || booked.iter().any(|(_, x)| !plan.covers(*x)) {
+            return Err(TransactionError::Invalid);
+        }
+
+        let Some(period) = membership_period_at(conn, subscription.id, now)? else {
+            return Err(TransactionError::Invalid);
+        };
+        let paid_until = membership_periods::table
+            .filter(membership_periods::membership_subscription_id.eq(subscription.id))
+            .select(diesel::dsl::max(membership_periods::ends_at))
+            .first::<Option<chrono::NaiveDateTime>>(conn)?;
+        if paid_until.is_none_or(|x| screening.starting_time >= x) {
+            return Err(TransactionError::Invalid);
+        }
+
+        let booked_for_screening = tickets::table
+            .filter(tickets::membership_subscription_id.eq(subscription.id))
+            .filter(tickets::theatre_screening_id.eq(screening.id))
+            .filter(tickets::is_cancelled.eq(false))
+            .count()
+            .get_result::<i64>(conn)?;
+        if booked_for_screening + booked.len() as i64 > 1 {
+            return Err(TransactionError::Invalid);
+        }
+
+        if let Some(allowance) = plan.tickets_per_period {
+            let used = membership_tickets_used(conn, &period)?;
+
+            if used + booked.len() as i64 > i64::from(allowance) {
+                return Err(TransactionError::AllowanceExceeded);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds the billing periods and the current period's usage to memberships
+pub(super) fn extend_membership_subscriptions(
+    conn: &mut PgConnection,
+    subscriptions: Vec<(MembershipSubscription, MembershipPlan)>,
+) -> QueryResult<Vec<ExtendedMembershipSubscription>> {
+    let now = chrono::Utc::now().naive_utc();
+    let (subscriptions, plans): (Vec<_>, Vec<_>) = subscriptions.into_iter().unzip();
+
+    let periods = MembershipPeriod::belonging_to(&subscriptions)
+        .order(membership_periods::starts_at)
+        .select(MembershipPeriod::as_select())
+        .load::<MembershipPeriod>(conn)?
+        .grouped_by(&subscriptions);
+
+    subscriptions
+        .into_iter()
+        .zip(plans)
+        .zip(periods)
+        .map(|((subscription, plan), periods)| {
+            let tickets_used = match periods.iter().find(|x| x.contains(now)) {
+                Some(period) => membership_tickets_used(conn, period)?,
+                None => 0,
+            };
+
+            Ok(ExtendedMembershipSubscription {
+                subscription,
+                plan,
+                periods,
+                tickets_used,
+            })
+        })
+        .collect()
+}
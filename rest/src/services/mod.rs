@@ -4,15 +4,95 @@ pub mod user;
 pub mod bridge_role;
 pub mod role;
 pub mod language;
+pub mod seat_hold;
+pub mod seat_event;
+pub mod payment;
+pub mod gift_card;
+pub mod loyalty;
+pub mod membership;
+
+use std::str::FromStr;
 
 use argon2::password_hash;
 use deadpool_diesel::{InteractError, PoolError};
+use diesel::result::DatabaseErrorKind;
 use either::Either;
-use lettre::{address::AddressError, Message};
+use lettre::message::{
+    header::ContentType, Attachment, Mailbox, MessageBuilder, MultiPart, SinglePart,
+};
+use lettre::{address::AddressError, Address, Message};
 use serde::Deserialize;
 use tokio::sync::mpsc::error::SendError;
 use utoipa::{IntoParams, ToSchema};
 
+use crate::model::MoneyError;
+use crate::payment::PaymentError;
+use crate::vars::gmail_user;
+
+pub type MailBuildError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Starts an email sent from the service's own address
+fn service_mail(to_email: &str, subject: &str) -> Result<MessageBuilder, MailBuildError> {
+    let Some(from_address) = gmail_user() else {
+        return Err("Problem building an email, because of gmail_user var missing".into());
+    };
+
+    let from_address = Address::from_str(&from_address)?;
+    let to_address = Address::from_str(to_email)?;
+
+    Ok(Message::builder()
+        .from(Mailbox::new(Some("Nice Movies".to_owned()), from_address))
+        .to(Mailbox::new(None, to_address))
+        .subject(subject))
+}
+
+/// Escapes text interpolated into the HTML body of an email, e.g. names users picked
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Builds an HTML email sent from the service's own address,
+/// text interpolated into `body` has to be run through [`escape_html`]
+pub fn notification_mail(
+    to_email: &str,
+    subject: &str,
+    body: String,
+) -> Result<Message, MailBuildError> {
+    Ok(service_mail(to_email, subject)?
+        .header(ContentType::TEXT_HTML)
+        .body(body)?)
+}
+
+/// Builds a plain text email sent from the service's own address, with a PDF attached
+pub fn pdf_attachment_mail(
+    to_email: &str,
+    subject: &str,
+    body: String,
+    filename: String,
+    pdf: Vec<u8>,
+) -> Result<Message, MailBuildError> {
+    Ok(service_mail(to_email, subject)?.multipart(
+        MultiPart::mixed()
+            .singlepart(SinglePart::plain(body))
+            .singlepart(
+                Attachment::new(filename).body(pdf, ContentType::parse("application/pdf")?),
+            ),
+    )?)
+}
+
 #[derive(Deserialize, Copy, Clone, ToSchema)]
 pub enum SortBy {
     Newest,
@@ -35,10 +115,71 @@ pub enum DatabaseError {
     EmailSend(#[from] SendError<Message>),
     #[error("something went wrong when building an email")]
     EmailBuild(Either<AddressError, lettre::error::Error>),
+    #[error("resource conflicts with an already existing one")]
+    Conflict(Vec<uuid::Uuid>),
+    #[error("operation isn't applicable to the given resource")]
+    Invalid,
+    #[error("tickets for the screening aren't on sale")]
+    NotOnSale,
+    #[error("the ticket has been cancelled")]
+    Cancelled,
+    #[error("the cancellation cutoff of the screening has passed")]
+    PastCutoff,
+    #[error("the membership's allowance for its billing period is used up")]
+    AllowanceExceeded,
+    #[error("the user would exceed the maximum amount of tickets for the screening")]
+    TicketLimitExceeded,
+    #[error("payment provider request was unsuccessful")]
+    Payment(#[from] PaymentError),
+    #[error("the payment was declined")]
+    PaymentDeclined,
+    #[error("amount of money is invalid")]
+    Money(#[from] MoneyError),
     #[error("{}", .0)]
     Other(String)
 }
 
+/// Error type for closures ran inside of a database transaction,
+/// it is kept small so it can be passed out of `interact` cheaply
+#[derive(Debug)]
+pub enum TransactionError {
+    Query(diesel::result::Error),
+    /// holds the IDs of the clashing resources, if there are any worth exposing
+    Conflict(Vec<uuid::Uuid>),
+    Invalid,
+    NotOnSale,
+    Cancelled,
+    PastCutoff,
+    AllowanceExceeded,
+    TicketLimitExceeded,
+}
+
+impl From<diesel::result::Error> for TransactionError {
+    fn from(value: diesel::result::Error) -> Self {
+        match value {
+            diesel::result::Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+                Self::Conflict(vec![])
+            }
+            e => Self::Query(e),
+        }
+    }
+}
+
+impl From<TransactionError> for DatabaseError {
+    fn from(value: TransactionError) -> Self {
+        match value {
+            TransactionError::Query(e) => Self::Query(e),
+            TransactionError::Conflict(ids) => Self::Conflict(ids),
+            TransactionError::Invalid => Self::Invalid,
+            TransactionError::NotOnSale => Self::NotOnSale,
+            TransactionError::Cancelled => Self::Cancelled,
+            TransactionError::PastCutoff => Self::PastCutoff,
+            TransactionError::AllowanceExceeded => Self::AllowanceExceeded,
+            TransactionError::TicketLimitExceeded => Self::TicketLimitExceeded,
+        }
+    }
+}
+
 impl From<AddressError> for DatabaseError {
     fn from(value: AddressError) -> Self {
         Self::EmailBuild(Either::Left(value))
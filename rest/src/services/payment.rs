@@ -0,0 +1,453 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use deadpool_diesel::postgres::Pool;
+use diesel::prelude::*;
+
+use super::{
+    gift_card::{credit_refund_to_gift_card, return_gift_card_redemptions},
+    seat_event::SeatEventBus,
+    user::void_pending_transfers,
+    DatabaseError, TransactionError,
+};
+use crate::model::*;
+use crate::payment::PaymentProvider;
+use crate::schema::*;
+
+/// Outcome of applying a payment state change to its order
+struct AppliedPayment {
+    payment: Payment,
+    /// tickets whose seats got released, because the order fell through
+    released: Vec<Ticket>,
+    /// the order had already been abandoned when its payment went through
+    refund_due: bool,
+}
+
+/// Cancels every ticket of a pending order along with the order itself,
+/// giving the seats back. The order has to be locked by the caller
+fn release_order(conn: &mut PgConnection, order: &Order) -> QueryResult<Vec<Ticket>> {
+    let released = diesel::update(
+        tickets::table
+            .filter(tickets::order_id.eq(order.id))
+            .filter(tickets::is_cancelled.eq(false)),
+    )
+    .set(tickets::is_cancelled.eq(true))
+    .returning(Ticket::as_returning())
+    .get_results::<Ticket>(conn)?;
+
+    void_pending_transfers(conn, &released.iter().map(|x| x.id).collect::<Vec<_>>())?;
+
+    diesel::update(orders::table.find(order.id))
+        .set(orders::status.eq(OrderStatus::Cancelled))
+        .execute(conn)?;
+    return_gift_card_redemptions(conn, &[order.id])?;
+
+    diesel::update(
+        theatre_screenings::table
+            .filter(theatre_screenings::id.eq(order.theatre_screening_id))
+            .filter(theatre_screenings::status.eq(ScreeningStatus::SoldOut)),
+    )
+    .set(theatre_screenings::status.eq(ScreeningStatus::OnSale))
+    .execute(conn)?;
+
+    Ok(released)
+}
+
+/// This service represents the 'payments' table, it drives
+/// the payment of orders through the configured provider
+#[derive(Clone)]
+pub struct PaymentService {
+    pool: Pool,
+    seat_events: SeatEventBus,
+    provider: Arc<dyn PaymentProvider>,
+}
+
+impl PaymentService {
+    pub fn new(pool: Pool, seat_events: SeatEventBus, provider: Arc<dyn PaymentProvider>) -> Self {
+        Self {
+            pool,
+            seat_events,
+            provider,
+        }
+    }
+
+    /// creates a payment intent for what's due of a freshly placed order and attaches it
+    /// to the order, orders which a gift card paid for entirely are left as they are.
+    /// The order is cancelled if its payment can't be started, so its seats aren't left taken
+    pub async fn start_payment(
+        &self,
+        mut order: ExtendedOrder,
+    ) -> Result<ExtendedOrder, DatabaseError> {
+        if order.order.status != OrderStatus::Pending {
+            return Ok(order);
+        }
+
+        match self.create_payment(&order.order).await {
+            Ok(payment) => {
+                order.payment = Some(payment);
+                Ok(order)
+            }
+            Err(e) => {
+                if let Err(cancel_error) = self.cancel_pending_order(order.order.id).await {
+                    log::error!(
+                        "Couldn't cancel order {} after its payment failed to start: {:?}",
+                        order.order.id,
+                        cancel_error
+                    );
+                }
+                Err(e)
+            }
+        }
+    }
+
+    async fn create_payment(&self, order: &Order) -> Result<Payment, DatabaseError> {
+        let amount_due = order.amount_due()?;
+        let intent = self.provider.create_intent(&amount_due, order.id).await?;
+
+        let conn = self.pool.get().await?;
+        let new_payment = CreatePayment {
+            order_id: order.id,
+            provider: self.provider.name().to_owned(),
+            intent_id: intent.id,
+            client_secret: intent.client_secret,
+            amount: amount_due.amount(),
+            currency: amount_due.currency().to_owned(),
+        };
+
+        Ok(conn
+            .interact(move |conn| {
+                diesel::insert_into(payments::table)
+                    .values(new_payment)
+                    .returning(Payment::as_returning())
+                    .get_result(conn)
+            })
+            .await??)
+    }
+
+    /// cancels an order which is still pending, giving its seats and gift card redemption back
+    async fn cancel_pending_order(&self, order_id: uuid::Uuid) -> Result<(), DatabaseError> {
+        let conn = self.pool.get().await?;
+
+        let released = conn
+            .interact(move |conn| {
+                conn.transaction::<_, TransactionError, _>(|conn| {
+                    let order = orders::table
+                        .find(order_id)
+                        .select(Order::as_select())
+                        .for_update()
+                        .first::<Order>(conn)?;
+
+                    if order.status != OrderStatus::Pending {
+                        return Ok(vec![]);
+                    }
+
+                    Ok(release_order(conn, &order)?)
+                })
+            })
+            .await??;
+
+        self.publish_released(&released);
+
+        Ok(())
+    }
+
+    /// confirms the payment of one of the user's pending orders with the given payment method,
+    /// a declined payment cancels the order
+    pub async fn pay_order(
+        &self,
+        user_id: uuid::Uuid,
+        order_id: uuid::Uuid,
+        payment_method: String,
+    ) -> Result<Payment, DatabaseError> {
+        let conn = self.pool.get().await?;
+
+        let (order, payment) = conn
+            .interact(move |conn| {
+                orders::table
+                    .inner_join(payments::table)
+                    .filter(orders::id.eq(order_id))
+                    .filter(orders::user_id.eq(user_id))
+                    .select((Order::as_select(), Payment::as_select()))
+                    .first::<(Order, Payment)>(conn)
+            })
+            .await??;
+
+        if order.status != OrderStatus::Pending
+            || payment.status != PaymentStatus::RequiresConfirmation
+        {
+            return Err(DatabaseError::Invalid);
+        }
+
+        let status = self
+            .provider
+            .confirm(&payment.intent_id, &payment_method)
+            .await?;
+        let payment = self.apply_status(payment.intent_id, status).await?;
+
+        match payment.status {
+            PaymentStatus::Succeeded => Ok(payment),
+            _ => Err(DatabaseError::PaymentDeclined),
+        }
+    }
+
+    /// applies a payment state change the provider notified us about through its webhook
+    pub async fn handle_webhook(
+        &self,
+        signature: &str,
+        payload: &[u8],
+    ) -> Result<Payment, DatabaseError> {
+        let event = self.provider.verify_webhook(signature, payload)?;
+
+        self.apply_status(event.intent_id, event.status).await
+    }
+
+    /// Moves a payment (and its order) into its final state. Payments which have already
+    /// been resolved are left as they are, so the same change can be applied more than once
+    async fn apply_status(
+        &self,
+        intent_id: String,
+        status: PaymentStatus,
+    ) -> Result<Payment, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let provider = self.provider.name();
+
+        let applied = conn
+            .interact(move |conn| {
+                conn.transaction::<_, TransactionError, _>(|conn| {
+                    let payment = payments::table
+                        .filter(payments::provider.eq(provider))
+                        .filter(payments::intent_id.eq(&intent_id))
+                        .select(Payment::as_select())
+                        .for_update()
+                        .first::<Payment>(conn)?;
+
+                    // an abandoned order can still get paid for, its money is returned then
+                    let late_success = payment.status == PaymentStatus::Cancelled
+                        && status == PaymentStatus::Succeeded;
+
+                    if status == PaymentStatus::RequiresConfirmation
+                        || (payment.status != PaymentStatus::RequiresConfirmation && !late_success)
+                    {
+                        return Ok(AppliedPayment {
+                            payment,
+                            released: vec![],
+                            refund_due: false,
+                        });
+                    }
+
+                    let payment = diesel::update(payments::table.find(payment.id))
+                        .set((
+                            payments::status.eq(status),
+                            payments::updated_at.eq(Utc::now().naive_utc()),
+                        ))
+                        .returning(Payment::as_returning())
+                        .get_result::<Payment>(conn)?;
+
+                    let order = orders::table
+                        .find(payment.order_id)
+                        .select(Order::as_select())
+                        .for_update()
+                        .first::<Order>(conn)?;
+
+                    if order.status != OrderStatus::Pending {
+                        return Ok(AppliedPayment {
+                            payment,
+                            released: vec![],
+                            refund_due: status == PaymentStatus::Succeeded,
+                        });
+                    }
+
+                    let released = if status == PaymentStatus::Succeeded {
+                        diesel::update(orders::table.find(order.id))
+                            .set(orders::status.eq(OrderStatus::Confirmed))
+                            .execute(conn)?;
+                        vec![]
+                    } else {
+                        release_order(conn, &order)?
+                    };
+
+                    Ok(AppliedPayment {
+                        payment,
+                        released,
+                        refund_due: false,
+                    })
+                })
+            })
+            .await??;
+
+        self.publish_released(&applied.released);
+
+        if applied.refund_due {
+            let payment = &applied.payment;
+            if let Err(e) = self
+                .provider
+                .refund(&payment.intent_id, &payment.amount()?)
+                .await
+            {
+                log::error!(
+                    "Couldn't refund payment {} of an abandoned order: {:?}",
+                    payment.id,
+                    e
+                );
+            }
+        }
+
+        Ok(applied.payment)
+    }
+
+    /// cancels pending orders which weren't paid for within `ttl`, giving their seats back
+    pub async fn expire_pending_orders(
+        &self,
+        ttl: chrono::Duration,
+    ) -> Result<usize, DatabaseError> {
+        let conn = self.pool.get().await?;
+
+        let released = conn
+            .interact(move |conn| {
+                conn.transaction::<_, TransactionError, _>(|conn| {
+                    let expired = orders::table
+                        .filter(orders::status.eq(OrderStatus::Pending))
+                        .filter(orders::created_at.le(Utc::now().naive_utc() - ttl))
+                        .select(Order::as_select())
+                        .for_update()
+                        .skip_locked()
+                        .load::<Order>(conn)?;
+
+                    diesel::update(
+                        payments::table
+                            .filter(payments::order_id.eq_any(expired.iter().map(|x| x.id)))
+                            .filter(payments::status.eq(PaymentStatus::RequiresConfirmation)),
+                    )
+                    .set((
+                        payments::status.eq(PaymentStatus::Cancelled),
+                        payments::updated_at.eq(Utc::now().naive_utc()),
+                    ))
+                    .execute(conn)?;
+
+                    let mut released = vec![];
+                    for order in expired.iter() {
+                        released.push(release_order(conn, order)?);
+                    }
+
+                    Ok(released)
+                })
+            })
+            .await??;
+
+        for tickets in released.iter() {
+            self.publish_released(tickets);
+        }
+
+        Ok(released.len())
+    }
+
+    /// Returns the money of cancelled tickets which were paid for online, the share of their
+    /// order's gift card goes back on the card and the rest through the provider. Returns how
+    /// many refunds were settled, refunds of tickets which weren't paid online (e.g. issued
+    /// by staff) are left for the theatre to settle
+    pub async fn settle_refunds(&self) -> Result<usize, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let provider = self.provider.name();
+
+        let pending = conn
+            .interact(move |conn| {
+                refunds::table
+                    .inner_join(tickets::table.inner_join(orders::table.left_join(payments::table)))
+                    .filter(refunds::settled_at.is_null())
+                    .filter(
+                        payments::provider
+                            .nullable()
+                            .eq(provider)
+                            .and(payments::status.nullable().eq(PaymentStatus::Succeeded))
+                            .or(orders::gift_card_id.is_not_null()),
+                    )
+                    .select((
+                        Refund::as_select(),
+                        Order::as_select(),
+                        payments::provider.nullable(),
+                        payments::intent_id.nullable(),
+                        payments::status.nullable(),
+                    ))
+                    .load::<(
+                        Refund,
+                        Order,
+                        Option<String>,
+                        Option<String>,
+                        Option<PaymentStatus>,
+                    )>(conn)
+            })
+            .await??;
+
+        let mut settled = vec![];
+
+        for (refund, order, payment_provider, intent_id, status) in pending {
+            let intent_id = intent_id.filter(|_| {
+                payment_provider.as_deref() == Some(provider)
+                    && status == Some(PaymentStatus::Succeeded)
+            });
+
+            let result = self.settle_refund(&refund, order, intent_id).await;
+
+            match result {
+                Ok(()) => settled.push(refund.id),
+                Err(e) => log::error!("Couldn't settle refund {}: {:?}", refund.id, e),
+            }
+        }
+
+        let conn = self.pool.get().await?;
+
+        Ok(conn
+            .interact(move |conn| {
+                diesel::update(refunds::table.filter(refunds::id.eq_any(settled)))
+                    .set(refunds::settled_at.eq(Utc::now().naive_utc()))
+                    .execute(conn)
+            })
+            .await??)
+    }
+
+    /// returns a refund's gift card share to the card, and the rest through the provider
+    async fn settle_refund(
+        &self,
+        refund: &Refund,
+        order: Order,
+        intent_id: Option<String>,
+    ) -> Result<(), DatabaseError> {
+        let conn = self.pool.get().await?;
+        let cloned_refund = refund.clone();
+
+        let credited = conn
+            .interact(move |conn| {
+                conn.transaction(|conn| {
+                    orders::table
+                        .find(order.id)
+                        .select(orders::id)
+                        .for_update()
+                        .first::<uuid::Uuid>(conn)?;
+
+                    credit_refund_to_gift_card(conn, &cloned_refund, &order)
+                })
+            })
+            .await??;
+
+        let remaining = Money::new(refund.amount - credited, &refund.currency)?;
+        if remaining.amount() <= 0 {
+            return Ok(());
+        }
+
+        let Some(intent_id) = intent_id else {
+            return Err(DatabaseError::Invalid);
+        };
+
+        Ok(self.provider.refund(&intent_id, &remaining).await?)
+    }
+
+    fn publish_released(&self, tickets: &[Ticket]) {
+        for ticket in tickets {
+            self.seat_events.publish(
+                ticket.theatre_screening_id,
+                [(ticket.seat_row, ticket.seat_column)],
+                SeatEventKind::Released,
+            );
+        }
+    }
+}
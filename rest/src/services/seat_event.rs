@@ -0,0 +1,46 @@
+use tokio::sync::broadcast::{self, Receiver, Sender};
+
+use crate::model::{SeatEvent, SeatEventKind};
+
+pub const SEAT_EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// In-process broadcast channel of seat status changes,
+/// every subscriber receives the events of all screenings
+#[derive(Clone)]
+pub struct SeatEventBus {
+    sender: Sender<SeatEvent>,
+}
+
+impl SeatEventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(SEAT_EVENT_CHANNEL_CAPACITY);
+        Self { sender }
+    }
+
+    pub fn subscribe(&self) -> Receiver<SeatEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn publish(
+        &self,
+        theatre_screening_id: uuid::Uuid,
+        seats: impl IntoIterator<Item = (i32, i32)>,
+        kind: SeatEventKind,
+    ) {
+        for (seat_row, seat_column) in seats {
+            // sending only fails when nobody is listening, which is fine
+            let _ = self.sender.send(SeatEvent {
+                theatre_screening_id,
+                seat_row,
+                seat_column,
+                kind,
+            });
+        }
+    }
+}
+
+impl Default for SeatEventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,250 @@
+use chrono::Utc;
+use deadpool_diesel::postgres::Pool;
+use diesel::prelude::*;
+
+use super::{
+    seat_event::SeatEventBus, theatre::lock_screening_seat_data, DatabaseError, TransactionError,
+};
+use crate::model::*;
+use crate::schema::*;
+use crate::vars::seat_hold_ttl_seconds;
+
+pub const DEFAULT_SEAT_HOLD_TTL_SECONDS: i64 = 600;
+
+/// how long held seats stay reserved, configurable through `SEAT_HOLD_TTL_SECONDS`
+pub fn seat_hold_ttl() -> chrono::Duration {
+    chrono::Duration::seconds(
+        seat_hold_ttl_seconds()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(DEFAULT_SEAT_HOLD_TTL_SECONDS),
+    )
+}
+
+/// Checks whether a seat is neither ticketed nor held by anyone other than `user_id`
+pub(super) fn seat_is_free(
+    conn: &mut PgConnection,
+    screening_id: uuid::Uuid,
+    user_id: uuid::Uuid,
+    seat: SeatPosition,
+) -> QueryResult<bool> {
+    let ticketed = diesel::select(diesel::dsl::exists(
+        tickets::table
+            .filter(tickets::theatre_screening_id.eq(screening_id))
+            .filter(tickets::is_cancelled.eq(false))
+            .filter(tickets::seat_row.eq(seat.seat_row))
+            .filter(tickets::seat_column.eq(seat.seat_column)),
+    ))
+    .get_result::<bool>(conn)?;
+
+    let held = diesel::select(diesel::dsl::exists(
+        seat_holds::table
+            .filter(seat_holds::theatre_screening_id.eq(screening_id))
+            .filter(seat_holds::seat_row.eq(seat.seat_row))
+            .filter(seat_holds::seat_column.eq(seat.seat_column))
+            .filter(seat_holds::user_id.ne(user_id))
+            .filter(seat_holds::expires_at.gt(Utc::now().naive_utc())),
+    ))
+    .get_result::<bool>(conn)?;
+
+    Ok(!ticketed && !held)
+}
+
+/// Number of valid tickets `user_id` owns for a screening
+pub(super) fn screening_tickets_owned(
+    conn: &mut PgConnection,
+    screening_id: uuid::Uuid,
+    user_id: uuid::Uuid,
+) -> QueryResult<i64> {
+    tickets::table
+        .filter(tickets::theatre_screening_id.eq(screening_id))
+        .filter(tickets::owner_user_id.eq(user_id))
+        .filter(tickets::is_cancelled.eq(false))
+        .count()
+        .get_result(conn)
+}
+
+/// This service represents the 'seat_holds' table,
+/// which reserves seats while their buyer goes through checkout
+#[derive(Clone)]
+pub struct SeatHoldService {
+    pool: Pool,
+    seat_events: SeatEventBus,
+}
+
+impl SeatHoldService {
+    pub fn new(pool: Pool, seat_events: SeatEventBus) -> Self {
+        Self { pool, seat_events }
+    }
+
+    /// holds the given seats for `user_id`, refreshing the expiry of any of them the user
+    /// has already been holding. The user's tickets and holds for the screening together
+    /// can't exceed `MAX_TICKETS_PER_SCREENING`
+    pub async fn hold_seats(
+        &self,
+        screening_id: uuid::Uuid,
+        user_id: uuid::Uuid,
+        mut seats: Vec<SeatPosition>,
+    ) -> Result<Vec<SeatHold>, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let expires_at = Utc::now().naive_utc() + seat_hold_ttl();
+
+        seats.sort();
+        seats.dedup();
+
+        let holds = conn
+            .interact(move |conn| {
+                conn.transaction::<_, TransactionError, _>(|conn| {
+                    let seat_data = lock_screening_seat_data(conn, screening_id)?;
+
+                    diesel::delete(
+                        seat_holds::table
+                            .filter(seat_holds::theatre_screening_id.eq(screening_id))
+                            .filter(seat_holds::expires_at.le(Utc::now().naive_utc())),
+                    )
+                    .execute(conn)?;
+
+                    for seat in seats.iter() {
+                        if !seat_data.is_bookable(seat.seat_row, seat.seat_column) {
+                            return Err(TransactionError::Invalid);
+                        }
+
+                        if !seat_is_free(conn, screening_id, user_id, *seat)? {
+                            return Err(TransactionError::Conflict(vec![]));
+                        }
+
+                        diesel::delete(
+                            seat_holds::table
+                                .filter(seat_holds::theatre_screening_id.eq(screening_id))
+                                .filter(seat_holds::user_id.eq(user_id))
+                                .filter(seat_holds::seat_row.eq(seat.seat_row))
+                                .filter(seat_holds::seat_column.eq(seat.seat_column)),
+                        )
+                        .execute(conn)?;
+                    }
+
+                    let held = seat_holds::table
+                        .filter(seat_holds::theatre_screening_id.eq(screening_id))
+                        .filter(seat_holds::user_id.eq(user_id))
+                        .count()
+                        .get_result::<i64>(conn)?;
+                    let owned = screening_tickets_owned(conn, screening_id, user_id)?;
+                    if owned + held + seats.len() as i64 > MAX_TICKETS_PER_SCREENING {
+                        return Err(TransactionError::TicketLimitExceeded);
+                    }
+
+                    let new_holds = seats
+                        .iter()
+                        .map(|seat| CreateSeatHold {
+                            theatre_screening_id: screening_id,
+                            user_id,
+                            seat_row: seat.seat_row,
+                            seat_column: seat.seat_column,
+                            expires_at,
+                        })
+                        .collect::<Vec<_>>();
+
+                    Ok(diesel::insert_into(seat_holds::table)
+                        .values(new_holds)
+                        .returning(SeatHold::as_returning())
+                        .get_results::<SeatHold>(conn)?)
+                })
+            })
+            .await??;
+
+        self.seat_events.publish(
+            screening_id,
+            holds.iter().map(|x| (x.seat_row, x.seat_column)),
+            SeatEventKind::Held,
+        );
+
+        Ok(holds)
+    }
+
+    /// fetches the holds of `user_id` for a screening which haven't expired yet
+    pub async fn get_user_holds(
+        &self,
+        screening_id: uuid::Uuid,
+        user_id: uuid::Uuid,
+    ) -> Result<Vec<SeatHold>, DatabaseError> {
+        let conn = self.pool.get().await?;
+
+        Ok(conn
+            .interact(move |conn| {
+                seat_holds::table
+                    .filter(seat_holds::theatre_screening_id.eq(screening_id))
+                    .filter(seat_holds::user_id.eq(user_id))
+                    .filter(seat_holds::expires_at.gt(Utc::now().naive_utc()))
+                    .order((seat_holds::seat_row, seat_holds::seat_column))
+                    .load(conn)
+            })
+            .await??)
+    }
+
+    /// releases a hold of `user_id`, as long as it's for the given screening of the theatre
+    pub async fn release(
+        &self,
+        theatre_id: uuid::Uuid,
+        screening_id: uuid::Uuid,
+        hold_id: uuid::Uuid,
+        user_id: uuid::Uuid,
+    ) -> Result<(), DatabaseError> {
+        let conn = self.pool.get().await?;
+
+        let released = conn
+            .interact(move |conn| {
+                diesel::delete(
+                    seat_holds::table
+                        .filter(seat_holds::id.eq(hold_id))
+                        .filter(seat_holds::user_id.eq(user_id))
+                        .filter(seat_holds::theatre_screening_id.eq(screening_id))
+                        .filter(
+                            seat_holds::theatre_screening_id.eq_any(
+                                theatre_screenings::table
+                                    .filter(theatre_screenings::theatre_id.eq(theatre_id))
+                                    .select(theatre_screenings::id),
+                            ),
+                        ),
+                )
+                .returning(SeatHold::as_returning())
+                .get_results(conn)
+            })
+            .await??;
+
+        if released.is_empty() {
+            return Err(diesel::result::Error::NotFound.into());
+        }
+
+        self.publish_released(&released);
+
+        Ok(())
+    }
+
+    /// deletes every expired hold, returning how many were released
+    pub async fn release_expired(&self) -> Result<usize, DatabaseError> {
+        let conn = self.pool.get().await?;
+
+        let released = conn
+            .interact(move |conn| {
+                diesel::delete(
+                    seat_holds::table.filter(seat_holds::expires_at.le(Utc::now().naive_utc())),
+                )
+                .returning(SeatHold::as_returning())
+                .get_results(conn)
+            })
+            .await??;
+
+        self.publish_released(&released);
+
+        Ok(released.len())
+    }
+
+    fn publish_released(&self, holds: &[SeatHold]) {
+        for hold in holds {
+            self.seat_events.publish(
+                hold.theatre_screening_id,
+                [(hold.seat_row, hold.seat_column)],
+                SeatEventKind::Released,
+            );
+        }
+    }
+}
@@ -1,11 +1,50 @@
-use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use std::collections::HashSet;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, SubsecRound, Utc};
 use deadpool_diesel::postgres::Pool;
+use diesel::connection::{AnsiTransactionManager, TransactionManager};
 use diesel::{dsl::count_distinct, pg::Pg, prelude::*};
 use rayon::prelude::*;
 
-use super::DatabaseError;
+use lettre::Message;
+
+use super::gift_card::{generate_gift_card_code, record_gift_card_entry};
+use super::loyalty::{return_loyalty_redemptions, sync_loyalty_points};
+use super::membership::extend_membership_subscriptions;
+use super::seat_event::SeatEventBus;
+use super::{escape_html, notification_mail, DatabaseError, MailBuildError, TransactionError};
 use crate::schema::*;
-use crate::{model::*, services::user::TicketResource};
+use crate::vars::{ticket_entry_grace_minutes, ticket_exit_grace_minutes};
+use crate::{
+    model::*,
+    services::user::{cancel_emptied_orders, void_pending_transfers, TicketResource},
+};
+
+pub const DEFAULT_TICKET_ENTRY_GRACE_MINUTES: i64 = 30;
+pub const DEFAULT_TICKET_EXIT_GRACE_MINUTES: i64 = 15;
+/// how far ahead of the server's clock a checker device's clock may run
+/// before its scans are considered to come from the future
+pub const MAX_SCAN_CLOCK_SKEW_SECONDS: i64 = 120;
+
+/// how long before a screening starts its tickets can be checked in,
+/// configurable through `TICKET_ENTRY_GRACE_MINUTES`
+pub fn ticket_entry_grace() -> chrono::Duration {
+    chrono::Duration::minutes(
+        ticket_entry_grace_minutes()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(DEFAULT_TICKET_ENTRY_GRACE_MINUTES),
+    )
+}
+
+/// how long after a screening ends its tickets are still accepted,
+/// configurable through `TICKET_EXIT_GRACE_MINUTES`
+pub fn ticket_exit_grace() -> chrono::Duration {
+    chrono::Duration::minutes(
+        ticket_exit_grace_minutes()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(DEFAULT_TICKET_EXIT_GRACE_MINUTES),
+    )
+}
 
 macro_rules! theatres_with_counts {
     () => {
@@ -22,6 +61,9 @@ macro_rules! theatres_with_counts {
                 theatres::location_lon,
                 theatres::logo_image_url,
                 theatres::cover_image_url,
+                theatres::cancellation_cutoff_minutes,
+                theatres::loyalty_points_per_unit,
+                theatres::tax_rate,
                 count_distinct(theatre_screenings::id.nullable()),
                 count_distinct(halls::id.nullable()),
                 count_distinct(tickets::id.nullable()),
@@ -29,14 +71,654 @@ macro_rules! theatres_with_counts {
     };
 }
 
+/// No screening is expected to run for longer than this, which bounds
+/// how far back the overlap check has to look for screenings
+pub const MAX_SCREENING_DURATION_HOURS: i64 = 24;
+
+/// Locks the given hall for the rest of the transaction and fetches the IDs of
+/// the screenings whose running time (movie length + the hall's cleaning buffer)
+/// overlaps with a screening of `movie_id` starting at `starting_time`
+pub(super) fn find_overlapping_screenings(
+    conn: &mut PgConnection,
+    theatre_id: uuid::Uuid,
+    hall_id: uuid::Uuid,
+    movie_id: uuid::Uuid,
+    starting_time: NaiveDateTime,
+    exclude_id: Option<uuid::Uuid>,
+) -> QueryResult<Vec<uuid::Uuid>> {
+    let cleaning_buffer_minutes = halls::table
+        .filter(halls::id.eq(hall_id))
+        .filter(halls::theatre_id.eq(theatre_id))
+        .filter(halls::is_deleted.eq(false))
+        .select(halls::cleaning_buffer_minutes)
+        .for_update()
+        .first::<i32>(conn)?;
+
+    let movie_length = movies::table
+        .filter(movies::id.eq(movie_id))
+        .filter(movies::is_deleted.eq(false))
+        .select(movies::length)
+        .first::<f64>(conn)?;
+
+    let occupied_for = |length: f64| {
+        chrono::Duration::seconds(((length + f64::from(cleaning_buffer_minutes)) * 60.0) as i64)
+    };
+    let ending_time = starting_time + occupied_for(movie_length);
+
+    let mut query = theatre_screenings::table
+        .inner_join(movies::table)
+        .filter(theatre_screenings::hall_id.eq(hall_id))
+        .filter(theatre_screenings::is_deleted.eq(false))
+        .filter(theatre_screenings::status.ne(ScreeningStatus::Cancelled))
+        .filter(theatre_screenings::starting_time.lt(ending_time))
+        .filter(
+            theatre_screenings::starting_time
+                .gt(starting_time - chrono::Duration::hours(MAX_SCREENING_DURATION_HOURS)),
+        )
+        .select((
+            theatre_screenings::id,
+            theatre_screenings::starting_time,
+            movies::length,
+        ))
+        .into_boxed();
+
+    if let Some(exclude_id) = exclude_id {
+        query = query.filter(theatre_screenings::id.ne(exclude_id));
+    }
+
+    Ok(query
+        .load::<(uuid::Uuid, NaiveDateTime, f64)>(conn)?
+        .into_iter()
+        .filter(|(_, other_start, other_length)| {
+            *other_start + occupied_for(*other_length) > starting_time
+        })
+        .map(|(id, _, _)| id)
+        .collect())
+}
+
+/// Inserts a screening for every occurrence of a schedule which doesn't overlap
+/// with anything in the hall, the ones which do are only reported back
+fn insert_schedule_occurrences(
+    conn: &mut PgConnection,
+    theatre_id: uuid::Uuid,
+    schedule_id: Option<uuid::Uuid>,
+    schedule: &FormScreeningSchedule,
+) -> QueryResult<(Vec<ScheduledScreening>, Vec<TheatreScreening>)> {
+    let mut planned = vec![];
+    let mut inserted: Vec<TheatreScreening> = vec![];
+
+    for starting_time in schedule.occurrences() {
+        let overlapping = find_overlapping_screenings(
+            conn,
+            theatre_id,
+            schedule.hall_id,
+            schedule.movie_id,
+            starting_time,
+            None,
+        )?;
+
+        if overlapping.is_empty() {
+            inserted.push(
+                diesel::insert_into(theatre_screenings::table)
+                    .values(CreateTheatreScreening {
+                        movie_id: schedule.movie_id,
+                        theatre_id,
+                        hall_id: schedule.hall_id,
+                        subtitles_language_id: schedule.subtitles_language_id,
+                        audio_language_id: schedule.audio_language_id,
+                        starting_time,
+                        is_3d: schedule.is_3d,
+                        status: schedule.status,
+                        schedule_id,
+                    })
+                    .returning(TheatreScreening::as_returning())
+                    .get_result(conn)?,
+            );
+        }
+
+        let (own, existing): (Vec<_>, Vec<_>) = overlapping
+            .into_iter()
+            .partition(|id| inserted.iter().any(|x| x.id == *id));
+
+        planned.push(ScheduledScreening {
+            starting_time,
+            conflicting_screening_ids: existing,
+            overlaps_schedule: !own.is_empty(),
+        });
+    }
+
+    Ok((planned, inserted))
+}
+
+/// A cancelled screening along with the tickets it invalidated
+/// and the refunds owed to their owners
+pub struct ScreeningCancellation {
+    pub screening: TheatreScreening,
+    pub movie_name: String,
+    /// the cancelled tickets, free ones (allowance, loyalty points) have no refund
+    pub refunds: Vec<(User, Ticket, Option<Refund>)>,
+    /// seats of the invalidated tickets and of holds which were let go
+    pub released_seats: Vec<(i32, i32)>,
+}
+
+impl ScreeningCancellation {
+    /// builds an email for every owner of a cancelled ticket
+    pub fn notification_mails(&self) -> Result<Vec<Message>, MailBuildError> {
+        let mut owners = self.refunds.iter().map(|x| &x.0).collect::<Vec<_>>();
+        owners.sort_by_key(|x| x.id);
+        owners.dedup_by_key(|x| x.id);
+
+        owners
+            .into_iter()
+            .map(|owner| {
+                let tickets = self
+                    .refunds
+                    .iter()
+                    .filter(|(user, _, _)| user.id == owner.id)
+                    .map(|(_, ticket, refund)| {
+                        let refunded = match refund {
+                            Some(refund) => format!(": {} refunded", refund.amount()?),
+                            None => String::new(),
+                        };
+                        Ok(format!(
+                            "<li>Row {}, seat {}{}</li>",
+                            ticket.seat_row + 1,
+                            ticket.seat_column + 1,
+                            refunded
+                        ))
+                    })
+                    .collect::<Result<String, MailBuildError>>()?;
+
+                let body = format!(
+                    "
+    <h1>Your screening has been cancelled</h1>
+    <p>Hi {}, unfortunately the screening of {} on {} has been cancelled.</p>
+    <p>The following tickets are no longer valid, paid ones will be refunded:</p>
+    <ul>{}</ul>
+    ",
+                    escape_html(&owner.first_name),
+                    escape_html(&self.movie_name),
+                    self.screening.starting_time.format("%d.%m.%Y %H:%M"),
+                    tickets
+                );
+
+                notification_mail(&owner.email, "Screening cancelled", body)
+            })
+            .collect()
+    }
+}
+
+/// Number of entries of a ticket which haven't been undone,
+/// the ticket is used as long as there is at least one
+pub(super) fn standing_entries(history: &[TicketCheckIn]) -> usize {
+    history.iter().fold(0, |entries, x| match x.action {
+        CheckInAction::Undo => entries.saturating_sub(1),
+        CheckInAction::Enter | CheckInAction::ReEnter => entries + 1,
+    })
+}
+
+/// The action a new entry of a ticket is recorded as
+pub(super) fn entry_action(history: &[TicketCheckIn]) -> CheckInAction {
+    if standing_entries(history) > 0 {
+        CheckInAction::ReEnter
+    } else {
+        CheckInAction::Enter
+    }
+}
+
+/// Fetches the check-in history of a ticket, oldest first
+pub(super) fn ticket_history(
+    conn: &mut PgConnection,
+    ticket: &Ticket,
+) -> QueryResult<Vec<TicketCheckIn>> {
+    TicketCheckIn::belonging_to(ticket)
+        .order((ticket_check_ins::scanned_at, ticket_check_ins::synced_at))
+        .load(conn)
+}
+
+/// Records a check-in event of a (locked) ticket and updates the `used` state
+/// derived from its history along with the owner's loyalty points for it,
+/// returning the event along with the new state
+pub(super) fn record_check_in(
+    conn: &mut PgConnection,
+    history: &[TicketCheckIn],
+    check_in: CreateTicketCheckIn,
+) -> QueryResult<(TicketCheckIn, bool)> {
+    let used = match check_in.action {
+        CheckInAction::Undo => standing_entries(history) > 1,
+        CheckInAction::Enter | CheckInAction::ReEnter => true,
+    };
+
+    let check_in = diesel::insert_into(ticket_check_ins::table)
+        .values(check_in)
+        .returning(TicketCheckIn::as_returning())
+        .get_result(conn)?;
+
+    diesel::update(tickets::table.find(check_in.ticket_id))
+        .set(tickets::used.eq(used))
+        .execute(conn)?;
+    sync_loyalty_points(conn, check_in.ticket_id, used)?;
+
+    Ok((check_in, used))
+}
+
+/// Applies a single scan uploaded by a checker device, recording it
+/// as a check-in unless it clashes with the state of the ticket
+fn apply_ticket_scan(
+    conn: &mut PgConnection,
+    theatre_id: uuid::Uuid,
+    checker_user_id: uuid::Uuid,
+    scan: &TicketScan,
+) -> QueryResult<(TicketScanOutcome, Option<TicketCheckIn>)> {
+    let Some(ticket) = tickets::table
+        .inner_join(theatre_screenings::table)
+        .filter(theatre_screenings::theatre_id.eq(theatre_id))
+        .filter(tickets::id.eq(scan.ticket_id))
+        .select(Ticket::as_select())
+        .for_update()
+        .first(conn)
+        .optional()?
+    else {
+        return Ok((TicketScanOutcome::NotFound, None));
+    };
+
+    let history = ticket_history(conn, &ticket)?;
+
+    if let Some(same) = history.iter().find(|x| {
+        x.device_id.as_deref() == Some(scan.device_id.as_str()) && x.scanned_at == scan.scanned_at
+    }) {
+        return Ok((TicketScanOutcome::AlreadyApplied, Some(same.clone())));
+    }
+
+    if ticket.is_cancelled {
+        return Ok((TicketScanOutcome::Cancelled, None));
+    }
+
+    if !scan.is_current(&ticket) {
+        return Ok((TicketScanOutcome::Rejected, None));
+    }
+
+    let latest_scanned_at =
+        Utc::now().naive_utc() + chrono::Duration::seconds(MAX_SCAN_CLOCK_SKEW_SECONDS);
+    if scan.scanned_at < ticket.valid_from
+        || scan.scanned_at > ticket.expires_at
+        || scan.scanned_at > latest_scanned_at
+    {
+        return Ok((TicketScanOutcome::OutsideValidity, None));
+    }
+
+    let mut check_in = CreateTicketCheckIn {
+        ticket_id: ticket.id,
+        checker_user_id,
+        device_id: Some(scan.device_id.clone()),
+        scanned_at: scan.scanned_at,
+        action: entry_action(&history),
+    };
+
+    if check_in.action == CheckInAction::ReEnter {
+        let last_entry = history
+            .iter()
+            .rev()
+            .find(|x| x.action != CheckInAction::Undo)
+            .cloned();
+
+        if last_entry.as_ref().and_then(|x| x.device_id.as_ref()) != check_in.device_id.as_ref() {
+            return Ok((TicketScanOutcome::ScannedElsewhere, last_entry));
+        }
+
+        let (check_in, _) = record_check_in(conn, &history, check_in)?;
+        return Ok((TicketScanOutcome::Rescanned, Some(check_in)));
+    }
+
+    check_in.action = CheckInAction::Enter;
+    let (check_in, _) = record_check_in(conn, &history, check_in)?;
+
+    Ok((TicketScanOutcome::Applied, Some(check_in)))
+}
+
+/// Cancels a screening, invalidating its tickets and recording a refund for each paid one,
+/// the points free ones were redeemed for are put back on their owners' accounts
+fn cancel_screening(
+    conn: &mut PgConnection,
+    theatre_id: uuid::Uuid,
+    screening_id: uuid::Uuid,
+) -> Result<ScreeningCancellation, TransactionError> {
+    let status = theatre_screenings::table
+        .filter(theatre_screenings::id.eq(screening_id))
+        .filter(theatre_screenings::theatre_id.eq(theatre_id))
+        .filter(theatre_screenings::is_deleted.eq(false))
+        .select(theatre_screenings::status)
+        .for_update()
+        .first::<ScreeningStatus>(conn)?;
+
+    if !status.can_transition_to(ScreeningStatus::Cancelled) {
+        return Err(TransactionError::Invalid);
+    }
+
+    let screening = diesel::update(theatre_screenings::table.find(screening_id))
+        .set(theatre_screenings::status.eq(ScreeningStatus::Cancelled))
+        .returning(TheatreScreening::as_returning())
+        .get_result(conn)?;
+
+    let movie_name = movies::table
+        .find(screening.movie_id)
+        .select(movies::name)
+        .first::<String>(conn)?;
+
+    let mut released_seats =
+        diesel::delete(seat_holds::table.filter(seat_holds::theatre_screening_id.eq(screening_id)))
+            .returning((seat_holds::seat_row, seat_holds::seat_column))
+            .get_results::<(i32, i32)>(conn)?;
+
+    let cancelled_ids = diesel::update(
+        tickets::table
+            .filter(tickets::theatre_screening_id.eq(screening_id))
+            .filter(tickets::is_cancelled.eq(false)),
+    )
+    .set(tickets::is_cancelled.eq(true))
+    .returning(tickets::id)
+    .get_results::<uuid::Uuid>(conn)?;
+
+    void_pending_transfers(conn, &cancelled_ids)?;
+    return_loyalty_redemptions(conn, &cancelled_ids)?;
+
+    let cancelled = tickets::table
+        .inner_join(users::table)
+        .filter(tickets::id.eq_any(cancelled_ids))
+        .select((Ticket::as_select(), User::as_select()))
+        .load::<(Ticket, User)>(conn)?;
+    released_seats.extend(cancelled.iter().map(|(x, _)| (x.seat_row, x.seat_column)));
+
+    let order_ids = cancelled
+        .iter()
+        .filter_map(|(ticket, ..)| ticket.order_id)
+        .collect::<Vec<_>>();
+    // tickets of orders which haven't been paid for have nothing to refund
+    let unpaid_order_ids = orders::table
+        .filter(orders::id.eq_any(&order_ids))
+        .filter(orders::status.eq(OrderStatus::Pending))
+        .select(orders::id)
+        .load::<uuid::Uuid>(conn)?;
+    cancel_emptied_orders(conn, &order_ids)?;
+
+    let mut refunds = vec![];
+
+    for (ticket, owner) in cancelled {
+        if ticket
+            .order_id
+            .is_some_and(|x| unpaid_order_ids.contains(&x))
+        {
+            continue;
+        }
+
+        // free tickets have nothing to refund, but their owners are still notified
+        if ticket.price == 0 {
+            refunds.push((owner, ticket, None));
+            continue;
+        }
+
+        let refund = diesel::insert_into(refunds::table)
+            .values(CreateRefund {
+                ticket_id: ticket.id,
+                user_id: owner.id,
+                amount: ticket.price,
+                currency: ticket.currency.clone(),
+                reason: RefundReason::ScreeningCancelled,
+            })
+            .returning(Refund::as_returning())
+            .get_result(conn)?;
+
+        refunds.push((owner, ticket, Some(refund)));
+    }
+
+    Ok(ScreeningCancellation {
+        screening,
+        movie_name,
+        refunds,
+        released_seats,
+    })
+}
+
+/// Computes the window (valid from, expires at) in which the tickets of a screening
+/// can be checked in, spanning the movie's length plus the grace periods
+pub(super) fn ticket_validity(
+    conn: &mut PgConnection,
+    screening_id: uuid::Uuid,
+) -> QueryResult<(NaiveDateTime, NaiveDateTime)> {
+    let (starting_time, length) = theatre_screenings::table
+        .inner_join(movies::table)
+        .filter(theatre_screenings::id.eq(screening_id))
+        .select((theatre_screenings::starting_time, movies::length))
+        .first::<(NaiveDateTime, f64)>(conn)?;
+
+    let running_time = chrono::Duration::seconds((length * 60.0) as i64);
+
+    Ok((
+        starting_time - ticket_entry_grace(),
+        starting_time + running_time + ticket_exit_grace(),
+    ))
+}
+
+/// Locks the given screening for the rest of the transaction and fetches the seat
+/// layout of the hall it takes place in, failing if the screening isn't on sale
+pub(super) fn lock_screening_seat_data(
+    conn: &mut PgConnection,
+    screening_id: uuid::Uuid,
+) -> Result<SeatData, TransactionError> {
+    let (hall_id, status) = theatre_screenings::table
+        .filter(theatre_screenings::id.eq(screening_id))
+        .filter(theatre_screenings::is_deleted.eq(false))
+        .select((theatre_screenings::hall_id, theatre_screenings::status))
+        .for_update()
+        .first::<(uuid::Uuid, ScreeningStatus)>(conn)?;
+
+    if status != ScreeningStatus::OnSale {
+        return Err(TransactionError::NotOnSale);
+    }
+
+    Ok(halls::table
+        .filter(halls::id.eq(hall_id))
+        .select(halls::seat_data)
+        .first(conn)?)
+}
+
+/// Marks the screening as sold out once every bookable seat has a ticket
+pub(super) fn update_sold_out(
+    conn: &mut PgConnection,
+    screening_id: uuid::Uuid,
+    seat_data: &SeatData,
+) -> QueryResult<()> {
+    let bookable = seat_data
+        .rows
+        .iter()
+        .flatten()
+        .filter(|x| x.is_bookable())
+        .count() as i64;
+
+    let sold = tickets::table
+        .filter(tickets::theatre_screening_id.eq(screening_id))
+        .filter(tickets::is_cancelled.eq(false))
+        .count()
+        .get_result::<i64>(conn)?;
+
+    if sold >= bookable {
+        diesel::update(
+            theatre_screenings::table
+                .filter(theatre_screenings::id.eq(screening_id))
+                .filter(theatre_screenings::status.eq(ScreeningStatus::OnSale)),
+        )
+        .set(theatre_screenings::status.eq(ScreeningStatus::SoldOut))
+        .execute(conn)?;
+    }
+
+    Ok(())
+}
+
+/// Prices a ticket of the given type for the screening, applying the type's matching
+/// pricing rules by ascending priority, the older rule first on ties
+pub(super) fn quote_price(
+    conn: &mut PgConnection,
+    ticket_type: TicketType,
+    screening: &TheatreScreening,
+    seat_kind: Option<SeatKind>,
+) -> Result<PriceQuote, TransactionError> {
+    let rules = PricingRule::belonging_to(&ticket_type)
+        .order((
+            pricing_rules::priority.asc(),
+            pricing_rules::created_at.asc(),
+        ))
+        .select(PricingRule::as_select())
+        .load::<PricingRule>(conn)?;
+
+    let base_price = ticket_type.price().map_err(|_| TransactionError::Invalid)?;
+    let mut price = base_price.clone();
+    let mut steps = vec![];
+
+    for rule in rules
+        .into_iter()
+        .filter(|x| x.matches(screening, seat_kind))
+    {
+        price = rule.apply(&price).map_err(|_| TransactionError::Invalid)?;
+        steps.push(PriceStep {
+            rule,
+            price: price.clone(),
+        });
+    }
+
+    Ok(PriceQuote {
+        ticket_type,
+        seat_kind,
+        base_price,
+        steps,
+        price,
+    })
+}
+
+/// Fetches a ticket type which can be sold for the screening,
+/// failing if it belongs to another theatre or was deleted
+pub(super) fn screening_ticket_type(
+    conn: &mut PgConnection,
+    screening: &TheatreScreening,
+    ticket_type_id: uuid::Uuid,
+) -> Result<TicketType, TransactionError> {
+    ticket_types::table
+        .filter(ticket_types::id.eq(ticket_type_id))
+        .filter(ticket_types::theatre_id.eq(screening.theatre_id))
+        .filter(ticket_types::is_deleted.eq(false))
+        .select(TicketType::as_select())
+        .first::<TicketType>(conn)
+        .optional()?
+        .ok_or(TransactionError::Invalid)
+}
+
+/// Counts the bookings a promo code is redeemed in, all of them or just the user's.
+/// A booking is an order which wasn't cancelled, or a valid ticket issued outside of one
+pub(super) fn promo_code_uses(
+    conn: &mut PgConnection,
+    promo_code_id: uuid::Uuid,
+    user_id: Option<uuid::Uuid>,
+) -> QueryResult<i64> {
+    let mut order_uses = orders::table
+        .filter(orders::promo_code_id.eq(promo_code_id))
+        .filter(orders::status.ne(OrderStatus::Cancelled))
+        .into_boxed();
+    let mut ticket_uses = tickets::table
+        .filter(tickets::promo_code_id.eq(promo_code_id))
+        .filter(tickets::order_id.is_null())
+        .filter(tickets::is_cancelled.eq(false))
+        .into_boxed();
+
+    if let Some(user_id) = user_id {
+        order_uses = order_uses.filter(orders::user_id.eq(user_id));
+        ticket_uses = ticket_uses.filter(tickets::owner_user_id.eq(user_id));
+    }
+
+    Ok(
+        order_uses.count().get_result::<i64>(conn)?
+            + ticket_uses.count().get_result::<i64>(conn)?,
+    )
+}
+
+/// Locks one of the theatre's promo codes for the rest of the transaction, failing
+/// if there's no such code, it isn't valid right now or its uses have run out
+pub(super) fn lock_promo_code(
+    conn: &mut PgConnection,
+    theatre_id: uuid::Uuid,
+    user_id: uuid::Uuid,
+    code: &str,
+) -> Result<PromoCode, TransactionError> {
+    let Some(promo_code) = promo_codes::table
+        .filter(promo_codes::theatre_id.eq(theatre_id))
+        .filter(promo_codes::code.eq(PromoCode::normalize_code(code)))
+        .filter(promo_codes::is_deleted.eq(false))
+        .select(PromoCode::as_select())
+        .for_update()
+        .first::<PromoCode>(conn)
+        .optional()?
+    else {
+        return Err(TransactionError::Invalid);
+    };
+
+    if !promo_code.is_valid_at(Utc::now().naive_utc()) {
+        return Err(TransactionError::Invalid);
+    }
+
+    if let Some(max_uses) = promo_code.max_uses {
+        if promo_code_uses(conn, promo_code.id, None)? >= max_uses.into() {
+            return Err(TransactionError::Invalid);
+        }
+    }
+
+    if let Some(max_uses) = promo_code.max_uses_per_user {
+        if promo_code_uses(conn, promo_code.id, Some(user_id))? >= max_uses.into() {
+            return Err(TransactionError::Invalid);
+        }
+    }
+
+    Ok(promo_code)
+}
+
+/// Takes a promo code's discount off of the (ticket type, price) pairs of a booking,
+/// returning the discount of each ticket. Fails if the code applies to none of them
+pub(super) fn apply_promo_code(
+    promo_code: &PromoCode,
+    movie_id: uuid::Uuid,
+    prices: &mut [(uuid::Uuid, Money)],
+) -> Result<Vec<i64>, TransactionError> {
+    let applicable = prices
+        .iter()
+        .map(|(ticket_type_id, price)| {
+            promo_code
+                .applies_to(movie_id, *ticket_type_id)
+                .then_some(price)
+        })
+        .collect::<Vec<_>>();
+
+    if applicable.iter().all(Option::is_none) {
+        return Err(TransactionError::Invalid);
+    }
+
+    let discounts = promo_code
+        .discounts(&applicable)
+        .map_err(|_| TransactionError::Invalid)?;
+
+    for ((_, price), discount) in prices.iter_mut().zip(discounts.iter()) {
+        *price = Money::new(price.amount() - discount, price.currency())
+            .map_err(|_| TransactionError::Invalid)?;
+    }
+
+    Ok(discounts)
+}
+
 #[derive(Clone)]
 pub struct TheatreService {
     pool: Pool,
+    seat_events: SeatEventBus,
 }
 
 impl TheatreService {
-    pub fn new(pool: Pool) -> Self {
-        Self { pool }
+    pub fn new(pool: Pool, seat_events: SeatEventBus) -> Self {
+        Self { pool, seat_events }
     }
 
     pub async fn create(&self, theatre: FormTheatre) -> Result<TheatreResource, DatabaseError> {
@@ -56,6 +738,7 @@ impl TheatreService {
         Ok(TheatreResource {
             theatre,
             pool: self.pool.clone(),
+            seat_events: self.seat_events.clone(),
         })
     }
 
@@ -80,6 +763,7 @@ impl TheatreService {
         Ok(TheatreResource {
             theatre,
             pool: self.pool.clone(),
+            seat_events: self.seat_events.clone(),
         })
     }
 
@@ -117,6 +801,7 @@ impl TheatreService {
         Ok(Some(TheatreResource {
             theatre,
             pool: self.pool.clone(),
+            seat_events: self.seat_events.clone(),
         }))
     }
 
@@ -184,6 +869,7 @@ impl TheatreService {
 pub struct TheatreResource {
     theatre: Theatre,
     pool: Pool,
+    seat_events: SeatEventBus,
 }
 
 impl TheatreResource {
@@ -344,6 +1030,545 @@ impl TheatreResource {
         Ok(())
     }
 
+    /// pricing rules of all of the theatre's ticket types
+    pub async fn get_pricing_rules(&self) -> Result<Vec<PricingRule>, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let theatre_id = self.theatre.id;
+
+        Ok(conn
+            .interact(move |conn| {
+                pricing_rules::table
+                    .inner_join(ticket_types::table)
+                    .filter(ticket_types::theatre_id.eq(theatre_id))
+                    .filter(ticket_types::is_deleted.eq(false))
+                    .order((
+                        pricing_rules::ticket_type_id,
+                        pricing_rules::priority.asc(),
+                        pricing_rules::created_at.asc(),
+                    ))
+                    .select(PricingRule::as_select())
+                    .load(conn)
+            })
+            .await??)
+    }
+
+    /// the ticket type and screening of the rule have to belong to the theatre
+    pub async fn create_pricing_rule(
+        &self,
+        new_rule: FormPricingRule,
+    ) -> Result<PricingRule, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let theatre_id = self.theatre.id;
+        let new_rule = CreatePricingRule::from_form(new_rule);
+
+        Ok(conn
+            .interact(move |conn| {
+                let ticket_type_belongs = diesel::select(diesel::dsl::exists(
+                    ticket_types::table
+                        .filter(ticket_types::id.eq(new_rule.ticket_type_id))
+                        .filter(ticket_types::theatre_id.eq(theatre_id))
+                        .filter(ticket_types::is_deleted.eq(false)),
+                ))
+                .get_result::<bool>(conn)?;
+
+                let screening_belongs = match new_rule.theatre_screening_id {
+                    Some(screening_id) => diesel::select(diesel::dsl::exists(
+                        theatre_screenings::table
+                            .filter(theatre_screenings::id.eq(screening_id))
+                            .filter(theatre_screenings::theatre_id.eq(theatre_id))
+                            .filter(theatre_screenings::is_deleted.eq(false)),
+                    ))
+                    .get_result::<bool>(conn)?,
+                    None => true,
+                };
+
+                if !ticket_type_belongs || !screening_belongs {
+                    return Err(TransactionError::Invalid);
+                }
+
+                Ok(diesel::insert_into(pricing_rules::table)
+                    .values(&new_rule)
+                    .returning(PricingRule::as_returning())
+                    .get_result(conn)?)
+            })
+            .await??)
+    }
+
+    pub async fn delete_pricing_rule(&self, id_: uuid::Uuid) -> Result<(), DatabaseError> {
+        let conn = self.pool.get().await?;
+        let theatre_id = self.theatre.id;
+
+        conn.interact(move |conn| {
+            diesel::delete(
+                pricing_rules::table
+                    .filter(pricing_rules::id.eq(id_))
+                    .filter(
+                        pricing_rules::ticket_type_id.eq_any(
+                            ticket_types::table
+                                .filter(ticket_types::theatre_id.eq(theatre_id))
+                                .select(ticket_types::id),
+                        ),
+                    ),
+            )
+            .execute(conn)
+        })
+        .await??;
+
+        Ok(())
+    }
+
+    /// the theatre's promo codes along with how many times they're redeemed, newest first
+    pub async fn get_promo_codes(&self) -> Result<Vec<ExtendedPromoCode>, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let theatre = self.theatre.clone();
+
+        Ok(conn
+            .interact(move |conn| {
+                let promo_codes = PromoCode::belonging_to(&theatre)
+                    .filter(promo_codes::is_deleted.eq(false))
+                    .order(promo_codes::created_at.desc())
+                    .select(PromoCode::as_select())
+                    .load::<PromoCode>(conn)?;
+
+                promo_codes
+                    .into_iter()
+                    .map(|promo_code| {
+                        Ok(ExtendedPromoCode {
+                            uses: promo_code_uses(conn, promo_code.id, None)?,
+                            promo_code,
+                        })
+                    })
+                    .collect::<QueryResult<Vec<_>>>()
+            })
+            .await??)
+    }
+
+    /// the movies the code is restricted to have to exist and its ticket types have to
+    /// belong to the theatre, codes are unique among the theatre's (case insensitively)
+    pub async fn create_promo_code(
+        &self,
+        new_promo_code: FormPromoCode,
+    ) -> Result<PromoCode, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let new_promo_code = CreatePromoCode::from_form(new_promo_code, self.theatre.id);
+
+        Ok(conn
+            .interact(move |conn| {
+                let movie_count = movies::table
+                    .filter(movies::id.eq_any(&new_promo_code.movie_ids))
+                    .count()
+                    .get_result::<i64>(conn)?;
+
+                let ticket_type_count = ticket_types::table
+                    .filter(ticket_types::id.eq_any(&new_promo_code.ticket_type_ids))
+                    .filter(ticket_types::theatre_id.eq(new_promo_code.theatre_id))
+                    .filter(ticket_types::is_deleted.eq(false))
+                    .count()
+                    .get_result::<i64>(conn)?;
+
+                if movie_count != new_promo_code.movie_ids.len() as i64
+                    || ticket_type_count != new_promo_code.ticket_type_ids.len() as i64
+                {
+                    return Err(TransactionError::Invalid);
+                }
+
+                Ok(diesel::insert_into(promo_codes::table)
+                    .values(&new_promo_code)
+                    .returning(PromoCode::as_returning())
+                    .get_result(conn)?)
+            })
+            .await??)
+    }
+
+    /// tickets and orders already booked with the code keep their discount
+    pub async fn delete_promo_code(&self, id_: uuid::Uuid) -> Result<(), DatabaseError> {
+        let conn = self.pool.get().await?;
+        let theatre_id = self.theatre.id;
+
+        conn.interact(move |conn| {
+            diesel::update(
+                promo_codes::table
+                    .filter(promo_codes::id.eq(id_))
+                    .filter(promo_codes::theatre_id.eq(theatre_id))
+                    .filter(promo_codes::is_deleted.eq(false)),
+            )
+            .set(promo_codes::is_deleted.eq(true))
+            .execute(conn)
+        })
+        .await??;
+
+        Ok(())
+    }
+
+    /// Issues a gift card loaded with the given amount, recording its purchase in its ledger.
+    /// The returned code is the only time it's available, just its hash is stored
+    pub async fn issue_gift_card(
+        &self,
+        issuer_user_id: uuid::Uuid,
+        new_gift_card: FormGiftCard,
+    ) -> Result<IssuedGiftCard, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let code = generate_gift_card_code()
+            .map_err(|_| DatabaseError::Other("couldn't generate a gift card code".to_owned()))?;
+        let normalized_code = GiftCard::normalize_code(&code);
+        let gift_card = CreateGiftCard {
+            theatre_id: self.theatre.id,
+            code_hash: GiftCard::hash_code(&code),
+            code_suffix: normalized_code[normalized_code.len() - 4..].to_owned(),
+            currency: new_gift_card.currency,
+            balance: 0,
+            issuer_user_id,
+        };
+
+        let gift_card = conn
+            .interact(move |conn| {
+                conn.transaction::<_, TransactionError, _>(|conn| {
+                    let gift_card = diesel::insert_into(gift_cards::table)
+                        .values(gift_card)
+                        .returning(GiftCard::as_returning())
+                        .get_result::<GiftCard>(conn)?;
+
+                    record_gift_card_entry(
+                        conn,
+                        CreateGiftCardEntry {
+                            gift_card_id: gift_card.id,
+                            kind: GiftCardEntryKind::Purchase,
+                            amount: new_gift_card.amount,
+                            order_id: None,
+                            refund_id: None,
+                        },
+                    )?;
+
+                    Ok(gift_cards::table
+                        .find(gift_card.id)
+                        .select(GiftCard::as_select())
+                        .first(conn)?)
+                })
+            })
+            .await??;
+
+        Ok(IssuedGiftCard { gift_card, code })
+    }
+
+    /// the theatre's gift cards, newest first
+    pub async fn get_gift_cards(&self) -> Result<Vec<GiftCard>, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let theatre = self.theatre.clone();
+
+        Ok(conn
+            .interact(move |conn| {
+                GiftCard::belonging_to(&theatre)
+                    .order(gift_cards::created_at.desc())
+                    .select(GiftCard::as_select())
+                    .load(conn)
+            })
+            .await??)
+    }
+
+    /// one of the theatre's gift cards along with its ledger
+    pub async fn get_gift_card(
+        &self,
+        id_: uuid::Uuid,
+    ) -> Result<Option<ExtendedGiftCard>, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let theatre = self.theatre.clone();
+
+        Ok(conn
+            .interact(move |conn| {
+                let Some(gift_card) = GiftCard::belonging_to(&theatre)
+                    .filter(gift_cards::id.eq(id_))
+                    .select(GiftCard::as_select())
+                    .first::<GiftCard>(conn)
+                    .optional()?
+                else {
+                    return QueryResult::Ok(None);
+                };
+
+                let entries = GiftCardEntry::belonging_to(&gift_card)
+                    .order(gift_card_entries::created_at.asc())
+                    .select(GiftCardEntry::as_select())
+                    .load(conn)?;
+
+                Ok(Some(ExtendedGiftCard { gift_card, entries }))
+            })
+            .await??)
+    }
+
+    /// the theatre's membership plans which can still be subscribed to, newest first
+    pub async fn get_membership_plans(&self) -> Result<Vec<MembershipPlan>, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let theatre = self.theatre.clone();
+
+        Ok(conn
+            .interact(move |conn| {
+                MembershipPlan::belonging_to(&theatre)
+                    .filter(membership_plans::is_deleted.eq(false))
+                    .order(membership_plans::created_at.desc())
+                    .select(MembershipPlan::as_select())
+                    .load(conn)
+            })
+            .await??)
+    }
+
+    /// the ticket types the plan covers have to belong to the theatre
+    pub async fn create_membership_plan(
+        &self,
+        new_plan: FormMembershipPlan,
+    ) -> Result<MembershipPlan, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let new_plan = CreateMembershipPlan::from_form(new_plan, self.theatre.id);
+
+        Ok(conn
+            .interact(move |conn| {
+                let ticket_type_count = ticket_types::table
+                    .filter(ticket_types::id.eq_any(&new_plan.ticket_type_ids))
+                    .filter(ticket_types::theatre_id.eq(new_plan.theatre_id))
+                    .filter(ticket_types::is_deleted.eq(false))
+                    .count()
+                    .get_result::<i64>(conn)?;
+
+                if ticket_type_count != new_plan.ticket_type_ids.len() as i64 {
+                    return Err(TransactionError::Invalid);
+                }
+
+                Ok(diesel::insert_into(membership_plans::table)
+                    .values(&new_plan)
+                    .returning(MembershipPlan::as_returning())
+                    .get_result(conn)?)
+            })
+            .await??)
+    }
+
+    /// existing memberships in the plan keep covering bookings, but can't be renewed
+    pub async fn delete_membership_plan(&self, id_: uuid::Uuid) -> Result<(), DatabaseError> {
+        let conn = self.pool.get().await?;
+        let theatre_id = self.theatre.id;
+
+        conn.interact(move |conn| {
+            diesel::update(
+                membership_plans::table
+                    .filter(membership_plans::id.eq(id_))
+                    .filter(membership_plans::theatre_id.eq(theatre_id))
+                    .filter(membership_plans::is_deleted.eq(false)),
+            )
+            .set(membership_plans::is_deleted.eq(true))
+            .execute(conn)
+        })
+        .await??;
+
+        Ok(())
+    }
+
+    /// memberships in the theatre's plans with their billing periods, newest first
+    pub async fn get_membership_subscriptions(
+        &self,
+    ) -> Result<Vec<ExtendedMembershipSubscription>, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let theatre_id = self.theatre.id;
+
+        Ok(conn
+            .interact(move |conn| {
+                let subscriptions = membership_subscriptions::table
+                    .inner_join(membership_plans::table)
+                    .filter(membership_plans::theatre_id.eq(theatre_id))
+                    .order(membership_subscriptions::created_at.desc())
+                    .select((
+                        MembershipSubscription::as_select(),
+                        MembershipPlan::as_select(),
+                    ))
+                    .load::<(MembershipSubscription, MembershipPlan)>(conn)?;
+
+                extend_membership_subscriptions(conn, subscriptions)
+            })
+            .await??)
+    }
+
+    /// Signs a user up for one of the theatre's plans, charging the first billing period
+    /// (starting right away) at the box office
+    pub async fn create_membership_subscription(
+        &self,
+        issuer_user_id: uuid::Uuid,
+        new_subscription: FormMembershipSubscription,
+    ) -> Result<ExtendedMembershipSubscription, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let theatre_id = self.theatre.id;
+
+        Ok(conn
+            .interact(move |conn| {
+                conn.transaction::<_, TransactionError, _>(|conn| {
+                    let Some(plan) = membership_plans::table
+                        .filter(membership_plans::id.eq(new_subscription.membership_plan_id))
+                        .filter(membership_plans::theatre_id.eq(theatre_id))
+                        .filter(membership_plans::is_deleted.eq(false))
+                        .select(MembershipPlan::as_select())
+                        .first::<MembershipPlan>(conn)
+                        .optional()?
+                    else {
+                        return Err(TransactionError::Invalid);
+                    };
+
+                    let user_exists = diesel::select(diesel::dsl::exists(
+                        users::table.filter(users::id.eq(new_subscription.user_id)),
+                    ))
+                    .get_result::<bool>(conn)?;
+                    if !user_exists {
+                        return Err(TransactionError::Invalid);
+                    }
+
+                    let subscription = diesel::insert_into(membership_subscriptions::table)
+                        .values(CreateMembershipSubscription {
+                            membership_plan_id: plan.id,
+                            user_id: new_subscription.user_id,
+                            issuer_user_id,
+                        })
+                        .returning(MembershipSubscription::as_returning())
+                        .get_result::<MembershipSubscription>(conn)?;
+
+                    let starts_at = Utc::now().naive_utc();
+                    diesel::insert_into(membership_periods::table)
+                        .values(CreateMembershipPeriod {
+                            membership_subscription_id: subscription.id,
+                            starts_at,
+                            ends_at: starts_at + chrono::Duration::days(plan.period_days.into()),
+                            price: plan.price,
+                            currency: plan.currency.clone(),
+                        })
+                        .execute(conn)?;
+
+                    Ok(
+                        extend_membership_subscriptions(conn, vec![(subscription, plan)])?
+                            .remove(0),
+                    )
+                })
+            })
+            .await??)
+    }
+
+    /// Charges another billing period of one of the theatre's memberships at the plan's
+    /// current price, it starts when the last paid one ends or right away if that's over.
+    /// `None` if there's no such membership
+    pub async fn renew_membership_subscription(
+        &self,
+        id_: uuid::Uuid,
+    ) -> Result<Option<ExtendedMembershipSubscription>, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let theatre_id = self.theatre.id;
+
+        Ok(conn
+            .interact(move |conn| {
+                conn.transaction::<_, TransactionError, _>(|conn| {
+                    let Some((subscription, plan)) = membership_subscriptions::table
+                        .inner_join(membership_plans::table)
+                        .filter(membership_subscriptions::id.eq(id_))
+                        .filter(membership_plans::theatre_id.eq(theatre_id))
+                        .select((
+                            MembershipSubscription::as_select(),
+                            MembershipPlan::as_select(),
+                        ))
+                        .for_update()
+                        .first::<(MembershipSubscription, MembershipPlan)>(conn)
+                        .optional()?
+                    else {
+                        return Ok(None);
+                    };
+
+                    if subscription.cancelled_at.is_some() || plan.is_deleted {
+                        return Err(TransactionError::Invalid);
+                    }
+
+                    let paid_until = membership_periods::table
+                        .filter(membership_periods::membership_subscription_id.eq(subscription.id))
+                        .select(diesel::dsl::max(membership_periods::ends_at))
+                        .first::<Option<NaiveDateTime>>(conn)?;
+                    let now = Utc::now().naive_utc();
+                    let starts_at = paid_until.map_or(now, |x| x.max(now));
+
+                    diesel::insert_into(membership_periods::table)
+                        .values(CreateMembershipPeriod {
+                            membership_subscription_id: subscription.id,
+                            starts_at,
+                            ends_at: starts_at + chrono::Duration::days(plan.period_days.into()),
+                            price: plan.price,
+                            currency: plan.currency.clone(),
+                        })
+                        .execute(conn)?;
+
+                    Ok(extend_membership_subscriptions(conn, vec![(subscription, plan)])?.pop())
+                })
+            })
+            .await??)
+    }
+
+    /// Stops one of the theatre's memberships from being renewed, the periods already paid
+    /// for keep covering bookings. `None` if there's no such active membership
+    pub async fn cancel_membership_subscription(
+        &self,
+        id_: uuid::Uuid,
+    ) -> Result<Option<MembershipSubscription>, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let theatre_id = self.theatre.id;
+
+        Ok(conn
+            .interact(move |conn| {
+                diesel::update(
+                    membership_subscriptions::table
+                        .filter(membership_subscriptions::id.eq(id_))
+                        .filter(membership_subscriptions::cancelled_at.is_null())
+                        .filter(
+                            membership_subscriptions::membership_plan_id.eq_any(
+                                membership_plans::table
+                                    .filter(membership_plans::theatre_id.eq(theatre_id))
+                                    .select(membership_plans::id),
+                            ),
+                        ),
+                )
+                .set(membership_subscriptions::cancelled_at.eq(diesel::dsl::now))
+                .returning(MembershipSubscription::as_returning())
+                .get_result(conn)
+                .optional()
+            })
+            .await??)
+    }
+
+    /// Prices a ticket for one of the theatre's screenings without booking it, `None`
+    /// if the screening doesn't belong to the theatre. A given seat has to be bookable
+    pub async fn quote_price(
+        &self,
+        screening_id: uuid::Uuid,
+        query: PriceQuoteQuery,
+    ) -> Result<Option<PriceQuote>, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let theatre_id = self.theatre.id;
+
+        Ok(conn
+            .interact(move |conn| {
+                let Some((screening, seat_data)) = theatre_screenings::table
+                    .inner_join(halls::table)
+                    .filter(theatre_screenings::theatre_id.eq(theatre_id))
+                    .filter(theatre_screenings::id.eq(screening_id))
+                    .filter(theatre_screenings::is_deleted.eq(false))
+                    .select((TheatreScreening::as_select(), halls::seat_data))
+                    .first::<(TheatreScreening, SeatData)>(conn)
+                    .optional()?
+                else {
+                    return Ok(None);
+                };
+
+                let seat_kind = match (query.seat_row, query.seat_column) {
+                    (Some(row), Some(column)) if seat_data.is_bookable(row, column) => {
+                        seat_data.get(row, column)
+                    }
+                    (None, None) => None,
+                    _ => return Err(TransactionError::Invalid),
+                };
+
+                let ticket_type = screening_ticket_type(conn, &screening, query.ticket_type_id)?;
+
+                Ok(Some(quote_price(conn, ticket_type, &screening, seat_kind)?))
+            })
+            .await??)
+    }
+
     pub async fn get_ticket_by_id(
         &self,
         tid: uuid::Uuid,
@@ -368,7 +1593,56 @@ impl TheatreResource {
             .map(|x| TicketResource::new(x, self.pool.clone())))
     }
 
-    pub async fn query_tickets(&self, tquery: TicketQuery) -> Result<Vec<Ticket>, DatabaseError> {
+    /// applies scans synced by checker devices in the order they happened,
+    /// scans which were already synced are reported but not applied again
+    pub async fn sync_ticket_scans(
+        &self,
+        checker_user_id: uuid::Uuid,
+        mut scans: Vec<TicketScan>,
+    ) -> Result<Vec<TicketScanResult>, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let theatre_id = self.theatre.id;
+
+        // the database only keeps microseconds, which would break matching resent scans
+        for scan in scans.iter_mut() {
+            scan.scanned_at = scan.scanned_at.trunc_subsecs(6);
+        }
+
+        // scans are applied in the order they happened, but reported in the order they came in
+        let mut scans = scans.into_iter().enumerate().collect::<Vec<_>>();
+        scans.sort_by_key(|(_, scan)| scan.scanned_at);
+
+        let mut results = conn
+            .interact(move |conn| {
+                conn.transaction(|conn| {
+                    scans
+                        .into_iter()
+                        .map(|(index, scan)| {
+                            let (outcome, check_in) =
+                                apply_ticket_scan(conn, theatre_id, checker_user_id, &scan)?;
+
+                            Ok((
+                                index,
+                                TicketScanResult {
+                                    scan,
+                                    outcome,
+                                    check_in,
+                                },
+                            ))
+                        })
+                        .collect::<QueryResult<Vec<_>>>()
+                })
+            })
+            .await??;
+        results.sort_by_key(|(index, _)| *index);
+
+        Ok(results.into_iter().map(|(_, result)| result).collect())
+    }
+
+    pub async fn query_tickets(
+        &self,
+        tquery: TicketQuery,
+    ) -> Result<Vec<ExtendedTicket>, DatabaseError> {
         use crate::schema::*;
 
         let conn = self.pool.get().await?;
@@ -408,7 +1682,19 @@ impl TheatreResource {
                     query = query.filter(theatre_screenings::movie_id.eq(movie_id));
                 }
 
-                query.load(conn)
+                let tickets = query.load::<Ticket>(conn)?;
+                let check_ins = TicketCheckIn::belonging_to(&tickets)
+                    .order((ticket_check_ins::scanned_at, ticket_check_ins::synced_at))
+                    .load::<TicketCheckIn>(conn)?
+                    .grouped_by(&tickets);
+
+                QueryResult::Ok(
+                    tickets
+                        .into_iter()
+                        .zip(check_ins)
+                        .map(|(ticket, check_ins)| ExtendedTicket { ticket, check_ins })
+                        .collect::<Vec<_>>(),
+                )
             })
             .await??)
     }
@@ -473,6 +1759,77 @@ impl TheatreResource {
             .cloned())
     }
 
+    /// merges the hall's seat layout with the sold tickets and active holds
+    /// of a screening, `None` if the screening doesn't belong to the theatre
+    pub async fn get_screening_seats(
+        &self,
+        screening_id: uuid::Uuid,
+    ) -> Result<Option<Vec<SeatAvailability>>, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let theatre_id = self.theatre.id;
+
+        Ok(conn
+            .interact(move |conn| {
+                let Some(seat_data) = theatre_screenings::table
+                    .inner_join(halls::table)
+                    .filter(theatre_screenings::theatre_id.eq(theatre_id))
+                    .filter(theatre_screenings::id.eq(screening_id))
+                    .filter(theatre_screenings::is_deleted.eq(false))
+                    .select(halls::seat_data)
+                    .first::<SeatData>(conn)
+                    .optional()?
+                else {
+                    return Ok(None);
+                };
+
+                let sold = tickets::table
+                    .filter(tickets::theatre_screening_id.eq(screening_id))
+                    .filter(tickets::is_cancelled.eq(false))
+                    .select((tickets::seat_row, tickets::seat_column))
+                    .load::<(i32, i32)>(conn)?
+                    .into_iter()
+                    .collect::<HashSet<_>>();
+
+                let held = seat_holds::table
+                    .filter(seat_holds::theatre_screening_id.eq(screening_id))
+                    .filter(seat_holds::expires_at.gt(Utc::now().naive_utc()))
+                    .select((seat_holds::seat_row, seat_holds::seat_column))
+                    .load::<(i32, i32)>(conn)?
+                    .into_iter()
+                    .collect::<HashSet<_>>();
+
+                let mut seats = vec![];
+
+                for (seat_row, row) in (0..).zip(seat_data.rows.iter()) {
+                    for (seat_column, kind) in (0..).zip(row.iter().copied()) {
+                        if kind == SeatKind::Aisle {
+                            continue;
+                        }
+
+                        let status = if sold.contains(&(seat_row, seat_column)) {
+                            SeatStatus::Sold
+                        } else if !kind.is_bookable() {
+                            SeatStatus::Unavailable
+                        } else if held.contains(&(seat_row, seat_column)) {
+                            SeatStatus::Held
+                        } else {
+                            SeatStatus::Free
+                        };
+
+                        seats.push(SeatAvailability {
+                            seat_row,
+                            seat_column,
+                            kind,
+                            status,
+                        });
+                    }
+                }
+
+                Ok::<_, diesel::result::Error>(Some(seats))
+            })
+            .await??)
+    }
+
     pub async fn create_theatre_screening(
         &self,
         new_theatre_screening: FormTheatreScreening,
@@ -483,10 +1840,32 @@ impl TheatreResource {
 
         Ok(conn
             .interact(|conn| {
-                diesel::insert_into(crate::schema::theatre_screenings::table)
-                    .values(new_theatre_screening)
-                    .returning(TheatreScreening::as_returning())
-                    .get_result(conn)
+                conn.transaction::<_, TransactionError, _>(|conn| {
+                    if new_theatre_screening
+                        .status
+                        .is_some_and(|x| !x.is_initial())
+                    {
+                        return Err(TransactionError::Invalid);
+                    }
+
+                    let overlapping = find_overlapping_screenings(
+                        conn,
+                        new_theatre_screening.theatre_id,
+                        new_theatre_screening.hall_id,
+                        new_theatre_screening.movie_id,
+                        new_theatre_screening.starting_time,
+                        None,
+                    )?;
+
+                    if !overlapping.is_empty() {
+                        return Err(TransactionError::Conflict(overlapping));
+                    }
+
+                    Ok(diesel::insert_into(theatre_screenings::table)
+                        .values(new_theatre_screening)
+                        .returning(TheatreScreening::as_returning())
+                        .get_result(conn)?)
+                })
             })
             .await??)
     }
@@ -503,36 +1882,288 @@ impl TheatreResource {
 
         Ok(conn
             .interact(move |conn| {
-                diesel::update(
-                    theatre_screenings
+                conn.transaction::<_, TransactionError, _>(|conn| {
+                    let (current_hall_id, current_status) = theatre_screenings
                         .filter(theatre_id.eq(theatre.id))
                         .filter(id.eq(id_))
-                        .filter(is_deleted.eq(false)),
-                )
-                .set(new_theatre_screening)
-                .returning(TheatreScreening::as_returning())
-                .get_result(conn)
+                        .filter(is_deleted.eq(false))
+                        .select((hall_id, status))
+                        .for_update()
+                        .first::<(uuid::Uuid, ScreeningStatus)>(conn)?;
+
+                    // sold tickets and held seats refer to the seats of the current hall
+                    if new_theatre_screening.hall_id != current_hall_id {
+                        let mut taken = tickets::table
+                            .filter(tickets::theatre_screening_id.eq(id_))
+                            .filter(tickets::is_cancelled.eq(false))
+                            .select(tickets::id)
+                            .load::<uuid::Uuid>(conn)?;
+                        taken.extend(
+                            seat_holds::table
+                                .filter(seat_holds::theatre_screening_id.eq(id_))
+                                .filter(seat_holds::expires_at.gt(Utc::now().naive_utc()))
+                                .select(seat_holds::id)
+                                .load::<uuid::Uuid>(conn)?,
+                        );
+
+                        if !taken.is_empty() {
+                            return Err(TransactionError::Conflict(taken));
+                        }
+                    }
+
+                    // cancelling has to go through `cancel_theatre_screening`,
+                    // so that the sold tickets get refunded
+                    if let Some(next_status) = new_theatre_screening.status {
+                        if next_status != current_status
+                            && (next_status == ScreeningStatus::Cancelled
+                                || !current_status.can_transition_to(next_status))
+                        {
+                            return Err(TransactionError::Invalid);
+                        }
+                    }
+
+                    if current_status != ScreeningStatus::Cancelled {
+                        let overlapping = find_overlapping_screenings(
+                            conn,
+                            theatre.id,
+                            new_theatre_screening.hall_id,
+                            new_theatre_screening.movie_id,
+                            new_theatre_screening.starting_time,
+                            Some(id_),
+                        )?;
+
+                        if !overlapping.is_empty() {
+                            return Err(TransactionError::Conflict(overlapping));
+                        }
+                    }
+
+                    let screening = diesel::update(
+                        theatre_screenings
+                            .filter(theatre_id.eq(theatre.id))
+                            .filter(id.eq(id_))
+                            .filter(is_deleted.eq(false)),
+                    )
+                    .set(new_theatre_screening)
+                    .returning(TheatreScreening::as_returning())
+                    .get_result(conn)?;
+
+                    // the screening might have been moved or had its movie changed
+                    let (valid_from, expires_at) = ticket_validity(conn, screening.id)?;
+
+                    diesel::update(tickets::table.filter(tickets::theatre_screening_id.eq(id_)))
+                        .set((
+                            tickets::valid_from.eq(valid_from),
+                            tickets::expires_at.eq(expires_at),
+                        ))
+                        .execute(conn)?;
+
+                    Ok(screening)
+                })
             })
             .await??)
     }
 
-    pub async fn delete_theatre_screening(&self, id_: uuid::Uuid) -> Result<(), DatabaseError> {
-        use crate::schema::theatre_screenings::dsl::*;
+    pub async fn get_screening_schedules(&self) -> Result<Vec<ScreeningSchedule>, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let theatre = self.theatre.clone();
 
+        Ok(conn
+            .interact(move |conn| {
+                ScreeningSchedule::belonging_to(&theatre)
+                    .filter(screening_schedules::is_deleted.eq(false))
+                    .order(screening_schedules::start_date)
+                    .load(conn)
+            })
+            .await??)
+    }
+
+    /// lists the screenings a schedule would generate along with whatever they
+    /// would overlap with, without saving anything
+    pub async fn preview_screening_schedule(
+        &self,
+        schedule: FormScreeningSchedule,
+    ) -> Result<Vec<ScheduledScreening>, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let theatre_id = self.theatre.id;
+
+        Ok(conn
+            .interact(move |conn| {
+                // the occurrences get inserted so they're checked against each other
+                // exactly the same way as when creating, then everything is rolled back
+                AnsiTransactionManager::begin_transaction(conn)?;
+                let result = insert_schedule_occurrences(conn, theatre_id, None, &schedule);
+                let rollback = AnsiTransactionManager::rollback_transaction(conn);
+
+                let (planned, _) = result?;
+                rollback?;
+
+                QueryResult::Ok(planned)
+            })
+            .await??)
+    }
+
+    /// creates a schedule along with all of its screenings, failing with
+    /// the clashing screening IDs if any of them would overlap with another
+    pub async fn create_screening_schedule(
+        &self,
+        schedule: FormScreeningSchedule,
+    ) -> Result<ExtendedScreeningSchedule, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let theatre_id = self.theatre.id;
+        let new_schedule = CreateScreeningSchedule::from_form(schedule.clone(), theatre_id);
+
+        Ok(conn
+            .interact(move |conn| {
+                conn.transaction::<_, TransactionError, _>(|conn| {
+                    let new_schedule: ScreeningSchedule =
+                        diesel::insert_into(screening_schedules::table)
+                            .values(new_schedule)
+                            .returning(ScreeningSchedule::as_returning())
+                            .get_result(conn)?;
+
+                    let (planned, screenings) = insert_schedule_occurrences(
+                        conn,
+                        theatre_id,
+                        Some(new_schedule.id),
+                        &schedule,
+                    )?;
+
+                    let mut conflicting = planned
+                        .iter()
+                        .flat_map(|x| x.conflicting_screening_ids.iter().copied())
+                        .collect::<Vec<_>>();
+                    conflicting.sort();
+                    conflicting.dedup();
+
+                    if !conflicting.is_empty() {
+                        return Err(TransactionError::Conflict(conflicting));
+                    }
+
+                    if screenings.is_empty() || planned.iter().any(|x| x.overlaps_schedule) {
+                        return Err(TransactionError::Invalid);
+                    }
+
+                    Ok(ExtendedScreeningSchedule {
+                        schedule: new_schedule,
+                        screenings,
+                    })
+                })
+            })
+            .await??)
+    }
+
+    /// cancels a schedule and all of its screenings which haven't started yet
+    pub async fn cancel_screening_schedule(
+        &self,
+        id_: uuid::Uuid,
+    ) -> Result<Vec<ScreeningCancellation>, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let theatre_id = self.theatre.id;
+
+        let cancellations = conn
+            .interact(move |conn| {
+                conn.transaction::<_, TransactionError, _>(|conn| {
+                    let schedule_id = diesel::update(
+                        screening_schedules::table
+                            .filter(screening_schedules::id.eq(id_))
+                            .filter(screening_schedules::theatre_id.eq(theatre_id))
+                            .filter(screening_schedules::is_deleted.eq(false)),
+                    )
+                    .set(screening_schedules::is_deleted.eq(true))
+                    .returning(screening_schedules::id)
+                    .get_result::<uuid::Uuid>(conn)?;
+
+                    let upcoming = theatre_screenings::table
+                        .filter(theatre_screenings::schedule_id.eq(schedule_id))
+                        .filter(theatre_screenings::starting_time.gt(Utc::now().naive_utc()))
+                        .filter(theatre_screenings::is_deleted.eq(false))
+                        .select((theatre_screenings::id, theatre_screenings::status))
+                        .load::<(uuid::Uuid, ScreeningStatus)>(conn)?;
+
+                    upcoming
+                        .into_iter()
+                        .filter(|(_, status)| status.can_transition_to(ScreeningStatus::Cancelled))
+                        .map(|(screening_id, _)| cancel_screening(conn, theatre_id, screening_id))
+                        .collect::<Result<Vec<_>, _>>()
+                })
+            })
+            .await??;
+
+        for cancellation in cancellations.iter() {
+            self.publish_released(cancellation);
+        }
+
+        Ok(cancellations)
+    }
+
+    pub async fn cancel_theatre_screening(
+        &self,
+        id_: uuid::Uuid,
+    ) -> Result<ScreeningCancellation, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let theatre_id = self.theatre.id;
+
+        let cancellation = conn
+            .interact(move |conn| {
+                conn.transaction::<_, TransactionError, _>(|conn| {
+                    cancel_screening(conn, theatre_id, id_)
+                })
+            })
+            .await??;
+
+        self.publish_released(&cancellation);
+
+        Ok(cancellation)
+    }
+
+    fn publish_released(&self, cancellation: &ScreeningCancellation) {
+        self.seat_events.publish(
+            cancellation.screening.id,
+            cancellation.released_seats.iter().copied(),
+            SeatEventKind::Released,
+        );
+    }
+
+    /// deletes a screening which has no valid tickets left,
+    /// screenings with sold tickets have to be cancelled instead
+    pub async fn delete_theatre_screening(&self, id_: uuid::Uuid) -> Result<(), DatabaseError> {
         let conn = self.pool.get().await?;
         let theatre = self.theatre.clone();
 
         conn.interact(move |conn| {
-            diesel::update(
-                theatre_screenings
-                    .filter(id.eq(id_))
-                    .filter(theatre_id.eq(theatre.id))
-                    .filter(is_deleted.eq(false)),
-            )
-            .set(is_deleted.eq(true))
-            .execute(conn)
+            conn.transaction::<_, TransactionError, _>(|conn| {
+                let screening_id = theatre_screenings::table
+                    .filter(theatre_screenings::id.eq(id_))
+                    .filter(theatre_screenings::theatre_id.eq(theatre.id))
+                    .filter(theatre_screenings::is_deleted.eq(false))
+                    .select(theatre_screenings::id)
+                    .for_update()
+                    .first::<uuid::Uuid>(conn)?;
+
+                let sold = tickets::table
+                    .filter(tickets::theatre_screening_id.eq(screening_id))
+                    .filter(tickets::is_cancelled.eq(false))
+                    .select(tickets::id)
+                    .load::<uuid::Uuid>(conn)?;
+
+                if !sold.is_empty() {
+                    return Err(TransactionError::Conflict(sold));
+                }
+
+                diesel::delete(
+                    seat_holds::table.filter(seat_holds::theatre_screening_id.eq(screening_id)),
+                )
+                .execute(conn)?;
+
+                diesel::update(theatre_screenings::table.find(screening_id))
+                    .set(theatre_screenings::is_deleted.eq(true))
+                    .execute(conn)?;
+
+                Ok(())
+            })
         })
         .await??;
+
         Ok(())
     }
 }
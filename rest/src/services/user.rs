@@ -18,9 +18,20 @@ use serde::Serialize;
 use std::str::FromStr;
 use utoipa::{ToResponse, ToSchema};
 
-use super::DatabaseError;
+use super::gift_card::{redeem_gift_card, return_gift_card_redemptions};
+use super::loyalty::{redeem_loyalty_points, return_loyalty_redemptions};
+use super::membership::{check_membership_coverage, extend_membership_subscriptions};
+use super::seat_event::SeatEventBus;
+use super::seat_hold::{screening_tickets_owned, seat_is_free};
+use super::theatre::{
+    apply_promo_code, entry_action, lock_promo_code, lock_screening_seat_data, quote_price,
+    record_check_in, screening_ticket_type, standing_entries, ticket_history, ticket_validity,
+    update_sold_out,
+};
+use super::{escape_html, notification_mail, DatabaseError, MailBuildError, TransactionError};
 use crate::model::*;
 use crate::password;
+use crate::ticket_keys::TICKET_KEYS;
 use crate::vars::{
     gmail_user, jwt_email_secret, jwt_ticket_secret, jwt_user_secret, server_domain, server_port,
     server_protocol,
@@ -32,11 +43,12 @@ pub const USER_TOKEN_EXPIRY_DAYS: i64 = 2;
 #[derive(Clone)]
 pub struct UserService {
     pool: Pool,
+    seat_events: SeatEventBus,
 }
 
 impl UserService {
-    pub fn new(pool: Pool) -> Self {
-        Self { pool }
+    pub fn new(pool: Pool, seat_events: SeatEventBus) -> Self {
+        Self { pool, seat_events }
     }
 
     pub async fn create(&self, user: FormUser) -> Result<UserResource, DatabaseError> {
@@ -65,7 +77,11 @@ impl UserService {
             })
             .await??;
 
-        Ok(UserResource::new(result, self.pool.clone()))
+        Ok(UserResource::new(
+            result,
+            self.pool.clone(),
+            self.seat_events.clone(),
+        ))
     }
 
     pub async fn get_by_email(
@@ -89,7 +105,11 @@ impl UserService {
             .cloned();
 
         match result {
-            Some(user) => Ok(Some(UserResource::new(user, self.pool.clone()))),
+            Some(user) => Ok(Some(UserResource::new(
+                user,
+                self.pool.clone(),
+                self.seat_events.clone(),
+            ))),
             None => Ok(None),
         }
     }
@@ -117,7 +137,11 @@ impl UserService {
             .cloned();
 
         match result {
-            Some(user) => Ok(Some(UserResource::new(user, self.pool.clone()))),
+            Some(user) => Ok(Some(UserResource::new(
+                user,
+                self.pool.clone(),
+                self.seat_events.clone(),
+            ))),
             None => Ok(None),
         }
     }
@@ -140,7 +164,11 @@ impl UserService {
             .cloned();
 
         match result {
-            Some(user) => Ok(Some(UserResource::new(user, self.pool.clone()))),
+            Some(user) => Ok(Some(UserResource::new(
+                user,
+                self.pool.clone(),
+                self.seat_events.clone(),
+            ))),
             None => Ok(None),
         }
     }
@@ -171,11 +199,16 @@ pub struct LoginResponse {
 pub struct UserResource {
     user: User,
     pool: Pool,
+    seat_events: SeatEventBus,
 }
 
 impl UserResource {
-    fn new(user: User, pool: Pool) -> Self {
-        Self { user, pool }
+    fn new(user: User, pool: Pool, seat_events: SeatEventBus) -> Self {
+        Self {
+            user,
+            pool,
+            seat_events,
+        }
     }
 
     pub fn create_jwt(&self) -> Result<LoginResponse, DatabaseError> {
@@ -374,6 +407,66 @@ impl UserResource {
             .collect::<Vec<_>>())
     }
 
+    pub async fn get_ticket_by_id(
+        &self,
+        tid: uuid::Uuid,
+    ) -> Result<Option<TicketResource>, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let cloned_user = self.user.clone();
+
+        Ok(conn
+            .interact(move |conn| {
+                Ticket::belonging_to(&cloned_user)
+                    .filter(crate::schema::tickets::id.eq(tid))
+                    .first(conn)
+                    .optional()
+            })
+            .await??
+            .map(|x| TicketResource::new(x, self.pool.clone())))
+    }
+
+    /// Gets a ticket the user bought, which is one of their orders' tickets or one issued
+    /// to them without an order. It stays theirs to look up after they've transferred it
+    pub async fn get_purchased_ticket_by_id(
+        &self,
+        tid: uuid::Uuid,
+    ) -> Result<Option<TicketResource>, DatabaseError> {
+        use crate::schema::{orders, ticket_transfers, tickets};
+
+        let conn = self.pool.get().await?;
+        let user_id = self.user.id;
+
+        Ok(conn
+            .interact(move |conn| {
+                let Some((ticket, order_user_id)) = tickets::table
+                    .left_join(orders::table)
+                    .filter(tickets::id.eq(tid))
+                    .select((Ticket::as_select(), orders::user_id.nullable()))
+                    .first::<(Ticket, Option<uuid::Uuid>)>(conn)
+                    .optional()?
+                else {
+                    return QueryResult::Ok(None);
+                };
+
+                let purchaser_id = match order_user_id {
+                    Some(x) => x,
+                    // whoever the ticket was issued to made its first transfer
+                    None => ticket_transfers::table
+                        .filter(ticket_transfers::ticket_id.eq(ticket.id))
+                        .filter(ticket_transfers::status.eq(TicketTransferStatus::Accepted))
+                        .order(ticket_transfers::resolved_at.asc())
+                        .select(ticket_transfers::from_user_id)
+                        .first::<uuid::Uuid>(conn)
+                        .optional()?
+                        .unwrap_or(ticket.owner_user_id),
+                };
+
+                Ok((purchaser_id == user_id).then_some(ticket))
+            })
+            .await??
+            .map(|x| TicketResource::new(x, self.pool.clone())))
+    }
+
     pub async fn get_tickets_count(
         &self,
         screening_id: Option<uuid::Uuid>,
@@ -384,6 +477,7 @@ impl UserResource {
         Ok(conn
             .interact(move |conn| {
                 let mut query = Ticket::belonging_to(&cloned_user)
+                    .filter(crate::schema::tickets::is_cancelled.eq(false))
                     .select(diesel::dsl::count(crate::schema::tickets::id))
                     .into_boxed();
 
@@ -398,41 +492,703 @@ impl UserResource {
 
     pub async fn create_ticket(
         &self,
+        theatre_id: uuid::Uuid,
         new_ticket: FormTicket,
         issuer_user_id: uuid::Uuid,
     ) -> Result<TicketResource, DatabaseError> {
         let conn = self.pool.get().await?;
+        let owner_user_id = self.user.id;
 
-        let ticket = CreateTicket {
-            owner_user_id: self.user.id,
-            theatre_screening_id: new_ticket.theatre_screening_id,
-            ticket_type_id: new_ticket.ticket_type_id,
-            issuer_user_id,
-            seat_row: new_ticket.seat_row,
-            seat_column: new_ticket.seat_column,
-        };
+        let result = conn
+            .interact(move |conn| {
+                conn.transaction::<_, TransactionError, _>(|conn| {
+                    let screening_id = new_ticket.theatre_screening_id;
+                    let seat_data = lock_screening_seat_data(conn, screening_id)?;
+                    let (valid_from, expires_at) = ticket_validity(conn, screening_id)?;
+
+                    // staff can only issue tickets for their own theatre's screenings
+                    let screening = crate::schema::theatre_screenings::table
+                        .find(screening_id)
+                        .filter(crate::schema::theatre_screenings::theatre_id.eq(theatre_id))
+                        .select(TheatreScreening::as_select())
+                        .first::<TheatreScreening>(conn)?;
+                    let ticket_type =
+                        screening_ticket_type(conn, &screening, new_ticket.ticket_type_id)?;
+                    let seat_kind = seat_data.get(new_ticket.seat_row, new_ticket.seat_column);
+                    let mut prices = [(
+                        new_ticket.ticket_type_id,
+                        quote_price(conn, ticket_type, &screening, seat_kind)?.price,
+                    )];
+
+                    let promo_code = match &new_ticket.promo_code {
+                        Some(code) => Some(lock_promo_code(
+                            conn,
+                            screening.theatre_id,
+                            owner_user_id,
+                            code,
+                        )?),
+                        None => None,
+                    };
+
+                    // a ticket booked with a membership is free, there's nothing to discount
+                    let discount =
+                        if let Some(subscription_id) = new_ticket.membership_subscription_id {
+                            if promo_code.is_some() {
+                                return Err(TransactionError::Invalid);
+                            }
+
+                            check_membership_coverage(
+                                conn,
+                                owner_user_id,
+                                &screening,
+                                &[(subscription_id, new_ticket.ticket_type_id)],
+                            )?;
+                            prices[0].1 = Money::new(0, prices[0].1.currency())
+                                .map_err(|_| TransactionError::Invalid)?;
+                            0
+                        } else {
+                            match &promo_code {
+                                Some(x) => apply_promo_code(x, screening.movie_id, &mut prices)?[0],
+                                None => 0,
+                            }
+                        };
+                    let [(_, price)] = prices;
+
+                    let ticket = CreateTicket {
+                        owner_user_id,
+                        theatre_screening_id: screening_id,
+                        ticket_type_id: new_ticket.ticket_type_id,
+                        issuer_user_id,
+                        seat_row: new_ticket.seat_row,
+                        seat_column: new_ticket.seat_column,
+                        valid_from,
+                        expires_at,
+                        order_id: None,
+                        price: price.amount(),
+                        currency: price.currency().to_owned(),
+                        promo_code_id: promo_code.map(|x| x.id),
+                        discount,
+                        membership_subscription_id: new_ticket.membership_subscription_id,
+                        tax_rate: theatre_tax_rate(conn, screening.theatre_id)?,
+                    };
+                    let ticket = book_seat(conn, &seat_data, ticket)?;
+
+                    update_sold_out(conn, screening_id, &seat_data)?;
+                    Ok(ticket)
+                })
+            })
+            .await??;
+
+        self.seat_events.publish(
+            result.theatre_screening_id,
+            [(result.seat_row, result.seat_column)],
+            SeatEventKind::Booked,
+        );
+
+        Ok(TicketResource::new(result, self.pool.clone()))
+    }
+
+    /// books several seats of a screening for the user as one order, either all of them or none,
+    /// the order stays pending until it's paid for
+    pub async fn create_order(
+        &self,
+        screening_id: uuid::Uuid,
+        items: Vec<FormOrderTicket>,
+        promo_code: Option<String>,
+        gift_card_code: Option<String>,
+    ) -> Result<ExtendedOrder, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let user_id = self.user.id;
 
         let result = conn
             .interact(move |conn| {
-                diesel::insert_into(Ticket::table())
-                    .values(ticket)
-                    .returning(Ticket::as_returning())
-                    .get_result(conn)
+                conn.transaction::<_, TransactionError, _>(|conn| {
+                    let seat_data = lock_screening_seat_data(conn, screening_id)?;
+                    let order = place_order(
+                        conn,
+                        &seat_data,
+                        user_id,
+                        screening_id,
+                        &items,
+                        promo_code.as_deref(),
+                        gift_card_code.as_deref(),
+                    )?;
+
+                    update_sold_out(conn, screening_id, &seat_data)?;
+                    Ok(order)
+                })
             })
             .await??;
 
+        self.seat_events.publish(
+            screening_id,
+            result.tickets.iter().map(|x| (x.seat_row, x.seat_column)),
+            SeatEventKind::Booked,
+        );
+
+        Ok(result)
+    }
+
+    /// books a seat for the user with loyalty points of the screening's theatre instead of
+    /// money, fails with `Invalid` if the ticket type can't be redeemed or the user doesn't
+    /// have enough points. The ticket is free, so it doesn't earn any points itself
+    pub async fn redeem_loyalty_points(
+        &self,
+        screening_id: uuid::Uuid,
+        redemption: FormLoyaltyRedemption,
+    ) -> Result<TicketResource, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let owner_user_id = self.user.id;
+
+        let result = conn
+            .interact(move |conn| {
+                conn.transaction::<_, TransactionError, _>(|conn| {
+                    let seat_data = lock_screening_seat_data(conn, screening_id)?;
+
+                    // checked under the screening's lock, like orders are
+                    if screening_tickets_owned(conn, screening_id, owner_user_id)? + 1
+                        > MAX_TICKETS_PER_SCREENING
+                    {
+                        return Err(TransactionError::TicketLimitExceeded);
+                    }
+
+                    let (valid_from, expires_at) = ticket_validity(conn, screening_id)?;
+
+                    let screening = crate::schema::theatre_screenings::table
+                        .find(screening_id)
+                        .select(TheatreScreening::as_select())
+                        .first::<TheatreScreening>(conn)?;
+                    let ticket_type =
+                        screening_ticket_type(conn, &screening, redemption.ticket_type_id)?;
+                    let Some(points) = ticket_type.loyalty_points_price else {
+                        return Err(TransactionError::Invalid);
+                    };
+
+                    let ticket = CreateTicket {
+                        owner_user_id,
+                        theatre_screening_id: screening_id,
+                        ticket_type_id: ticket_type.id,
+                        issuer_user_id: owner_user_id,
+                        seat_row: redemption.seat_row,
+                        seat_column: redemption.seat_column,
+                        valid_from,
+                        expires_at,
+                        order_id: None,
+                        price: 0,
+                        currency: ticket_type.currency,
+                        promo_code_id: None,
+                        discount: 0,
+                        membership_subscription_id: None,
+                        tax_rate: theatre_tax_rate(conn, screening.theatre_id)?,
+                    };
+                    let ticket = book_seat(conn, &seat_data, ticket)?;
+                    redeem_loyalty_points(
+                        conn,
+                        owner_user_id,
+                        screening.theatre_id,
+                        points,
+                        ticket.id,
+                    )?;
+
+                    update_sold_out(conn, screening_id, &seat_data)?;
+                    Ok(ticket)
+                })
+            })
+            .await??;
+
+        self.seat_events.publish(
+            result.theatre_screening_id,
+            [(result.seat_row, result.seat_column)],
+            SeatEventKind::Booked,
+        );
+
         Ok(TicketResource::new(result, self.pool.clone()))
     }
 
-    pub async fn delete_ticket(&self, id_: uuid::Uuid) -> Result<(), DatabaseError> {
-        use crate::schema::tickets::dsl::*;
+    /// fetches the user's loyalty points at every theatre along with their ledger
+    pub async fn get_loyalty(&self) -> Result<UserLoyalty, DatabaseError> {
+        use crate::schema::{loyalty_accounts, loyalty_entries};
+
+        let conn = self.pool.get().await?;
+        let user_id = self.user.id;
+
+        Ok(conn
+            .interact(move |conn| {
+                let accounts = loyalty_accounts::table
+                    .filter(loyalty_accounts::user_id.eq(user_id))
+                    .select(LoyaltyAccount::as_select())
+                    .load::<LoyaltyAccount>(conn)?;
+                let entries = loyalty_entries::table
+                    .filter(loyalty_entries::user_id.eq(user_id))
+                    .order(loyalty_entries::created_at.desc())
+                    .select(LoyaltyEntry::as_select())
+                    .load::<LoyaltyEntry>(conn)?;
+
+                QueryResult::Ok(UserLoyalty { accounts, entries })
+            })
+            .await??)
+    }
+
+    /// fetches the user's memberships along with their billing periods, newest first
+    pub async fn get_memberships(
+        &self,
+    ) -> Result<Vec<ExtendedMembershipSubscription>, DatabaseError> {
+        use crate::schema::{membership_plans, membership_subscriptions};
 
         let conn = self.pool.get().await?;
+        let user_id = self.user.id;
+
+        Ok(conn
+            .interact(move |conn| {
+                let subscriptions = membership_subscriptions::table
+                    .inner_join(membership_plans::table)
+                    .filter(membership_subscriptions::user_id.eq(user_id))
+                    .order(membership_subscriptions::created_at.desc())
+                    .select((
+                        MembershipSubscription::as_select(),
+                        MembershipPlan::as_select(),
+                    ))
+                    .load::<(MembershipSubscription, MembershipPlan)>(conn)?;
+
+                extend_membership_subscriptions(conn, subscriptions)
+            })
+            .await??)
+    }
 
-        conn.interact(move |conn| diesel::delete(tickets).filter(id.eq(id_)).execute(conn))
+    /// fetches the user's orders along with their tickets, newest first
+    pub async fn get_orders(&self) -> Result<Vec<ExtendedOrder>, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let cloned_user = self.user.clone();
+
+        Ok(conn
+            .interact(move |conn| {
+                let orders = Order::belonging_to(&cloned_user)
+                    .order(crate::schema::orders::created_at.desc())
+                    .load::<Order>(conn)?;
+                let tickets = Ticket::belonging_to(&orders)
+                    .select(Ticket::as_select())
+                    .load::<Ticket>(conn)?
+                    .grouped_by(&orders);
+                let payments = Payment::belonging_to(&orders)
+                    .select(Payment::as_select())
+                    .load::<Payment>(conn)?
+                    .grouped_by(&orders);
+
+                QueryResult::Ok(
+                    orders
+                        .into_iter()
+                        .zip(tickets)
+                        .zip(payments)
+                        .map(|((order, tickets), payments)| ExtendedOrder {
+                            order,
+                            tickets,
+                            payment: payments.into_iter().next(),
+                        })
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .await??)
+    }
+
+    pub async fn get_order_by_id(
+        &self,
+        oid: uuid::Uuid,
+    ) -> Result<Option<ExtendedOrder>, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let cloned_user = self.user.clone();
+
+        Ok(conn
+            .interact(move |conn| {
+                let Some(order) = Order::belonging_to(&cloned_user)
+                    .filter(crate::schema::orders::id.eq(oid))
+                    .first::<Order>(conn)
+                    .optional()?
+                else {
+                    return QueryResult::Ok(None);
+                };
+
+                let tickets = Ticket::belonging_to(&order)
+                    .select(Ticket::as_select())
+                    .load(conn)?;
+                let payment = Payment::belonging_to(&order)
+                    .select(Payment::as_select())
+                    .first(conn)
+                    .optional()?;
+
+                Ok(Some(ExtendedOrder {
+                    order,
+                    tickets,
+                    payment,
+                }))
+            })
+            .await??)
+    }
+
+    /// converts seats held by the user into tickets of one order, either all of them or none
+    pub async fn confirm_seat_holds(
+        &self,
+        screening_id: uuid::Uuid,
+        holds: Vec<ConfirmSeatHold>,
+        promo_code: Option<String>,
+        gift_card_code: Option<String>,
+    ) -> Result<ExtendedOrder, DatabaseError> {
+        use crate::schema::seat_holds;
+
+        let conn = self.pool.get().await?;
+        let user_id = self.user.id;
+
+        let result = conn
+            .interact(move |conn| {
+                conn.transaction::<_, TransactionError, _>(|conn| {
+                    let seat_data = lock_screening_seat_data(conn, screening_id)?;
+                    let mut items = vec![];
+
+                    for confirmed in holds {
+                        let hold = seat_holds::table
+                            .filter(seat_holds::id.eq(confirmed.seat_hold_id))
+                            .filter(seat_holds::theatre_screening_id.eq(screening_id))
+                            .filter(seat_holds::user_id.eq(user_id))
+                            .filter(seat_holds::expires_at.gt(chrono::Utc::now().naive_utc()))
+                            .select(SeatHold::as_select())
+                            .first(conn)?;
+
+                        items.push(FormOrderTicket {
+                            ticket_type_id: confirmed.ticket_type_id,
+                            seat_row: hold.seat_row,
+                            seat_column: hold.seat_column,
+                            membership_subscription_id: confirmed.membership_subscription_id,
+                        });
+                    }
+
+                    let order = place_order(
+                        conn,
+                        &seat_data,
+                        user_id,
+                        screening_id,
+                        &items,
+                        promo_code.as_deref(),
+                        gift_card_code.as_deref(),
+                    )?;
+
+                    update_sold_out(conn, screening_id, &seat_data)?;
+                    Ok(order)
+                })
+            })
             .await??;
 
-        Ok(())
+        self.seat_events.publish(
+            screening_id,
+            result.tickets.iter().map(|x| (x.seat_row, x.seat_column)),
+            SeatEventKind::Booked,
+        );
+
+        Ok(result)
+    }
+
+    /// cancels one of the user's tickets, refunding it and giving its seat back,
+    /// `ignore_cutoff` lets staff cancel it past the theatre's cancellation cutoff
+    pub async fn cancel_ticket(
+        &self,
+        ticket_id: uuid::Uuid,
+        ignore_cutoff: bool,
+    ) -> Result<TicketCancellation, DatabaseError> {
+        use crate::schema::*;
+
+        let conn = self.pool.get().await?;
+        let owner = self.user.clone();
+
+        let cancellation = conn
+            .interact(move |conn| {
+                conn.transaction::<_, TransactionError, _>(|conn| {
+                    let ticket = Ticket::belonging_to(&owner)
+                        .filter(tickets::id.eq(ticket_id))
+                        .select(Ticket::as_select())
+                        .for_update()
+                        .first(conn)?;
+
+                    if ticket.is_cancelled {
+                        return Err(TransactionError::Cancelled);
+                    }
+
+                    // there's nothing to refund yet, an unpaid order lapses on its own
+                    if ticket.used || awaits_payment(conn, &ticket)? {
+                        return Err(TransactionError::Invalid);
+                    }
+
+                    let (starting_time, movie_name, cutoff_minutes) = theatre_screenings::table
+                        .inner_join(movies::table)
+                        .inner_join(theatres::table)
+                        .filter(theatre_screenings::id.eq(ticket.theatre_screening_id))
+                        .select((
+                            theatre_screenings::starting_time,
+                            movies::name,
+                            theatres::cancellation_cutoff_minutes,
+                        ))
+                        .first::<(chrono::NaiveDateTime, String, i32)>(conn)?;
+
+                    let cutoff = starting_time - chrono::Duration::minutes(cutoff_minutes.into());
+                    if !ignore_cutoff && chrono::Utc::now().naive_utc() > cutoff {
+                        return Err(TransactionError::PastCutoff);
+                    }
+
+                    let ticket = diesel::update(tickets::table.find(ticket.id))
+                        .set(tickets::is_cancelled.eq(true))
+                        .returning(Ticket::as_returning())
+                        .get_result(conn)?;
+
+                    void_pending_transfers(conn, &[ticket.id])?;
+                    return_loyalty_redemptions(conn, &[ticket.id])?;
+                    cancel_emptied_orders(conn, &ticket.order_id.into_iter().collect::<Vec<_>>())?;
+
+                    diesel::update(
+                        theatre_screenings::table
+                            .filter(theatre_screenings::id.eq(ticket.theatre_screening_id))
+                            .filter(theatre_screenings::status.eq(ScreeningStatus::SoldOut)),
+                    )
+                    .set(theatre_screenings::status.eq(ScreeningStatus::OnSale))
+                    .execute(conn)?;
+
+                    // free tickets (membership allowance, loyalty points) have nothing to refund
+                    let refund = if ticket.price == 0 {
+                        None
+                    } else {
+                        Some(
+                            diesel::insert_into(refunds::table)
+                                .values(CreateRefund {
+                                    ticket_id: ticket.id,
+                                    user_id: owner.id,
+                                    amount: ticket.price,
+                                    currency: ticket.currency.clone(),
+                                    reason: RefundReason::TicketCancelled,
+                                })
+                                .returning(Refund::as_returning())
+                                .get_result(conn)?,
+                        )
+                    };
+
+                    Ok(TicketCancellation {
+                        owner,
+                        ticket,
+                        refund,
+                        movie_name,
+                        starting_time,
+                    })
+                })
+            })
+            .await??;
+
+        let ticket = &cancellation.ticket;
+        self.seat_events.publish(
+            ticket.theatre_screening_id,
+            [(ticket.seat_row, ticket.seat_column)],
+            SeatEventKind::Released,
+        );
+
+        Ok(cancellation)
+    }
+
+    /// offers one of the user's tickets to `recipient`, the ticket
+    /// only changes hands once the recipient accepts the offer
+    pub async fn offer_ticket_transfer(
+        &self,
+        ticket_id: uuid::Uuid,
+        recipient: User,
+    ) -> Result<TicketTransferOffer, DatabaseError> {
+        use crate::schema::*;
+
+        let conn = self.pool.get().await?;
+        let sender = self.user.clone();
+
+        Ok(conn
+            .interact(move |conn| {
+                conn.transaction::<_, TransactionError, _>(|conn| {
+                    let ticket = Ticket::belonging_to(&sender)
+                        .filter(tickets::id.eq(ticket_id))
+                        .select(Ticket::as_select())
+                        .for_update()
+                        .first(conn)?;
+
+                    if ticket.is_cancelled {
+                        return Err(TransactionError::Cancelled);
+                    }
+
+                    if ticket.used
+                        || ticket.expires_at < chrono::Utc::now().naive_utc()
+                        || recipient.id == sender.id
+                        || awaits_payment(conn, &ticket)?
+                    {
+                        return Err(TransactionError::Invalid);
+                    }
+
+                    let transfer = diesel::insert_into(ticket_transfers::table)
+                        .values(CreateTicketTransfer {
+                            ticket_id,
+                            from_user_id: sender.id,
+                            to_user_id: recipient.id,
+                        })
+                        .returning(TicketTransfer::as_returning())
+                        .get_result(conn)?;
+
+                    let (movie_name, starting_time) = theatre_screenings::table
+                        .inner_join(movies::table)
+                        .filter(theatre_screenings::id.eq(ticket.theatre_screening_id))
+                        .select((movies::name, theatre_screenings::starting_time))
+                        .first::<(String, chrono::NaiveDateTime)>(conn)?;
+
+                    Ok(TicketTransferOffer {
+                        transfer,
+                        sender,
+                        recipient,
+                        movie_name,
+                        starting_time,
+                    })
+                })
+            })
+            .await??)
+    }
+
+    /// fetches the transfers the user has sent or received, newest first
+    pub async fn get_ticket_transfers(&self) -> Result<Vec<TicketTransfer>, DatabaseError> {
+        use crate::schema::ticket_transfers::dsl::*;
+
+        let conn = self.pool.get().await?;
+        let user_id = self.user.id;
+
+        Ok(conn
+            .interact(move |conn| {
+                ticket_transfers
+                    .filter(from_user_id.eq(user_id).or(to_user_id.eq(user_id)))
+                    .order(created_at.desc())
+                    .load(conn)
+            })
+            .await??)
+    }
+
+    /// accepts a transfer offered to the user, the ticket is reissued
+    /// to them which invalidates the tokens of the previous owner
+    pub async fn accept_ticket_transfer(
+        &self,
+        transfer_id: uuid::Uuid,
+    ) -> Result<TicketResource, DatabaseError> {
+        use crate::schema::*;
+
+        let conn = self.pool.get().await?;
+        let recipient_id = self.user.id;
+
+        let ticket = conn
+            .interact(move |conn| {
+                conn.transaction::<_, TransactionError, _>(|conn| {
+                    let transfer = ticket_transfers::table
+                        .filter(ticket_transfers::id.eq(transfer_id))
+                        .filter(ticket_transfers::to_user_id.eq(recipient_id))
+                        .select(TicketTransfer::as_select())
+                        .for_update()
+                        .first(conn)?;
+
+                    if transfer.status != TicketTransferStatus::Pending {
+                        return Err(TransactionError::Invalid);
+                    }
+
+                    let ticket = tickets::table
+                        .find(transfer.ticket_id)
+                        .select(Ticket::as_select())
+                        .for_update()
+                        .first(conn)?;
+
+                    if ticket.is_cancelled {
+                        return Err(TransactionError::Cancelled);
+                    }
+
+                    let now = chrono::Utc::now().naive_utc();
+
+                    if ticket.used
+                        || ticket.expires_at < now
+                        || ticket.owner_user_id != transfer.from_user_id
+                    {
+                        return Err(TransactionError::Invalid);
+                    }
+
+                    let owned_count = tickets::table
+                        .filter(tickets::owner_user_id.eq(recipient_id))
+                        .filter(tickets::theatre_screening_id.eq(ticket.theatre_screening_id))
+                        .filter(tickets::is_cancelled.eq(false))
+                        .count()
+                        .get_result::<i64>(conn)?;
+
+                    if owned_count >= MAX_TICKETS_PER_SCREENING {
+                        return Err(TransactionError::Invalid);
+                    }
+
+                    diesel::update(ticket_transfers::table.find(transfer.id))
+                        .set((
+                            ticket_transfers::status.eq(TicketTransferStatus::Accepted),
+                            ticket_transfers::resolved_at.eq(now),
+                        ))
+                        .execute(conn)?;
+
+                    Ok(diesel::update(tickets::table.find(ticket.id))
+                        .set((
+                            tickets::owner_user_id.eq(recipient_id),
+                            tickets::issued_at.eq(now),
+                        ))
+                        .returning(Ticket::as_returning())
+                        .get_result(conn)?)
+                })
+            })
+            .await??;
+
+        Ok(TicketResource::new(ticket, self.pool.clone()))
+    }
+
+    /// resolves a pending transfer without moving the ticket, either declined
+    /// by its recipient or cancelled by its sender (`as_sender`)
+    pub async fn reject_ticket_transfer(
+        &self,
+        transfer_id: uuid::Uuid,
+        as_sender: bool,
+    ) -> Result<TicketTransfer, DatabaseError> {
+        use crate::schema::ticket_transfers::dsl::*;
+
+        let conn = self.pool.get().await?;
+        let user_id = self.user.id;
+
+        let new_status = match as_sender {
+            true => TicketTransferStatus::Cancelled,
+            false => TicketTransferStatus::Declined,
+        };
+
+        Ok(conn
+            .interact(move |conn| {
+                conn.transaction::<_, TransactionError, _>(|conn| {
+                    let transfer = ticket_transfers
+                        .find(transfer_id)
+                        .select(TicketTransfer::as_select())
+                        .for_update()
+                        .first(conn)?;
+
+                    let party_id = match as_sender {
+                        true => transfer.from_user_id,
+                        false => transfer.to_user_id,
+                    };
+
+                    if party_id != user_id {
+                        return Err(TransactionError::Query(diesel::result::Error::NotFound));
+                    }
+
+                    if transfer.status != TicketTransferStatus::Pending {
+                        return Err(TransactionError::Invalid);
+                    }
+
+                    Ok(diesel::update(ticket_transfers.find(transfer.id))
+                        .set((
+                            status.eq(new_status),
+                            resolved_at.eq(chrono::Utc::now().naive_utc()),
+                        ))
+                        .returning(TicketTransfer::as_returning())
+                        .get_result(conn)?)
+                })
+            })
+            .await??)
     }
 
     pub async fn get_reviews(&self) -> Result<Vec<ExtendedMovieReview>, DatabaseError> {
@@ -517,6 +1273,386 @@ impl UserResource {
     }
 }
 
+/// The rate of the taxes the theatre currently includes in its ticket prices
+fn theatre_tax_rate(conn: &mut PgConnection, theatre_id: uuid::Uuid) -> QueryResult<i32> {
+    crate::schema::theatres::table
+        .find(theatre_id)
+        .select(crate::schema::theatres::tax_rate)
+        .first(conn)
+}
+
+/// Inserts a ticket for a seat of an already locked screening,
+/// consuming the hold the owner might've had on it
+fn book_seat(
+    conn: &mut PgConnection,
+    seat_data: &SeatData,
+    ticket: CreateTicket,
+) -> Result<Ticket, TransactionError> {
+    use crate::schema::seat_holds;
+
+    let seat = SeatPosition {
+        seat_row: ticket.seat_row,
+        seat_column: ticket.seat_column,
+    };
+
+    if !seat_data.is_bookable(seat.seat_row, seat.seat_column) {
+        return Err(TransactionError::Invalid);
+    }
+
+    if !seat_is_free(
+        conn,
+        ticket.theatre_screening_id,
+        ticket.owner_user_id,
+        seat,
+    )? {
+        return Err(TransactionError::Conflict(vec![]));
+    }
+
+    diesel::delete(
+        seat_holds::table
+            .filter(seat_holds::theatre_screening_id.eq(ticket.theatre_screening_id))
+            .filter(seat_holds::user_id.eq(ticket.owner_user_id))
+            .filter(seat_holds::seat_row.eq(seat.seat_row))
+            .filter(seat_holds::seat_column.eq(seat.seat_column)),
+    )
+    .execute(conn)?;
+
+    Ok(diesel::insert_into(Ticket::table())
+        .values(ticket)
+        .returning(Ticket::as_returning())
+        .get_result(conn)?)
+}
+
+/// Books every seat of an order for `user_id`, pricing each one from its ticket type
+/// and the type's pricing rules, minus the promo code's discount if one is given.
+/// Seats booked with one of the user's memberships are free instead and count towards
+/// its allowance. A given gift card pays for as much of the order as its balance covers.
+/// The screening has to be locked by the caller
+fn place_order(
+    conn: &mut PgConnection,
+    seat_data: &SeatData,
+    user_id: uuid::Uuid,
+    screening_id: uuid::Uuid,
+    items: &[FormOrderTicket],
+    promo_code: Option<&str>,
+    gift_card_code: Option<&str>,
+) -> Result<ExtendedOrder, TransactionError> {
+    use crate::schema::*;
+
+    // checked under the screening's lock, so concurrent orders can't both fit under the cap
+    if screening_tickets_owned(conn, screening_id, user_id)? + items.len() as i64
+        > MAX_TICKETS_PER_SCREENING
+    {
+        return Err(TransactionError::TicketLimitExceeded);
+    }
+
+    let (valid_from, expires_at) = ticket_validity(conn, screening_id)?;
+    let screening = theatre_screenings::table
+        .find(screening_id)
+        .select(TheatreScreening::as_select())
+        .first::<TheatreScreening>(conn)?;
+
+    let mut prices = vec![];
+
+    for item in items {
+        let ticket_type = screening_ticket_type(conn, &screening, item.ticket_type_id)?;
+        let seat_kind = seat_data.get(item.seat_row, item.seat_column);
+        prices.push((
+            item.ticket_type_id,
+            quote_price(conn, ticket_type, &screening, seat_kind)?.price,
+        ));
+    }
+
+    let membership_tickets = items
+        .iter()
+        .filter_map(|x| Some((x.membership_subscription_id?, x.ticket_type_id)))
+        .collect::<Vec<_>>();
+    check_membership_coverage(conn, user_id, &screening, &membership_tickets)?;
+
+    // tickets booked with a membership are free, so the promo code only applies to the rest
+    let mut paid_prices = items
+        .iter()
+        .zip(prices.iter())
+        .filter(|(item, _)| item.membership_subscription_id.is_none())
+        .map(|(_, price)| price.clone())
+        .collect::<Vec<_>>();
+
+    let promo_code = match promo_code {
+        Some(code) => Some(lock_promo_code(conn, screening.theatre_id, user_id, code)?),
+        None => None,
+    };
+    let paid_discounts = match &promo_code {
+        Some(x) => apply_promo_code(x, screening.movie_id, &mut paid_prices)?,
+        None => vec![0; paid_prices.len()],
+    };
+    let mut paid = paid_prices.into_iter().zip(paid_discounts);
+
+    let mut discounts = vec![];
+
+    for (item, (_, price)) in items.iter().zip(prices.iter_mut()) {
+        if item.membership_subscription_id.is_some() {
+            *price = Money::new(0, price.currency()).map_err(|_| TransactionError::Invalid)?;
+            discounts.push(0);
+        } else if let Some(((_, paid_price), discount)) = paid.next() {
+            *price = paid_price;
+            discounts.push(discount);
+        }
+    }
+
+    let mut total_price: Option<Money> = None;
+
+    for (_, price) in prices.iter() {
+        // a purchase is paid for at once, so it can't mix currencies
+        total_price = Some(match total_price {
+            Some(total) => total
+                .checked_add(price)
+                .map_err(|_| TransactionError::Invalid)?,
+            None => price.clone(),
+        });
+    }
+
+    let Some(total_price) = total_price else {
+        return Err(TransactionError::Invalid);
+    };
+
+    let order = diesel::insert_into(orders::table)
+        .values(CreateOrder {
+            user_id,
+            theatre_screening_id: screening_id,
+            // there's nothing to pay for when memberships or discounts cover the whole order
+            status: if total_price.amount() == 0 {
+                OrderStatus::Confirmed
+            } else {
+                OrderStatus::Pending
+            },
+            total_price: total_price.amount(),
+            currency: total_price.currency().to_owned(),
+            promo_code_id: promo_code.as_ref().map(|x| x.id),
+            discount: discounts.iter().sum(),
+        })
+        .returning(Order::as_returning())
+        .get_result(conn)?;
+    let order = match gift_card_code {
+        Some(code) => redeem_gift_card(conn, screening.theatre_id, code, order)?,
+        None => order,
+    };
+
+    let tax_rate = theatre_tax_rate(conn, screening.theatre_id)?;
+    let mut tickets = vec![];
+
+    for ((item, (_, price)), discount) in items.iter().zip(prices).zip(discounts) {
+        let ticket = CreateTicket {
+            owner_user_id: user_id,
+            theatre_screening_id: screening_id,
+            ticket_type_id: item.ticket_type_id,
+            issuer_user_id: user_id,
+            seat_row: item.seat_row,
+            seat_column: item.seat_column,
+            valid_from,
+            expires_at,
+            order_id: Some(order.id),
+            price: price.amount(),
+            currency: price.currency().to_owned(),
+            promo_code_id: order
+                .promo_code_id
+                .filter(|_| item.membership_subscription_id.is_none()),
+            discount,
+            membership_subscription_id: item.membership_subscription_id,
+            tax_rate,
+        };
+
+        tickets.push(book_seat(conn, seat_data, ticket)?);
+    }
+
+    Ok(ExtendedOrder {
+        order,
+        tickets,
+        payment: None,
+    })
+}
+
+/// Whether the ticket belongs to an order which hasn't been paid for yet
+fn awaits_payment(conn: &mut PgConnection, ticket: &Ticket) -> QueryResult<bool> {
+    use crate::schema::orders;
+
+    let Some(order_id) = ticket.order_id else {
+        return Ok(false);
+    };
+
+    let status = orders::table
+        .find(order_id)
+        .select(orders::status)
+        .first::<OrderStatus>(conn)?;
+
+    Ok(status == OrderStatus::Pending)
+}
+
+/// Whether a ticket has been paid for, which tickets issued without an order always are.
+/// Cancelled tickets count as paid when they've been refunded, unlike ones whose
+/// order fell through before it was paid for
+fn is_paid(conn: &mut PgConnection, ticket: &Ticket) -> QueryResult<bool> {
+    use crate::schema::{orders, refunds};
+
+    let Some(order_id) = ticket.order_id else {
+        return Ok(true);
+    };
+
+    let status = orders::table
+        .find(order_id)
+        .select(orders::status)
+        .first::<OrderStatus>(conn)?;
+
+    if status == OrderStatus::Confirmed {
+        return Ok(true);
+    }
+
+    diesel::select(diesel::dsl::exists(
+        refunds::table.filter(refunds::ticket_id.eq(ticket.id)),
+    ))
+    .get_result(conn)
+}
+
+/// Marks the given orders as cancelled once none of their tickets are valid anymore,
+/// payments which haven't gone through by then are cancelled along with them and
+/// what unpaid orders took off of their gift cards is put back
+pub(super) fn cancel_emptied_orders(
+    conn: &mut PgConnection,
+    order_ids: &[uuid::Uuid],
+) -> QueryResult<usize> {
+    use crate::schema::{orders, payments, tickets};
+
+    let unpaid = orders::table
+        .filter(orders::id.eq_any(order_ids))
+        .filter(orders::status.eq(OrderStatus::Pending))
+        .select(orders::id)
+        .load::<uuid::Uuid>(conn)?;
+
+    let cancelled = diesel::update(
+        orders::table
+            .filter(orders::id.eq_any(order_ids))
+            .filter(diesel::dsl::not(diesel::dsl::exists(
+                tickets::table
+                    .filter(tickets::order_id.eq(orders::id.nullable()))
+                    .filter(tickets::is_cancelled.eq(false)),
+            ))),
+    )
+    .set(orders::status.eq(OrderStatus::Cancelled))
+    .returning(orders::id)
+    .get_results::<uuid::Uuid>(conn)?;
+
+    diesel::update(
+        payments::table
+            .filter(payments::order_id.eq_any(&cancelled))
+            .filter(payments::status.eq(PaymentStatus::RequiresConfirmation)),
+    )
+    .set((
+        payments::status.eq(PaymentStatus::Cancelled),
+        payments::updated_at.eq(chrono::Utc::now().naive_utc()),
+    ))
+    .execute(conn)?;
+
+    return_gift_card_redemptions(
+        conn,
+        &cancelled
+            .iter()
+            .filter(|x| unpaid.contains(x))
+            .copied()
+            .collect::<Vec<_>>(),
+    )?;
+
+    Ok(cancelled.len())
+}
+
+/// Cancels the pending transfers of tickets which are no longer valid
+pub(super) fn void_pending_transfers(
+    conn: &mut PgConnection,
+    ticket_ids: &[uuid::Uuid],
+) -> QueryResult<usize> {
+    use crate::schema::ticket_transfers::dsl::*;
+
+    diesel::update(
+        ticket_transfers
+            .filter(ticket_id.eq_any(ticket_ids))
+            .filter(status.eq(TicketTransferStatus::Pending)),
+    )
+    .set((
+        status.eq(TicketTransferStatus::Cancelled),
+        resolved_at.eq(chrono::Utc::now().naive_utc()),
+    ))
+    .execute(conn)
+}
+
+/// A ticket offered to another user, along with what the recipient needs to know about it
+pub struct TicketTransferOffer {
+    pub transfer: TicketTransfer,
+    pub sender: User,
+    pub recipient: User,
+    pub movie_name: String,
+    pub starting_time: chrono::NaiveDateTime,
+}
+
+impl TicketTransferOffer {
+    /// builds the email letting the recipient know about the offer
+    pub fn notification_mails(&self) -> Result<Vec<Message>, MailBuildError> {
+        let body = format!(
+            "
+    <h1>You've been offered a ticket</h1>
+    <p>Hi {}, {} {} would like to give you their ticket for the screening of {} on {}.</p>
+    <p>Accept it in the app to have it issued to you.</p>
+    ",
+            escape_html(&self.recipient.first_name),
+            escape_html(&self.sender.first_name),
+            escape_html(&self.sender.last_name),
+            escape_html(&self.movie_name),
+            self.starting_time.format("%d.%m.%Y %H:%M"),
+        );
+
+        Ok(vec![notification_mail(
+            &self.recipient.email,
+            "Ticket offered to you",
+            body,
+        )?])
+    }
+}
+
+/// A ticket cancelled ahead of its screening along with the refund owed to its owner
+pub struct TicketCancellation {
+    pub owner: User,
+    pub ticket: Ticket,
+    pub refund: Option<Refund>,
+    pub movie_name: String,
+    pub starting_time: chrono::NaiveDateTime,
+}
+
+impl TicketCancellation {
+    /// builds the confirmation email sent to the owner of the ticket
+    pub fn notification_mails(&self) -> Result<Vec<Message>, MailBuildError> {
+        let refund = match &self.refund {
+            Some(refund) => format!("<p>{} will be refunded to you.</p>", refund.amount()?),
+            None => String::new(),
+        };
+        let body = format!(
+            "
+    <h1>Your ticket has been cancelled</h1>
+    <p>Hi {}, your ticket for the screening of {} on {} (row {}, seat {}) has been cancelled.</p>
+    {}
+    ",
+            escape_html(&self.owner.first_name),
+            escape_html(&self.movie_name),
+            self.starting_time.format("%d.%m.%Y %H:%M"),
+            self.ticket.seat_row + 1,
+            self.ticket.seat_column + 1,
+            refund
+        );
+
+        Ok(vec![notification_mail(
+            &self.owner.email,
+            "Ticket cancelled",
+            body,
+        )?])
+    }
+}
+
 #[derive(Clone)]
 pub struct TicketResource {
     ticket: Ticket,
@@ -528,18 +1664,19 @@ impl TicketResource {
         Self { ticket, pool }
     }
 
+    /// signs the ticket with the active ticket key, falling back
+    /// to the shared `JWT_TICKET_SECRET` if no keys are configured
     pub fn create_jwt(&self) -> Result<String, Either<(), jsonwebtoken::errors::Error>> {
+        let claims = TicketClaims::from(&self.ticket);
+
+        if let Some(ticket_keys) = TICKET_KEYS.as_ref() {
+            return ticket_keys.sign(&claims).map_err(Either::Right);
+        }
+
         let Some(jwt_ticket_secret) = jwt_ticket_secret() else {
             return Err(Either::Left(()));
         };
 
-        let claims = JwtClaims {
-            dat: JwtType::Ticket(self.ticket.id),
-            sub: self.ticket.owner_user_id,
-            iat: self.ticket.issued_at.timestamp(),
-            exp: self.ticket.expires_at.timestamp(),
-        };
-
         match jsonwebtoken::encode(
             &Header::default(),
             &claims,
@@ -550,47 +1687,153 @@ impl TicketResource {
         }
     }
 
-    pub fn verify_jwt(jwt: &str) -> Result<uuid::Uuid, DatabaseError> {
+    pub fn verify_jwt(jwt: &str) -> Result<TicketClaims, DatabaseError> {
+        if let Some(ticket_keys) = TICKET_KEYS.as_ref() {
+            return Ok(ticket_keys.verify::<TicketClaims>(jwt)?.claims);
+        }
+
         let Some(jwt_ticket_secret) = jwt_ticket_secret() else {
             return Err(DatabaseError::Other("Problem building an email, because of jwt_ticket_secret var missing".to_string()));
         };
 
-        let data = decode::<JwtClaims>(
+        let data = decode::<TicketClaims>(
             jwt,
             &DecodingKey::from_secret(jwt_ticket_secret.as_bytes()),
             &Validation::new(*JWT_ALGO),
         )?;
 
-        match data.claims.dat {
-            JwtType::Ticket(id) => Ok(id),
-            _ => Err(DatabaseError::Other("Invalid JWT token".to_string())),
-        }
+        Ok(data.claims)
     }
 
-    async fn set_usage(&mut self, state: bool) -> Result<(), DatabaseError> {
-        use crate::schema::tickets::dsl::*;
+    /// whether the ticket belongs to an order which hasn't been paid for yet
+    pub async fn awaits_payment(&self) -> Result<bool, DatabaseError> {
+        let conn = self.pool.get().await?;
+        let ticket = self.ticket.clone();
 
+        Ok(conn
+            .interact(move |conn| awaits_payment(conn, &ticket))
+            .await??)
+    }
+
+    /// whether the ticket has been paid for, cancelled ones included
+    pub async fn is_paid(&self) -> Result<bool, DatabaseError> {
         let conn = self.pool.get().await?;
         let ticket = self.ticket.clone();
 
-        conn.interact(move |conn| {
-            diesel::update(tickets.filter(id.eq(ticket.id)))
-                .set(used.eq(state))
-                .execute(conn)
-        })
-        .await??;
+        Ok(conn.interact(move |conn| is_paid(conn, &ticket)).await??)
+    }
 
-        self.ticket.used = state;
+    /// gathers what's printed on the receipt of the ticket `customer` bought
+    pub async fn receipt(&self, customer: &User) -> Result<TicketReceipt, DatabaseError> {
+        use crate::schema::{halls, movies, theatre_screenings, theatres, ticket_types};
 
-        Ok(())
+        let conn = self.pool.get().await?;
+        let ticket = self.ticket.clone();
+        let customer_name = format!("{} {}", customer.first_name, customer.last_name);
+        let customer_email = customer.email.clone();
+
+        Ok(conn
+            .interact(move |conn| {
+                let (screening, theatre_name, movie_name, hall_name) = theatre_screenings::table
+                    .inner_join(theatres::table)
+                    .inner_join(movies::table)
+                    .inner_join(halls::table)
+                    .filter(theatre_screenings::id.eq(ticket.theatre_screening_id))
+                    .select((
+                        TheatreScreening::as_select(),
+                        theatres::name,
+                        movies::name,
+                        halls::name,
+                    ))
+                    .first::<(TheatreScreening, String, String, String)>(conn)?;
+                let ticket_type = ticket_types::table
+                    .find(ticket.ticket_type_id)
+                    .select(ticket_types::type_)
+                    .first::<String>(conn)?;
+
+                QueryResult::Ok(TicketReceipt {
+                    ticket,
+                    theatre_name,
+                    movie_name,
+                    hall_name,
+                    ticket_type,
+                    starting_time: screening.starting_time,
+                    customer_name,
+                    customer_email,
+                })
+            })
+            .await??)
+    }
+
+    /// records a check-in event, `undo` reverts the latest entry
+    /// and fails with `Invalid` if the ticket hasn't been used
+    async fn check_in(
+        &mut self,
+        checker_user_id: uuid::Uuid,
+        device_id: Option<String>,
+        undo: bool,
+    ) -> Result<TicketCheckIn, DatabaseError> {
+        use crate::schema::tickets;
+
+        let conn = self.pool.get().await?;
+        let ticket_id = self.ticket.id;
+
+        let (check_in, used) = conn
+            .interact(move |conn| {
+                conn.transaction::<_, TransactionError, _>(|conn| {
+                    let ticket = tickets::table
+                        .find(ticket_id)
+                        .select(Ticket::as_select())
+                        .for_update()
+                        .first(conn)?;
+                    let history = ticket_history(conn, &ticket)?;
+
+                    let action = if undo {
+                        if standing_entries(&history) == 0 {
+                            return Err(TransactionError::Invalid);
+                        }
+
+                        CheckInAction::Undo
+                    } else {
+                        entry_action(&history)
+                    };
+
+                    Ok(record_check_in(
+                        conn,
+                        &history,
+                        CreateTicketCheckIn {
+                            ticket_id,
+                            checker_user_id,
+                            device_id,
+                            scanned_at: chrono::Utc::now().naive_utc(),
+                            action,
+                        },
+                    )?)
+                })
+            })
+            .await??;
+
+        self.ticket.used = used;
+
+        Ok(check_in)
     }
 
-    pub async fn mark_as_used(&mut self) -> Result<(), DatabaseError> {
-        self.set_usage(true).await
+    /// checks the ticket in, as a re-entry if it's already been used
+    pub async fn mark_as_used(
+        &mut self,
+        checker_user_id: uuid::Uuid,
+        device_id: Option<String>,
+    ) -> Result<TicketCheckIn, DatabaseError> {
+        self.check_in(checker_user_id, device_id, false).await
     }
 
-    pub async fn mark_as_unused(&mut self) -> Result<(), DatabaseError> {
-        self.set_usage(false).await
+    /// undoes the latest entry of the ticket
+    pub async fn mark_as_unused(
+        &mut self,
+        checker_user_id: uuid::Uuid,
+        device_id: Option<String>,
+    ) -> Result<TicketCheckIn, DatabaseError> {
+        self.check_in(checker_user_id, device_id, true).await
     }
 }
 
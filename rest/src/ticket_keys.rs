@@ -0,0 +1,304 @@
+use std::{fs, path::Path};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{
+    errors::ErrorKind,
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters,
+        EllipticCurveKeyType, Jwk, JwkSet, KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType,
+        PublicKeyUse,
+    },
+    Algorithm, DecodingKey, EncodingKey, Header, TokenData, Validation,
+};
+use lazy_static::lazy_static;
+use ring::{
+    rand::SystemRandom,
+    signature::{self, KeyPair},
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::vars::{jwt_ticket_active_kid, jwt_ticket_keys_dir};
+
+lazy_static! {
+    /// keys loaded from `JWT_TICKET_KEYS_DIR`, `None` when the variable isn't set
+    pub static ref TICKET_KEYS: Option<TicketKeys> = jwt_ticket_keys_dir().map(|dir| {
+        TicketKeys::load(Path::new(&dir), jwt_ticket_active_kid().as_deref())
+            .expect("couldn't load the ticket signing keys")
+    });
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum KeyError {
+    #[error("couldn't read the key directory")]
+    Io(#[from] std::io::Error),
+    #[error("key file isn't valid PEM")]
+    Pem(#[from] pem::PemError),
+    #[error("key {0} is neither an Ed25519 nor a P-256 PKCS#8 private key")]
+    Unsupported(String),
+    #[error("jwt key creation was unsuccessful")]
+    Jwt(#[from] jsonwebtoken::errors::Error),
+    #[error("no ticket signing keys were found")]
+    Empty,
+    #[error("active ticket signing key {0} was not found")]
+    MissingActive(String),
+}
+
+struct SigningKey {
+    kid: String,
+    algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    jwk: Jwk,
+}
+
+impl SigningKey {
+    /// parses a PKCS#8 private key, which is either Ed25519 (EdDSA) or P-256 (ES256)
+    fn from_pkcs8(kid: String, der: &[u8]) -> Result<Self, KeyError> {
+        let (algorithm, encoding_key, parameters) =
+            if let Ok(pair) = signature::Ed25519KeyPair::from_pkcs8_maybe_unchecked(der) {
+                (
+                    Algorithm::EdDSA,
+                    EncodingKey::from_ed_der(der),
+                    AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                        key_type: OctetKeyPairType::OctetKeyPair,
+                        curve: EllipticCurve::Ed25519,
+                        x: URL_SAFE_NO_PAD.encode(pair.public_key()),
+                    }),
+                )
+            } else if let Ok(pair) = signature::EcdsaKeyPair::from_pkcs8(
+                &signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+                der,
+                &SystemRandom::new(),
+            ) {
+                // the public key is an uncompressed point (0x04 || x || y)
+                let point = pair.public_key().as_ref();
+
+                (
+                    Algorithm::ES256,
+                    EncodingKey::from_ec_der(der),
+                    AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+                        key_type: EllipticCurveKeyType::EC,
+                        curve: EllipticCurve::P256,
+                        x: URL_SAFE_NO_PAD.encode(&point[1..33]),
+                        y: URL_SAFE_NO_PAD.encode(&point[33..]),
+                    }),
+                )
+            } else {
+                return Err(KeyError::Unsupported(kid));
+            };
+
+        let jwk = Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(match algorithm {
+                    Algorithm::EdDSA => KeyAlgorithm::EdDSA,
+                    _ => KeyAlgorithm::ES256,
+                }),
+                key_id: Some(kid.clone()),
+                ..Default::default()
+            },
+            algorithm: parameters,
+        };
+
+        Ok(Self {
+            decoding_key: DecodingKey::from_jwk(&jwk)?,
+            kid,
+            algorithm,
+            encoding_key,
+            jwk,
+        })
+    }
+}
+
+/// Key pairs which tickets are signed with. Each key is a `<kid>.pem` file,
+/// new tickets are signed with the active one while the rest are kept
+/// around (and published), so that already issued tickets stay verifiable
+pub struct TicketKeys {
+    keys: Vec<SigningKey>,
+    active: usize,
+}
+
+impl TicketKeys {
+    /// loads every key in `dir`, the active key defaults to the last one by name
+    pub fn load(dir: &Path, active_kid: Option<&str>) -> Result<Self, KeyError> {
+        let mut keys = vec![];
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+
+            if path.extension().and_then(|x| x.to_str()) != Some("pem") {
+                continue;
+            }
+
+            let Some(kid) = path.file_stem().and_then(|x| x.to_str()) else {
+                continue;
+            };
+
+            let pem = pem::parse(fs::read(&path)?)?;
+            keys.push(SigningKey::from_pkcs8(kid.to_owned(), pem.contents())?);
+        }
+
+        keys.sort_by(|a, b| a.kid.cmp(&b.kid));
+
+        let active = match active_kid {
+            Some(kid) => keys
+                .iter()
+                .position(|x| x.kid == kid)
+                .ok_or_else(|| KeyError::MissingActive(kid.to_owned()))?,
+            None => keys.len().checked_sub(1).ok_or(KeyError::Empty)?,
+        };
+
+        Ok(Self { keys, active })
+    }
+
+    pub fn sign<T: Serialize>(&self, claims: &T) -> jsonwebtoken::errors::Result<String> {
+        let key = &self.keys[self.active];
+
+        let mut header = Header::new(key.algorithm);
+        header.kid = Some(key.kid.clone());
+
+        jsonwebtoken::encode(&header, claims, &key.encoding_key)
+    }
+
+    /// verifies a token with the key its `kid` header points to
+    pub fn verify<T: DeserializeOwned>(
+        &self,
+        jwt: &str,
+    ) -> jsonwebtoken::errors::Result<TokenData<T>> {
+        let kid = jsonwebtoken::decode_header(jwt)?.kid;
+        let Some(key) = self.keys.iter().find(|x| Some(&x.kid) == kid.as_ref()) else {
+            return Err(ErrorKind::InvalidToken.into());
+        };
+
+        jsonwebtoken::decode(jwt, &key.decoding_key, &Validation::new(key.algorithm))
+    }
+
+    /// the public halves of all keys, for checker devices to verify tickets offline
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self.keys.iter().map(|x| x.jwk.clone()).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::TicketClaims;
+    use crate::services::user::TicketResource;
+
+    use super::*;
+
+    fn ed25519_pkcs8() -> Vec<u8> {
+        signature::Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+            .unwrap()
+            .as_ref()
+            .to_vec()
+    }
+
+    fn ticket_keys(keys: &[(&str, &[u8])], active: usize) -> TicketKeys {
+        TicketKeys {
+            keys: keys
+                .iter()
+                .map(|(kid, der)| SigningKey::from_pkcs8(kid.to_string(), der).unwrap())
+                .collect(),
+            active,
+        }
+    }
+
+    fn claims() -> TicketClaims {
+        let now = chrono::Utc::now().timestamp();
+
+        TicketClaims {
+            jti: uuid::Uuid::from_u128(1),
+            sub: uuid::Uuid::from_u128(2),
+            theatre_screening_id: uuid::Uuid::from_u128(3),
+            seat_row: 4,
+            seat_column: 5,
+            iat: now,
+            nbf: now,
+            exp: now + 3600,
+        }
+    }
+
+    #[test]
+    fn tokens_are_signed_with_the_active_key() {
+        let (old, new) = (ed25519_pkcs8(), ed25519_pkcs8());
+        let keys = ticket_keys(&[("2024-01", &old), ("2024-02", &new)], 1);
+
+        let jwt = keys.sign(&claims()).unwrap();
+        let header = jsonwebtoken::decode_header(&jwt).unwrap();
+        assert_eq!(header.kid.as_deref(), Some("2024-02"));
+        assert_eq!(header.alg, Algorithm::EdDSA);
+
+        let verified = keys.verify::<TicketClaims>(&jwt).unwrap().claims;
+        assert_eq!(verified.jti, claims().jti);
+        assert_eq!(verified.seat_column, 5);
+
+        // tickets signed before the rotation stay valid
+        let before_rotation = ticket_keys(&[("2024-01", &old)], 0);
+        let jwt = before_rotation.sign(&claims()).unwrap();
+        assert!(keys.verify::<TicketClaims>(&jwt).is_ok());
+    }
+
+    #[test]
+    fn tokens_of_unknown_keys_are_rejected() {
+        let keys = ticket_keys(&[("2024-01", &ed25519_pkcs8())], 0);
+
+        let unknown = ticket_keys(&[("other", &ed25519_pkcs8())], 0);
+        let jwt = unknown.sign(&claims()).unwrap();
+        assert!(keys.verify::<TicketClaims>(&jwt).is_err());
+
+        // a known kid doesn't help a token signed by another key
+        let forged = ticket_keys(&[("2024-01", &ed25519_pkcs8())], 0);
+        let jwt = forged.sign(&claims()).unwrap();
+        assert!(keys.verify::<TicketClaims>(&jwt).is_err());
+    }
+
+    #[test]
+    fn keys_are_loaded_from_pem_files() {
+        let dir = std::env::temp_dir().join(format!("ticket-keys-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for kid in ["2024-01", "2024-02"] {
+            let pem = pem::Pem::new("PRIVATE KEY", ed25519_pkcs8());
+            fs::write(dir.join(format!("{}.pem", kid)), pem::encode(&pem)).unwrap();
+        }
+        fs::write(dir.join("README"), "not a key").unwrap();
+
+        let latest = TicketKeys::load(&dir, None);
+        let pinned = TicketKeys::load(&dir, Some("2024-01"));
+        let missing = TicketKeys::load(&dir, Some("2023-12"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        let latest = latest.unwrap();
+        assert_eq!(latest.jwks().keys.len(), 2);
+        assert_eq!(latest.keys[latest.active].kid, "2024-02");
+
+        let pinned = pinned.unwrap();
+        assert_eq!(pinned.keys[pinned.active].kid, "2024-01");
+
+        assert!(matches!(missing, Err(KeyError::MissingActive(_))));
+    }
+
+    #[test]
+    fn secret_is_used_without_keys() {
+        std::env::remove_var("JWT_TICKET_KEYS_DIR");
+        std::env::set_var("JWT_TICKET_SECRET", "ticket-secret");
+        assert!(TICKET_KEYS.is_none());
+
+        let jwt = jsonwebtoken::encode(
+            &Header::default(),
+            &claims(),
+            &EncodingKey::from_secret(b"ticket-secret"),
+        )
+        .unwrap();
+        assert_eq!(TicketResource::verify_jwt(&jwt).unwrap().jti, claims().jti);
+
+        let other_secret = jsonwebtoken::encode(
+            &Header::default(),
+            &claims(),
+            &EncodingKey::from_secret(b"other-secret"),
+        )
+        .unwrap();
+        assert!(TicketResource::verify_jwt(&other_secret).is_err());
+    }
+}
@@ -38,5 +38,12 @@ envs!(
     gmail_password,
     server_protocol,
     server_domain,
-    server_port
+    server_port,
+    seat_hold_ttl_seconds,
+    ticket_entry_grace_minutes,
+    ticket_exit_grace_minutes,
+    jwt_ticket_keys_dir,
+    jwt_ticket_active_kid,
+    payment_provider,
+    payment_webhook_secret
 );
\ No newline at end of file